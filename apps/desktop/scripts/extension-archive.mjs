@@ -0,0 +1,83 @@
+/**
+ * Packs each bundled extension variant into `<variant>.zip` and removes the
+ * loose files. A single archive installs and code-signs much faster than the
+ * hundreds of files a build can produce; the app unpacks it on extraction.
+ */
+
+import { crc32, deflateRawSync } from 'zlib';
+import { existsSync, readFileSync, readdirSync, rmSync, statSync, writeFileSync } from 'fs';
+import { dirname, join, relative, sep } from 'path';
+import { fileURLToPath } from 'url';
+
+const EXTENSION_DIR = join(dirname(fileURLToPath(import.meta.url)), '..', 'src-tauri', 'resources', 'extension');
+// Safari's is only built on macOS.
+const VARIANTS = ['chrome', 'firefox', 'safari'].filter((variant) => existsSync(join(EXTENSION_DIR, variant)));
+
+// Version 2.0 of the format, written by a Unix system so the file modes in
+// the external attributes are picked up.
+const VERSION_NEEDED = 20;
+const VERSION_MADE_BY = (3 << 8) | VERSION_NEEDED;
+const DEFLATE = 8;
+
+function listFiles(dir) {
+  return readdirSync(dir, { withFileTypes: true }).flatMap((entry) => {
+    const path = join(dir, entry.name);
+    return entry.isDirectory() ? listFiles(path) : [path];
+  });
+}
+
+function zip(dir) {
+  const local = [];
+  const central = [];
+  let offset = 0;
+
+  for (const path of listFiles(dir).sort()) {
+    const name = Buffer.from(relative(dir, path).split(sep).join('/'));
+    const data = readFileSync(path);
+    const compressed = deflateRawSync(data);
+    const crc = crc32(data);
+    const mode = statSync(path).mode & 0o777;
+
+    const header = Buffer.alloc(30);
+    header.writeUInt32LE(0x04034b50, 0);
+    header.writeUInt16LE(VERSION_NEEDED, 4);
+    header.writeUInt16LE(DEFLATE, 8);
+    header.writeUInt32LE(crc, 14);
+    header.writeUInt32LE(compressed.length, 18);
+    header.writeUInt32LE(data.length, 22);
+    header.writeUInt16LE(name.length, 26);
+    local.push(header, name, compressed);
+
+    const entry = Buffer.alloc(46);
+    entry.writeUInt32LE(0x02014b50, 0);
+    entry.writeUInt16LE(VERSION_MADE_BY, 4);
+    entry.writeUInt16LE(VERSION_NEEDED, 6);
+    entry.writeUInt16LE(DEFLATE, 10);
+    entry.writeUInt32LE(crc, 16);
+    entry.writeUInt32LE(compressed.length, 20);
+    entry.writeUInt32LE(data.length, 24);
+    entry.writeUInt16LE(name.length, 28);
+    entry.writeUInt32LE(((0o100000 | mode) << 16) >>> 0, 38);
+    entry.writeUInt32LE(offset, 42);
+    central.push(entry, name);
+
+    offset += header.length + name.length + compressed.length;
+  }
+
+  const centralSize = central.reduce((size, part) => size + part.length, 0);
+  const end = Buffer.alloc(22);
+  end.writeUInt32LE(0x06054b50, 0);
+  end.writeUInt16LE(central.length / 2, 8);
+  end.writeUInt16LE(central.length / 2, 10);
+  end.writeUInt32LE(centralSize, 12);
+  end.writeUInt32LE(offset, 16);
+
+  return Buffer.concat([...local, ...central, end]);
+}
+
+for (const variant of VARIANTS) {
+  const dir = join(EXTENSION_DIR, variant);
+  writeFileSync(`${dir}.zip`, zip(dir));
+  rmSync(dir, { recursive: true });
+  console.log(`Packed the ${variant} extension into ${variant}.zip`);
+}
@@ -0,0 +1,48 @@
+/**
+ * Writes `checksums.json` into each bundled extension variant, mapping each
+ * file's path to its SHA-256. The app checks the extracted copy against it
+ * to catch partial copies and files quarantined by antivirus software.
+ *
+ * With TAURI_SIGNING_PRIVATE_KEY set, as for release builds, the checksums
+ * are also signed with the updater's key into `checksums.json.sig`, which
+ * the app verifies before installing the extension.
+ */
+
+import { execFileSync } from 'child_process';
+import { createHash } from 'crypto';
+import { existsSync, readFileSync, readdirSync, writeFileSync } from 'fs';
+import { dirname, join, relative, sep } from 'path';
+import { fileURLToPath } from 'url';
+
+const EXTENSION_DIR = join(dirname(fileURLToPath(import.meta.url)), '..', 'src-tauri', 'resources', 'extension');
+const CHECKSUMS_FILE = 'checksums.json';
+const SIGNATURE_FILE = `${CHECKSUMS_FILE}.sig`;
+// Safari's is only built on macOS.
+const VARIANTS = ['chrome', 'firefox', 'safari'].filter((variant) => existsSync(join(EXTENSION_DIR, variant)));
+
+function listFiles(dir) {
+  return readdirSync(dir, { withFileTypes: true }).flatMap((entry) => {
+    const path = join(dir, entry.name);
+    return entry.isDirectory() ? listFiles(path) : [path];
+  });
+}
+
+for (const variant of VARIANTS) {
+  const dir = join(EXTENSION_DIR, variant);
+  const checksums = {};
+  for (const path of listFiles(dir).sort()) {
+    const name = relative(dir, path).split(sep).join('/');
+    if (name === CHECKSUMS_FILE || name === SIGNATURE_FILE) continue;
+    checksums[name] = createHash('sha256').update(readFileSync(path)).digest('hex');
+  }
+
+  writeFileSync(join(dir, CHECKSUMS_FILE), JSON.stringify(checksums, null, 2) + '\n');
+  console.log(`Wrote checksums for ${Object.keys(checksums).length} ${variant} extension files`);
+
+  if (process.env.TAURI_SIGNING_PRIVATE_KEY) {
+    // Writes the signature next to the file, the way updater artifacts are signed
+    execFileSync('pnpm', ['exec', 'tauri', 'signer', 'sign', join(dir, CHECKSUMS_FILE)], { stdio: 'inherit' });
+  } else {
+    console.warn(`Not signing the ${variant} extension checksums: TAURI_SIGNING_PRIVATE_KEY isn't set`);
+  }
+}
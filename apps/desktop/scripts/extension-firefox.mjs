@@ -0,0 +1,36 @@
+/**
+ * Builds the Firefox variant of the bundled extension from the Chrome one.
+ * Firefox runs background scripts instead of a service worker and requires
+ * an add-on id, so only the manifest differs.
+ */
+
+import { cpSync, readFileSync, rmSync, writeFileSync } from 'fs';
+import { dirname, join } from 'path';
+import { fileURLToPath } from 'url';
+
+const EXTENSION_DIR = join(dirname(fileURLToPath(import.meta.url)), '..', 'src-tauri', 'resources', 'extension');
+const CHROME_DIR = join(EXTENSION_DIR, 'chrome');
+const FIREFOX_DIR = join(EXTENSION_DIR, 'firefox');
+
+const FIREFOX_EXTENSION_ID = 'claudin@claudin.app';
+// First Firefox release supporting everything the extension uses from MV3.
+const FIREFOX_MIN_VERSION = '121.0';
+
+rmSync(FIREFOX_DIR, { recursive: true, force: true });
+cpSync(CHROME_DIR, FIREFOX_DIR, { recursive: true });
+
+const manifestPath = join(FIREFOX_DIR, 'manifest.json');
+const manifest = JSON.parse(readFileSync(manifestPath, 'utf8'));
+if (manifest.background?.service_worker) {
+  manifest.background.scripts = [manifest.background.service_worker];
+  delete manifest.background.service_worker;
+}
+manifest.browser_specific_settings = {
+  gecko: {
+    id: FIREFOX_EXTENSION_ID,
+    strict_min_version: FIREFOX_MIN_VERSION,
+  },
+};
+
+writeFileSync(manifestPath, JSON.stringify(manifest, null, 2) + '\n');
+console.log('Wrote the Firefox extension variant');
@@ -0,0 +1,73 @@
+/**
+ * Builds the Safari variant of the bundled extension from the Chrome one.
+ * Safari only loads web extensions that come inside an app, so Xcode's
+ * converter wraps the extension in one, which is then built and ad-hoc
+ * signed. Needs macOS with Xcode; elsewhere the variant is skipped.
+ */
+
+import { execFileSync } from 'child_process';
+import { cpSync, mkdtempSync, rmSync } from 'fs';
+import { tmpdir } from 'os';
+import { dirname, join } from 'path';
+import { fileURLToPath } from 'url';
+
+const EXTENSION_DIR = join(dirname(fileURLToPath(import.meta.url)), '..', 'src-tauri', 'resources', 'extension');
+const CHROME_DIR = join(EXTENSION_DIR, 'chrome');
+const SAFARI_DIR = join(EXTENSION_DIR, 'safari');
+
+const APP_NAME = 'ClaudIn';
+const BUNDLE_ID = 'app.claudin.safari';
+
+function hasConverter() {
+  try {
+    execFileSync('xcrun', ['--find', 'safari-web-extension-converter'], { stdio: 'ignore' });
+    return true;
+  } catch {
+    return false;
+  }
+}
+
+rmSync(SAFARI_DIR, { recursive: true, force: true });
+if (process.platform !== 'darwin' || !hasConverter()) {
+  console.log('Skipped the Safari extension variant, which needs Xcode');
+  process.exit(0);
+}
+
+const work = mkdtempSync(join(tmpdir(), 'claudin-safari-'));
+try {
+  execFileSync(
+    'xcrun',
+    [
+      'safari-web-extension-converter',
+      CHROME_DIR,
+      '--project-location', work,
+      '--app-name', APP_NAME,
+      '--bundle-identifier', BUNDLE_ID,
+      '--macos-only',
+      '--copy-resources',
+      '--no-open',
+      '--no-prompt',
+      '--force',
+    ],
+    { stdio: 'inherit' },
+  );
+  execFileSync(
+    'xcodebuild',
+    [
+      '-project', join(work, APP_NAME, `${APP_NAME}.xcodeproj`),
+      '-scheme', APP_NAME,
+      '-configuration', 'Release',
+      '-derivedDataPath', join(work, 'build'),
+      'CODE_SIGN_IDENTITY=-',
+      'build',
+    ],
+    { stdio: 'inherit' },
+  );
+  cpSync(join(work, 'build', 'Build', 'Products', 'Release', `${APP_NAME}.app`), join(SAFARI_DIR, `${APP_NAME}.app`), {
+    recursive: true,
+    verbatimSymlinks: true,
+  });
+  console.log('Wrote the Safari extension variant');
+} finally {
+  rmSync(work, { recursive: true, force: true });
+}
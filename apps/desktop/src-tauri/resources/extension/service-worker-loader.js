@@ -1 +0,0 @@
-import './assets/background-BPWwon0G.js';
@@ -0,0 +1,292 @@
+//! Append-only record of the commands the webview invokes. The wrapper
+//! around the invoke handler in `lib.rs` takes each call's name and
+//! arguments, and refused calls are written right away. Dispatched ones
+//! wait for their command: `audited!` wraps every command and writes the
+//! call once it returns, with whether it failed and how long it took.
+
+use crate::config::AuditConfig;
+use crate::logs::RotatingLog;
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::Runtime;
+
+/// Kept in the log dir next to the app's log.
+const AUDIT_FILE: &str = "audit.log";
+
+/// Rotated audit logs kept next to the active one by default, 5 MB each,
+/// so the audit log takes about 100 MB at most.
+pub const DEFAULT_MAX_FILES: u32 = 20;
+
+/// Longer strings in arguments are cut, since the audit log is about who
+/// called what rather than the data passed along.
+const MAX_ARG_LENGTH: usize = 200;
+
+/// Commands whose arguments are secrets under names too generic for
+/// `redact` to catch, like `set_api_key`'s `key`. Their arguments aren't
+/// written at all.
+const SECRET_COMMANDS: &[&str] = &["set_api_key"];
+
+static LOG: Mutex<Option<RotatingLog>> = Mutex::new(None);
+
+/// The `audit.maxFiles` setting.
+static MAX_FILES: AtomicU32 = AtomicU32::new(DEFAULT_MAX_FILES);
+
+/// Dispatched calls by command, oldest first, until their command starts.
+static PENDING: Mutex<BTreeMap<String, VecDeque<Call>>> = Mutex::new(BTreeMap::new());
+
+/// A dispatched call its command hasn't picked up after this long never
+/// ran, most likely because its arguments didn't deserialize.
+const PENDING_TIMEOUT: Duration = Duration::from_secs(30);
+
+tokio::task_local! {
+    /// Set while an async command runs, so the commands it calls itself
+    /// aren't taken for calls from the webview.
+    static IN_ASYNC_COMMAND: ();
+}
+
+thread_local! {
+    /// Set while a command runs on this thread, like `IN_ASYNC_COMMAND`.
+    static IN_COMMAND: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A command the webview invoked, taken before the invoke is handed on.
+pub struct Call {
+    command: String,
+    args: Value,
+    at: chrono::DateTime<chrono::Utc>,
+    started: Instant,
+}
+
+/// A call from the webview its command is running.
+pub struct Running(Option<Call>);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry<'a> {
+    at: String,
+    command: &'a str,
+    args: &'a Value,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<u128>,
+}
+
+/// Wraps a command so its call is written to the audit log once it
+/// returns: "ok", or "error" when it returned an `Err`, with the time from
+/// dispatch to the response. Goes on every command, after
+/// `#[tauri::command]`.
+macro_rules! audited {
+    (
+        $(#[$attr:meta])*
+        $vis:vis async fn $name:ident($($args:tt)*) -> Result<$ok:ty, $err:ty> $body:block
+    ) => {
+        $(#[$attr])*
+        $vis async fn $name($($args)*) -> Result<$ok, $err> {
+            let running = $crate::audit::Running::start(stringify!($name));
+            let result: Result<$ok, $err> = $crate::audit::run_async(async move $body).await;
+            running.finish(result.is_ok());
+            result
+        }
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($($args:tt)*) -> Result<$ok:ty, $err:ty> $body:block
+    ) => {
+        $(#[$attr])*
+        $vis fn $name($($args)*) -> Result<$ok, $err> {
+            let running = $crate::audit::Running::start(stringify!($name));
+            let result = $crate::audit::run(move || -> Result<$ok, $err> { $body });
+            running.finish(result.is_ok());
+            result
+        }
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($($args:tt)*) -> $ret:ty $body:block
+    ) => {
+        $(#[$attr])*
+        $vis fn $name($($args)*) -> $ret {
+            let running = $crate::audit::Running::start(stringify!($name));
+            let result = $crate::audit::run(move || -> $ret { $body });
+            running.finish(true);
+            result
+        }
+    };
+}
+pub(crate) use audited;
+
+/// Writes the audit log to `path`, oldest entries first. Returns the path
+/// written.
+#[tauri::command]
+#[apply(audited!)]
+pub fn export_audit_log(app: tauri::AppHandle, path: String) -> Result<String, String> {
+    crate::paths::guard(&app, std::path::Path::new(&path))?;
+    let audit_path = crate::logs::get_log_dir()?.join(AUDIT_FILE);
+    let mut contents = String::new();
+    for rotated in crate::logs::rotated_files(&audit_path).iter().rev() {
+        if let Ok(lines) = fs::read_to_string(rotated) {
+            contents.push_str(&lines);
+        }
+    }
+    if let Ok(lines) = fs::read_to_string(&audit_path) {
+        contents.push_str(&lines);
+    }
+    fs::write(&path, contents).map_err(|e| format!("Could not write {}: {}", path, e))?;
+    tracing::info!("Exported the audit log to {}", path);
+    Ok(path)
+}
+
+/// Applies the audit settings to the log, which keeps every rotated file
+/// when `max_files` is 0.
+pub fn apply(config: &AuditConfig) {
+    MAX_FILES.store(config.max_files, Ordering::Relaxed);
+    if let Some(log) = LOG.lock().unwrap().as_mut() {
+        log.set_generations(config.max_files);
+    }
+}
+
+impl Call {
+    pub fn new<R: Runtime>(invoke: &Invoke<R>) -> Self {
+        let command = invoke.message.command();
+        let args = match invoke.message.payload() {
+            _ if SECRET_COMMANDS.contains(&command) => Value::String(crate::redact::REDACTED.to_string()),
+            InvokeBody::Json(args) => sanitize(args),
+            InvokeBody::Raw(bytes) => Value::String(format!("<{} bytes>", bytes.len())),
+        };
+        Call {
+            command: command.to_string(),
+            args,
+            at: chrono::Utc::now(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Hands the call to its command, which records it once it returns.
+    pub fn dispatch(self) {
+        let mut expired = Vec::new();
+        {
+            let mut pending = PENDING.lock().unwrap();
+            for calls in pending.values_mut() {
+                while calls.front().is_some_and(|call| call.started.elapsed() > PENDING_TIMEOUT) {
+                    expired.extend(calls.pop_front());
+                }
+            }
+            pending.retain(|_, calls| !calls.is_empty());
+            pending.entry(self.command.clone()).or_default().push_back(self);
+        }
+        for call in expired {
+            call.record("invalid");
+        }
+    }
+
+    /// Appends the call to the audit log. `status` is "denied" when the
+    /// permission check refused it, "unknown" when no command has that
+    /// name and "invalid" when its command never ran.
+    pub fn record(self, status: &'static str) {
+        self.write(status, None);
+    }
+
+    fn write(self, status: &'static str, duration: Option<Duration>) {
+        let entry = Entry {
+            at: self.at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            command: &self.command,
+            args: &self.args,
+            status,
+            duration_ms: duration.map(|duration| duration.as_millis()),
+        };
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+
+        let mut log = LOG.lock().unwrap();
+        if log.is_none() {
+            let opened = crate::logs::get_log_dir()
+                .and_then(|dir| RotatingLog::with_generations(dir.join(AUDIT_FILE), MAX_FILES.load(Ordering::Relaxed)));
+            match opened {
+                Ok(opened) => *log = Some(opened),
+                Err(e) => {
+                    eprintln!("Failed to open the audit log: {}", e);
+                    return;
+                }
+            }
+        }
+        let Some(log) = log.as_mut() else {
+            return;
+        };
+        if let Err(e) = log.write_line(&line) {
+            eprintln!("Failed to write the audit log: {}", e);
+        }
+        let dropped = log.take_dropped();
+        if dropped > 0 {
+            tracing::warn!(
+                "Deleted {} old audit log file(s) to keep {}, as set by audit.maxFiles",
+                dropped,
+                MAX_FILES.load(Ordering::Relaxed)
+            );
+        }
+    }
+}
+
+impl Running {
+    /// Takes the oldest dispatched call of `command`, unless another
+    /// command is the one calling it.
+    pub fn start(command: &str) -> Self {
+        if IN_COMMAND.get() || IN_ASYNC_COMMAND.try_with(|_| ()).is_ok() {
+            return Running(None);
+        }
+        Running(take(command))
+    }
+
+    pub fn finish(self, ok: bool) {
+        if let Some(call) = self.0 {
+            let duration = call.started.elapsed();
+            call.write(if ok { "ok" } else { "error" }, Some(duration));
+        }
+    }
+}
+
+/// Takes the oldest dispatched call of `command` off the calls waiting for
+/// their command.
+pub fn take(command: &str) -> Option<Call> {
+    PENDING.lock().unwrap().get_mut(command).and_then(VecDeque::pop_front)
+}
+
+/// Runs a sync command's body, marking the thread as running a command.
+pub fn run<T>(body: impl FnOnce() -> T) -> T {
+    let outer = IN_COMMAND.replace(true);
+    let result = body();
+    IN_COMMAND.set(outer);
+    result
+}
+
+/// Runs an async command's body, marking its task as running a command.
+pub async fn run_async<T>(body: impl std::future::Future<Output = T>) -> T {
+    IN_ASYNC_COMMAND.scope((), body).await
+}
+
+/// Arguments as written to the audit log: redacted, with long strings cut.
+fn sanitize(value: &Value) -> Value {
+    truncate(&crate::redact::json(value))
+}
+
+fn truncate(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            Value::Object(fields.iter().map(|(key, value)| (key.clone(), truncate(value))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(truncate).collect()),
+        Value::String(text) if text.chars().count() > MAX_ARG_LENGTH => {
+            let cut: String = text.chars().take(MAX_ARG_LENGTH).collect();
+            Value::String(format!("{}… ({} chars)", cut, text.chars().count()))
+        }
+        _ => value.clone(),
+    }
+}
@@ -0,0 +1,183 @@
+use crate::audit::audited;
+use macro_rules_attribute::apply;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Folder in the config dir the backups are kept in, one folder each.
+const BACKUPS_DIR: &str = "backups";
+
+/// Written into every backup to describe it.
+const BACKUP_MANIFEST: &str = "backup.json";
+
+/// Older backups are deleted when a new one is made.
+const MAX_BACKUPS: usize = 10;
+
+/// Bigger files, like databases, aren't backed up.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigBackup {
+    /// Name of the backup's folder, which sorts by age.
+    #[serde(skip_deserializing)]
+    id: String,
+    /// What the backup was made before, like `migration` or `reset`.
+    reason: String,
+    created_at: String,
+    app_version: String,
+    #[serde(skip_deserializing)]
+    files: usize,
+}
+
+/// Backups of the config dir, newest first.
+#[tauri::command]
+#[apply(audited!)]
+pub fn list_config_backups() -> Result<Vec<ConfigBackup>, String> {
+    let mut backups = Vec::new();
+    for dir in backup_dirs()? {
+        let Some(id) = dir.file_name().map(|name| name.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let Ok(contents) = fs::read_to_string(dir.join(BACKUP_MANIFEST)) else {
+            continue;
+        };
+        match serde_json::from_str::<ConfigBackup>(&contents) {
+            Ok(backup) => backups.push(ConfigBackup {
+                id,
+                files: count_files(&dir).saturating_sub(1),
+                ..backup
+            }),
+            Err(e) => tracing::warn!("Ignoring backup {} with an unreadable manifest: {}", id, e),
+        }
+    }
+    Ok(backups)
+}
+
+/// Copies the files of a backup back into the config dir, after backing up
+/// the current ones so the restore can be undone too. Files created since
+/// the backup are kept.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn restore_config_backup(app: AppHandle, id: String) -> Result<ConfigBackup, String> {
+    let backup_dir = backup_dirs()?
+        .into_iter()
+        .find(|dir| dir.file_name().is_some_and(|name| name.to_string_lossy() == id))
+        .ok_or_else(|| format!("No config backup {:?}", id))?;
+    let before = crate::config::load().ok();
+
+    let undo = snapshot("restore")?;
+    let config_dir = crate::get_config_dir()?;
+    copy_dir(&backup_dir, &config_dir, &[backup_dir.join(BACKUP_MANIFEST)])?;
+    tracing::info!("Restored config backup {}, the previous config is in {}", id, undo.display());
+
+    let settings = crate::config::load()?;
+    crate::config::broadcast(&app, &settings)?;
+    if before.is_none_or(|before| before.server != settings.server) {
+        crate::server::restart_all(&app).await?;
+    }
+
+    list_config_backups()?
+        .into_iter()
+        .find(|backup| backup.id == id)
+        .ok_or_else(|| format!("No config backup {:?}", id))
+}
+
+/// Copies the settings and other small files of every profile into a new
+/// backup, leaving out logs, PID files, databases and earlier backups.
+/// Returns the backup's folder.
+pub fn snapshot(reason: &str) -> Result<PathBuf, String> {
+    let config_dir = crate::get_config_dir()?;
+    let now = chrono::Utc::now();
+    let mut dir = backups_dir()?.join(format!("{}-{}", now.format("%Y%m%d-%H%M%S"), reason));
+    // Several backups can be made within a second, e.g. when each profile's
+    // settings are migrated.
+    let mut suffix = 1;
+    while dir.exists() {
+        suffix += 1;
+        dir = backups_dir()?.join(format!("{}-{}-{}", now.format("%Y%m%d-%H%M%S"), reason, suffix));
+    }
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut excluded = vec![backups_dir()?, crate::logs::get_log_dir()?];
+    excluded.extend(crate::server::data_dirs()?);
+    copy_dir(&config_dir, &dir, &excluded)?;
+
+    let manifest = ConfigBackup {
+        id: String::new(),
+        reason: reason.to_string(),
+        created_at: now.to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: 0,
+    };
+    let contents = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(dir.join(BACKUP_MANIFEST), contents).map_err(|e| e.to_string())?;
+    tracing::info!("Backed up the config before {} to {}", reason, dir.display());
+
+    for old in backup_dirs()?.into_iter().skip(MAX_BACKUPS) {
+        if let Err(e) = fs::remove_dir_all(&old) {
+            tracing::warn!("Failed to delete old config backup {}: {}", old.display(), e);
+        }
+    }
+    Ok(dir)
+}
+
+pub fn backups_dir() -> Result<PathBuf, String> {
+    Ok(crate::get_config_dir()?.join(BACKUPS_DIR))
+}
+
+/// Backup folders, newest first.
+fn backup_dirs() -> Result<Vec<PathBuf>, String> {
+    let Ok(entries) = fs::read_dir(backups_dir()?) else {
+        return Ok(Vec::new());
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+    Ok(dirs)
+}
+
+/// Copies the files of `from` into `to`, skipping the `excluded` paths and
+/// files too big to back up.
+fn copy_dir(from: &Path, to: &Path, excluded: &[PathBuf]) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(from) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if excluded.contains(&path) || entry.file_name() == crate::server::SERVER_PID_FILE {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let target = to.join(entry.file_name());
+        if metadata.is_dir() {
+            copy_dir(&path, &target, excluded)?;
+        } else if metadata.is_file() && metadata.len() <= MAX_FILE_SIZE {
+            fs::create_dir_all(to).map_err(|e| e.to_string())?;
+            fs::copy(&path, &target).map_err(|e| format!("Could not copy {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn count_files(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                count_files(&path)
+            } else {
+                1
+            }
+        })
+        .sum()
+}
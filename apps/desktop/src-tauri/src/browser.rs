@@ -0,0 +1,1265 @@
+use macro_rules_attribute::apply;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::{AppHandle, Emitter};
+
+use crate::audit::audited;
+use crate::extension::{self, ExtensionRegistry, ExtensionVariant};
+use crate::i18n::{t, t_with};
+
+pub const PREFERENCES_FILE: &str = "Preferences";
+
+/// Lists the profiles of a user data dir with their names, in
+/// `profile.info_cache`.
+const LOCAL_STATE_FILE: &str = "Local State";
+
+/// How long a browser gets to save its session and quit before a relaunch
+/// gives up.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Recent Chromium versions keep the settings of unpacked extensions here
+/// instead of in `Preferences`.
+pub const SECURE_PREFERENCES_FILE: &str = "Secure Preferences";
+
+/// The browsers the app ships support for.
+const BUILTIN_REGISTRY: &str = include_str!("../resources/browsers.json");
+
+/// Entries in this file in the config dir replace the shipped ones with the
+/// same id, and add browsers that aren't shipped, so a new Chromium fork can
+/// be supported without a new release.
+const REGISTRY_FILE: &str = "browsers.json";
+
+static REGISTRY: OnceLock<Vec<BrowserSpec>> = OnceLock::new();
+
+/// A browser the extension can be loaded into, by its id in the registry.
+/// Chrome's Beta, Dev and Canary channels install side by side with it, each
+/// with its own profiles, so each is a browser of its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Browser(&'static str);
+
+/// How a browser is found, started and recognized, as listed in the
+/// registry.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BrowserSpec {
+    id: String,
+    name: String,
+    #[serde(default)]
+    engine: Engine,
+    /// Arc has no extensions page of its own and uses Chrome's. Firefox
+    /// loads unpacked add-ons from its debugging page.
+    extensions_url: String,
+    /// Whether the browser's version is its engine's, so it can be compared
+    /// with the minimum in the extension's manifest. Brave, Vivaldi, Opera
+    /// and Arc number their releases their own way.
+    #[serde(default)]
+    engine_versions: bool,
+    #[serde(default)]
+    macos: PlatformSpec,
+    #[serde(default)]
+    windows: PlatformSpec,
+    #[serde(default)]
+    linux: PlatformSpec,
+}
+
+/// The engine a browser is built on, which decides the build of the
+/// extension it takes.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Chromium,
+    #[serde(alias = "firefox")]
+    Gecko,
+    WebKit,
+    /// An engine listed in the registry that the app has no build for.
+    #[serde(other)]
+    Other,
+}
+
+/// What the registry says about a browser on one OS. Paths are `/`
+/// separated on every OS.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlatformSpec {
+    /// Name of the app bundle in `/Applications`, without `.app`.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    app: Option<String>,
+    /// How the OS identifies the app as the default browser.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    bundle_id: Option<String>,
+    /// Where the installer puts the executable, relative to Program Files or
+    /// the local app data dir for per-user installs. Canary only installs
+    /// per user, and Arc is a Store app reachable through its execution
+    /// alias.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    install_paths: Vec<String>,
+    /// The executable's name under the registry's `App Paths`, where
+    /// installers register it. Chrome's other channels and Chromium builds
+    /// register as chrome.exe too, which would find Chrome instead, so they
+    /// have none.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    app_path: Option<String>,
+    /// Prefix of the ProgId the browser registers for links, like
+    /// `ChromeHTML` or `FirefoxURL`, which some suffix per install.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    prog_id: Option<String>,
+    /// Names the browser is packaged under by the common distributions.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    executables: Vec<String>,
+    /// Names of the `.desktop` entries the browser's packages install, for
+    /// installs that aren't on `PATH`.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    desktop_entries: Vec<String>,
+    /// Flatpak app ids the browser is published under on Flathub.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    flatpak_ids: Vec<String>,
+    /// Names of the snaps the browser is published as.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    snaps: Vec<String>,
+    /// Candidates for the dir the browser keeps its profiles in, starting
+    /// with one of `{home}`, `{config}`, `{data}` or `{localData}`. Flatpak
+    /// and Snap installs keep it in their sandbox, and Chromium's snap in a
+    /// dir that is kept across revisions.
+    user_data_dirs: Vec<String>,
+    /// Names of the main and helper processes, for installs whose processes
+    /// don't run from the dir `locate` finds, like wrapper scripts on Linux.
+    /// Executables shared with another browser, like the chrome.exe of
+    /// Chrome's channels and Chromium on Windows, are left out, so those are
+    /// only recognized by their location.
+    process_names: Vec<String>,
+    /// Where the browser's policies are kept: the managed preferences domain
+    /// on macOS, the key under `Software\Policies` on Windows and the policy
+    /// dir on Linux. Every channel of Chrome reads Chrome's.
+    pub policies: Option<String>,
+    /// Whether the browser looks up native messaging hosts in its user data
+    /// dir.
+    #[cfg_attr(windows, allow(dead_code))]
+    pub native_messaging: bool,
+    /// The vendor key under `Software` the browser looks up native messaging
+    /// hosts in. Chrome's channels share Chrome's, and Arc doesn't document
+    /// one.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub native_messaging_key: Option<String>,
+}
+
+/// The shipped browsers with the overrides in the config dir applied. A file
+/// that can't be read is ignored as a whole, and entries whose id couldn't
+/// be used in a path are skipped.
+fn registry() -> &'static [BrowserSpec] {
+    REGISTRY.get_or_init(|| {
+        let mut specs: Vec<BrowserSpec> =
+            serde_json::from_str(BUILTIN_REGISTRY).expect("the shipped browser registry is valid");
+
+        let Some(path) = crate::get_config_dir().ok().map(|dir| dir.join(REGISTRY_FILE)) else {
+            return specs;
+        };
+        if !path.is_file() {
+            return specs;
+        }
+        let overrides = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| serde_json::from_str::<Vec<BrowserSpec>>(&contents).map_err(|e| e.to_string()));
+        let overrides = match overrides {
+            Ok(overrides) => overrides,
+            Err(e) => {
+                tracing::warn!("Ignoring invalid browser registry {}: {}", path.display(), e);
+                return specs;
+            }
+        };
+
+        for spec in overrides {
+            let valid_id = !spec.id.is_empty()
+                && spec
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid_id {
+                tracing::warn!("Ignoring browser with invalid id {:?} in {}", spec.id, path.display());
+                continue;
+            }
+            tracing::info!("Using browser {:?} from {}", spec.id, path.display());
+            match specs.iter_mut().find(|existing| existing.id == spec.id) {
+                Some(existing) => *existing = spec,
+                None => specs.push(spec),
+            }
+        }
+        specs
+    })
+}
+
+impl Serialize for Browser {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Browser {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Browser::from_id(&id).ok_or_else(|| serde::de::Error::custom(format!("unknown browser {:?}", id)))
+    }
+}
+
+impl Browser {
+    /// Shipped with the app, and the fallback when no browser is found.
+    pub const CHROME: Browser = Browser("chrome");
+    pub const FIREFOX: Browser = Browser("firefox");
+
+    /// Every browser in the registry, in the order a default is picked when
+    /// several are installed.
+    pub fn all() -> Vec<Browser> {
+        registry().iter().map(|spec| Browser(spec.id.as_str())).collect()
+    }
+
+    pub fn from_id(id: &str) -> Option<Browser> {
+        registry()
+            .iter()
+            .find(|spec| spec.id == id)
+            .map(|spec| Browser(spec.id.as_str()))
+    }
+
+    fn spec(self) -> &'static BrowserSpec {
+        registry()
+            .iter()
+            .find(|spec| spec.id == self.0)
+            .expect("browsers are only made from registry ids")
+    }
+
+    /// The registry's entry for the OS the app runs on.
+    pub fn platform(self) -> &'static PlatformSpec {
+        let spec = self.spec();
+        if cfg!(target_os = "macos") {
+            &spec.macos
+        } else if cfg!(windows) {
+            &spec.windows
+        } else {
+            &spec.linux
+        }
+    }
+
+    pub fn is_chromium(self) -> bool {
+        self.engine() == Engine::Chromium
+    }
+
+    pub fn engine(self) -> Engine {
+        self.spec().engine
+    }
+
+    /// The name the browser is serialized under.
+    pub fn id(self) -> &'static str {
+        self.0
+    }
+
+    pub fn display_name(self) -> &'static str {
+        &self.spec().name
+    }
+
+    pub fn extensions_url(self) -> &'static str {
+        &self.spec().extensions_url
+    }
+
+    #[cfg(target_os = "macos")]
+    fn app_name(self) -> Result<&'static str, String> {
+        self.platform()
+            .app
+            .as_deref()
+            .ok_or_else(|| format!("{} isn't available for macOS", self.display_name()))
+    }
+
+    /// Path to the installed browser, if it can be found.
+    #[cfg(target_os = "macos")]
+    pub fn locate(self) -> Option<PathBuf> {
+        let bundle = format!("{}.app", self.app_name().ok()?);
+        let mut candidates = vec![PathBuf::from("/Applications")];
+        if let Some(home) = dirs::home_dir() {
+            candidates.push(home.join("Applications"));
+        }
+        candidates
+            .into_iter()
+            .map(|dir| dir.join(&bundle))
+            .find(|path| path.exists())
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn locate(self) -> Option<PathBuf> {
+        let platform = self.platform();
+        if let Some(path) = platform.app_path.as_deref().and_then(registered_app_path) {
+            return Some(path);
+        }
+
+        let roots: Vec<PathBuf> = ["ProgramFiles", "ProgramFiles(x86)", "LOCALAPPDATA"]
+            .iter()
+            .filter_map(|var| std::env::var_os(var).map(PathBuf::from))
+            .collect();
+        roots
+            .iter()
+            .flat_map(|root| platform.install_paths.iter().map(move |path| join_path(root.clone(), path)))
+            .find(|path| path.is_file())
+    }
+
+    /// Snap and Flatpak installs usually aren't on the `PATH` apps are
+    /// started with, so their launchers are looked for where they put them:
+    /// `/snap/bin/<snap>` for `snap run <snap>`, and the wrappers Flatpak
+    /// exports for `flatpak run <app id>`. Both take the browser's arguments.
+    #[cfg(target_os = "linux")]
+    pub fn locate(self) -> Option<PathBuf> {
+        let platform = self.platform();
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let snaps = platform.snaps.iter().map(|name| Path::new("/snap/bin").join(name));
+        let flatpaks = flatpak_export_dirs()
+            .into_iter()
+            .flat_map(|dir| platform.flatpak_ids.iter().map(move |id| dir.join(id)));
+
+        platform
+            .executables
+            .iter()
+            .find_map(|name| crate::runtime::find_on_path(name, &path))
+            .or_else(|| snaps.chain(flatpaks).find(|launcher| launcher.is_file()))
+            .or_else(|| platform.desktop_entries.iter().find_map(|entry| desktop_entry_exec(entry)))
+    }
+
+    /// The installed version, read without starting the browser where the
+    /// install records it.
+    #[cfg(target_os = "macos")]
+    pub fn version(self, path: &Path) -> Option<String> {
+        app_version(path)
+    }
+
+    /// Chromium installers keep each version in a folder named after it next
+    /// to the executable, and Firefox writes its version to `application.ini`.
+    #[cfg(target_os = "windows")]
+    pub fn version(self, path: &Path) -> Option<String> {
+        let dir = path.parent()?;
+        if !self.is_chromium() {
+            let ini = std::fs::read_to_string(dir.join("application.ini")).ok()?;
+            return ini
+                .lines()
+                .find_map(|line| line.strip_prefix("Version="))
+                .map(|version| version.trim().to_string());
+        }
+        std::fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| is_version(name))
+            .max_by(|a, b| compare_versions(a, b))
+    }
+
+    /// Every browser on Linux prints e.g. `Chromium 120.0.6099.129` for
+    /// `--version`.
+    #[cfg(target_os = "linux")]
+    pub fn version(self, path: &Path) -> Option<String> {
+        let output = Command::new(path).arg("--version").output().ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .find(|word| is_version(word))
+            .map(str::to_string)
+    }
+}
+
+/// The version in the `Info.plist` of the app bundle at `path`.
+#[cfg(target_os = "macos")]
+pub fn app_version(path: &Path) -> Option<String> {
+    let plist = std::fs::read_to_string(path.join("Contents").join("Info.plist")).ok()?;
+    let (_, rest) = plist.split_once("<key>CFBundleShortVersionString</key>")?;
+    let (_, rest) = rest.split_once("<string>")?;
+    let (version, _) = rest.split_once("</string>")?;
+    Some(version.trim().to_string())
+}
+
+/// Appends a `/` separated registry path to `base`.
+fn join_path(base: PathBuf, path: &str) -> PathBuf {
+    path.split('/').filter(|part| !part.is_empty()).fold(base, |dir, part| dir.join(part))
+}
+
+/// Expands a registry path like `{config}/vivaldi`, whose first part names
+/// one of the user's dirs.
+fn expand_dir(template: &str) -> Option<PathBuf> {
+    let (base, rest) = template.split_once('/').unwrap_or((template, ""));
+    let base = match base {
+        "{home}" => dirs::home_dir(),
+        "{config}" => dirs::config_dir(),
+        "{data}" => dirs::data_dir(),
+        "{localData}" => dirs::data_local_dir(),
+        _ => {
+            tracing::warn!("Ignoring browser dir {:?} that doesn't start with a known dir", template);
+            None
+        }
+    }?;
+    Some(join_path(base, rest))
+}
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn is_version(text: &str) -> bool {
+    text.contains('.') && text.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(target_os = "windows")]
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |version: &str| -> Vec<u64> { version.split('.').filter_map(|part| part.parse().ok()).collect() };
+    parts(a).cmp(&parts(b))
+}
+
+/// Looks up an executable registered under `App Paths`, per user first.
+#[cfg(target_os = "windows")]
+fn registered_app_path(exe: &str) -> Option<PathBuf> {
+    use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+    use winreg::RegKey;
+
+    let key = format!("Software\\Microsoft\\Windows\\CurrentVersion\\App Paths\\{}", exe);
+    [HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE].into_iter().find_map(|root| {
+        let path: String = RegKey::predef(root).open_subkey(&key).ok()?.get_value("").ok()?;
+        let path = PathBuf::from(path.trim_matches('"'));
+        path.is_file().then_some(path)
+    })
+}
+
+/// Where Flatpak puts the launchers of per-user and system-wide installs.
+#[cfg(target_os = "linux")]
+fn flatpak_export_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(data_dir) = dirs::data_dir() {
+        dirs.push(data_dir.join("flatpak").join("exports").join("bin"));
+    }
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/bin"));
+    dirs
+}
+
+/// Finds the executable a `.desktop` entry starts, in the system and
+/// per-user application dirs. Entries starting `flatpak` or `snap` with the
+/// app as an argument are skipped, since their launchers are found directly.
+#[cfg(target_os = "linux")]
+fn desktop_entry_exec(entry: &str) -> Option<PathBuf> {
+    let mut dirs = vec![dirs::data_dir()?.join("applications")];
+    let data_dirs = std::env::var("XDG_DATA_DIRS").unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(data_dirs.split(':').map(|dir| Path::new(dir).join("applications")));
+
+    dirs.iter().find_map(|dir| {
+        let contents = std::fs::read_to_string(dir.join(entry)).ok()?;
+        let exec = contents.lines().find_map(|line| line.strip_prefix("Exec="))?;
+        let program = PathBuf::from(exec.split_whitespace().next()?);
+        if program.file_name().is_some_and(|name| name == "flatpak" || name == "snap") {
+            return None;
+        }
+        program.is_absolute().then_some(program).filter(|program| program.is_file())
+    })
+}
+
+/// A browser found on this machine.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledBrowser {
+    browser: Browser,
+    name: &'static str,
+    path: String,
+    version: Option<String>,
+    /// Whether it takes the Chrome build of the extension.
+    chromium: bool,
+}
+
+/// A profile in a browser's user data dir.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserProfile {
+    /// Name of the profile's directory, e.g. `Default` or `Profile 1`.
+    pub id: String,
+    /// Name shown in the browser's profile menu.
+    pub name: Option<String>,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Browser {
+    /// Where the browser keeps its profiles: the first of the registry's
+    /// dirs that exists, or else the first one. Firefox profiles aren't laid
+    /// out like Chromium ones, so there is none for Firefox.
+    pub fn user_data_dir(self) -> Option<PathBuf> {
+        let candidates: Vec<PathBuf> = self
+            .platform()
+            .user_data_dirs
+            .iter()
+            .filter_map(|dir| expand_dir(dir))
+            .collect();
+        candidates
+            .iter()
+            .find(|dir| dir.exists())
+            .or(candidates.first())
+            .cloned()
+    }
+
+    /// Profiles found in the user data dir, from `Local State` when the
+    /// browser keeps one. Opera keeps its single profile in the user data
+    /// dir itself rather than in a subdirectory.
+    pub fn profiles(self) -> Vec<BrowserProfile> {
+        let Some(user_data_dir) = self.user_data_dir() else {
+            return Vec::new();
+        };
+
+        let local_state = read_preferences(&user_data_dir.join(LOCAL_STATE_FILE));
+        if let Some(info_cache) = local_state
+            .as_ref()
+            .and_then(|state| state["profile"]["info_cache"].as_object())
+        {
+            let mut profiles: Vec<BrowserProfile> = info_cache
+                .iter()
+                .map(|(id, info)| BrowserProfile {
+                    id: id.clone(),
+                    name: info["name"].as_str().map(str::to_string),
+                    path: user_data_dir.join(id),
+                })
+                .filter(|profile| profile.path.is_dir())
+                .collect();
+            if !profiles.is_empty() {
+                profiles.sort_by(|a, b| a.id.cmp(&b.id));
+                return profiles;
+            }
+        }
+
+        let mut candidates = vec![user_data_dir.clone()];
+        if let Ok(entries) = std::fs::read_dir(&user_data_dir) {
+            candidates.extend(entries.flatten().map(|entry| entry.path()));
+        }
+
+        candidates
+            .into_iter()
+            .filter(|dir| dir.join(PREFERENCES_FILE).is_file())
+            .map(|path| BrowserProfile {
+                id: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+                name: read_preferences(&path.join(PREFERENCES_FILE))
+                    .and_then(|prefs| prefs["profile"]["name"].as_str().map(str::to_string)),
+                path,
+            })
+            .collect()
+    }
+}
+
+impl Browser {
+    /// The profile the user picked for this browser, if any.
+    pub fn chosen_profile(self) -> Option<String> {
+        crate::config::load().ok()?.browser.profiles.remove(&self)
+    }
+
+    /// The profiles to look at: only the one the user picked when it still
+    /// exists, otherwise all of them.
+    pub fn relevant_profiles(self) -> Vec<BrowserProfile> {
+        let profiles = self.profiles();
+        match self.chosen_profile() {
+            Some(chosen) if profiles.iter().any(|profile| profile.id == chosen) => profiles
+                .into_iter()
+                .filter(|profile| profile.id == chosen)
+                .collect(),
+            _ => profiles,
+        }
+    }
+}
+
+/// Lists the profiles of `browser` (the default browser when not given) as
+/// the browser's profile menu shows them.
+#[tauri::command]
+#[apply(audited!)]
+pub fn list_browser_profiles(browser: Option<Browser>) -> Result<Vec<BrowserProfile>, String> {
+    Ok(browser.unwrap_or_else(default_browser).profiles())
+}
+
+/// The profile picked for `browser`, if any.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_browser_profile(browser: Option<Browser>) -> Result<Option<String>, String> {
+    Ok(browser.unwrap_or_else(default_browser).chosen_profile())
+}
+
+/// Picks the profile the extension is used in, or clears the choice so every
+/// profile is considered again.
+#[tauri::command]
+#[apply(audited!)]
+pub fn set_browser_profile(browser: Option<Browser>, profile: Option<String>) -> Result<(), String> {
+    let browser = browser.unwrap_or_else(default_browser);
+    let mut settings = crate::config::load()?;
+    match profile {
+        Some(profile) => {
+            if !browser.profiles().iter().any(|existing| existing.id == profile) {
+                return Err(format!("{} has no profile {:?}", browser.display_name(), profile));
+            }
+            settings.browser.profiles.insert(browser, profile);
+        }
+        None => {
+            settings.browser.profiles.remove(&browser);
+        }
+    }
+    crate::config::save(&settings)
+}
+
+/// Whether `browser` (the default browser when not given) is running, so
+/// flows that need a restart, like reloading the extension after an update,
+/// can ask for one first.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn is_browser_running(browser: Option<Browser>) -> Result<bool, String> {
+    let browser = browser.unwrap_or_else(default_browser);
+    tauri::async_runtime::spawn_blocking(move || !browser.running_processes(&scan_processes()).is_empty())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// What `relaunch_browser` did.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase", tag = "outcome")]
+pub enum RelaunchOutcome {
+    /// The browser wasn't running, so it picks up the changes whenever it's
+    /// started next.
+    NotRunning,
+    /// The user chose to restart it later.
+    Declined,
+    /// Chromium browsers reopen the tabs they had. Firefox only does when
+    /// the user set it to open previous windows and tabs.
+    #[serde(rename_all = "camelCase")]
+    Relaunched { session_restored: bool },
+}
+
+/// Restarts `browser` (the default browser when not given) so it loads the
+/// extension files again, after asking the user unless `confirmed` says the
+/// UI already did. The browser is asked to quit the way closing its window
+/// would, so it saves its session, and is started again once it has exited.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn relaunch_browser(
+    app: AppHandle,
+    browser: Option<Browser>,
+    confirmed: Option<bool>,
+) -> Result<RelaunchOutcome, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let browser = browser.unwrap_or_else(default_browser);
+    tauri::async_runtime::spawn_blocking(move || {
+        if browser.running_processes(&scan_processes()).is_empty() {
+            return Ok(RelaunchOutcome::NotRunning);
+        }
+
+        let name = [("browser", browser.display_name())];
+        let confirmed = confirmed.unwrap_or(false)
+            || app
+                .dialog()
+                .message(t_with("dialog.relaunch.message", &name))
+                .title(t_with("dialog.relaunch.title", &name))
+                .kind(MessageDialogKind::Info)
+                .buttons(MessageDialogButtons::OkCancelCustom(
+                    t("dialog.relaunch.confirm"),
+                    t("dialog.relaunch.cancel"),
+                ))
+                .blocking_show();
+        if !confirmed {
+            return Ok(RelaunchOutcome::Declined);
+        }
+
+        browser.quit()?;
+
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while !browser.running_processes(&scan_processes()).is_empty() {
+            if Instant::now() >= deadline {
+                return Err(t_with("browser.didNotQuit", &[("browser", browser.display_name())]));
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+
+        let args = if browser.is_chromium() {
+            vec!["--restore-last-session".to_string()]
+        } else {
+            Vec::new()
+        };
+        tracing::info!("Relaunching {:?}", browser);
+        browser.launch(&args)?;
+        Ok(RelaunchOutcome::Relaunched {
+            session_restored: browser.is_chromium(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn scan_processes() -> System {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_exe(UpdateKind::Always),
+    );
+    system
+}
+
+impl Browser {
+    /// The folder every process of the browser runs from. Snap and Flatpak
+    /// launchers live apart from the sandboxed browser, so those are only
+    /// recognized by their process names.
+    fn install_dir(self) -> Option<PathBuf> {
+        let path = self.locate()?;
+        if cfg!(target_os = "macos") {
+            return Some(path);
+        }
+        #[cfg(target_os = "linux")]
+        if path.starts_with("/snap/bin") || flatpak_export_dirs().iter().any(|dir| path.starts_with(dir)) {
+            return None;
+        }
+        let path = dunce::canonicalize(&path).unwrap_or(path);
+        path.parent().map(Path::to_path_buf)
+    }
+
+    fn running_processes(self, system: &System) -> Vec<Pid> {
+        let install_dir = self.install_dir();
+        system
+            .processes()
+            .iter()
+            .filter(|(_, process)| {
+                let in_install_dir = match (&install_dir, process.exe()) {
+                    (Some(dir), Some(exe)) => exe.starts_with(dir),
+                    _ => false,
+                };
+                in_install_dir
+                    || self
+                        .platform()
+                        .process_names
+                        .iter()
+                        .any(|name| process.name() == std::ffi::OsStr::new(name))
+            })
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
+    /// Quits the browser as politely as the OS allows, so it saves its
+    /// session instead of offering to restore it after a crash.
+    fn quit(self) -> Result<(), String> {
+        #[cfg(target_os = "macos")]
+        {
+            let script = format!("quit app \"{}\"", self.app_name()?);
+            Command::new("osascript")
+                .args(["-e", &script])
+                .status()
+                .map_err(|e| e.to_string())?;
+        }
+
+        // Without /F, taskkill closes the windows like the user would. Only
+        // this browser's processes are named, since other Chromium browsers
+        // and other channels can share the executable's name.
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+            let pids = self.running_processes(&scan_processes());
+            if !pids.is_empty() {
+                let mut command = Command::new("taskkill");
+                for pid in pids {
+                    command.arg("/PID").arg(pid.to_string());
+                }
+                command
+                    .creation_flags(CREATE_NO_WINDOW)
+                    .status()
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let system = scan_processes();
+            for pid in self.running_processes(&system) {
+                if let Some(process) = system.process(pid) {
+                    process.kill_with(sysinfo::Signal::Term);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a Chromium preferences file, which is plain JSON.
+pub fn read_preferences(path: &Path) -> Option<serde_json::Value> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Lists the supported browsers installed on this machine with their
+/// version, so the setup wizard can offer the ones the user actually has.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn detect_installed_browsers() -> Result<Vec<InstalledBrowser>, String> {
+    // Reading versions may start each browser with `--version`.
+    tauri::async_runtime::spawn_blocking(|| {
+        Browser::all()
+            .into_iter()
+            .filter_map(|browser| {
+                let path = browser.locate()?;
+                Some(InstalledBrowser {
+                    browser,
+                    name: browser.display_name(),
+                    version: browser.version(&path),
+                    path: path.to_string_lossy().to_string(),
+                    chromium: browser.is_chromium(),
+                })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// The oldest Chromium the extension runs on when its manifest doesn't say:
+/// the first with module service workers for extensions, which its
+/// background script is.
+const MIN_CHROMIUM_VERSION: &str = "92";
+
+/// Whether the installed version of a browser is recent enough for the
+/// extension.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserCompatibility {
+    browser: Browser,
+    pub version: Option<String>,
+    pub minimum_version: Option<String>,
+    /// `None` when the version couldn't be read, or isn't numbered like
+    /// Chromium's as with Brave, Vivaldi, Opera and Arc.
+    pub compatible: Option<bool>,
+    /// What to tell the user when the browser is too old.
+    message: Option<String>,
+}
+
+/// Checks the version of `browser` (the default browser when not given)
+/// against the minimum in the extension's manifest, so setup can ask the
+/// user to update before loading an extension that wouldn't run.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn check_browser_compatibility(
+    app: AppHandle,
+    browser: Option<Browser>,
+) -> Result<BrowserCompatibility, String> {
+    let browser = browser.unwrap_or_else(default_browser);
+    tauri::async_runtime::spawn_blocking(move || compatibility(&app, browser))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+pub fn compatibility(app: &AppHandle, browser: Browser) -> Result<BrowserCompatibility, String> {
+    let version = browser.locate().and_then(|path| browser.version(&path));
+    let minimum_version = minimum_version(app, browser)?;
+    let compatible = match (&version, &minimum_version) {
+        (Some(version), Some(minimum)) => Some(!extension::is_newer_version(minimum, version)),
+        _ => None,
+    };
+
+    let message = match (compatible, &version, &minimum_version) {
+        (Some(false), Some(version), Some(minimum)) => Some(t_with(
+            "check.browser.outdated",
+            &[("browser", browser.display_name()), ("minimum", minimum), ("version", version)],
+        )),
+        _ => None,
+    };
+    if let Some(message) = &message {
+        tracing::warn!("{}", message);
+    }
+
+    Ok(BrowserCompatibility {
+        browser,
+        version,
+        minimum_version,
+        compatible,
+        message,
+    })
+}
+
+/// The minimum version from the manifest of the build `browser` takes, for
+/// the browsers whose version can be compared with it.
+fn minimum_version(app: &AppHandle, browser: Browser) -> Result<Option<String>, String> {
+    let spec = ExtensionRegistry::default_extension();
+    let manifest = |variant| -> Result<serde_json::Value, String> {
+        let contents = spec.shipped_bundle(app, variant)?.read_to_string("manifest.json")?;
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid extension manifest: {}", e))
+    };
+
+    if !browser.spec().engine_versions {
+        return Ok(None);
+    }
+    Ok(match browser.spec().engine {
+        Engine::Chromium => Some(
+            manifest(ExtensionVariant::Chrome)?["minimum_chrome_version"]
+                .as_str()
+                .unwrap_or(MIN_CHROMIUM_VERSION)
+                .to_string(),
+        ),
+        Engine::Gecko => manifest(ExtensionVariant::Firefox)?["browser_specific_settings"]["gecko"]
+            ["strict_min_version"]
+            .as_str()
+            .map(str::to_string),
+        Engine::WebKit | Engine::Other => None,
+    })
+}
+
+/// The browser the OS opens links in.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemBrowser {
+    /// How the OS identifies it: a bundle id on macOS, a ProgId on Windows
+    /// and a `.desktop` entry on Linux.
+    id: Option<String>,
+    /// `None` when it isn't a browser the extension supports.
+    browser: Option<Browser>,
+}
+
+/// Tells the UI which browser the OS opens links in and whether it's one the
+/// extension can be loaded into, so it can explain what to do otherwise.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn get_system_default_browser() -> Result<SystemBrowser, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let id = system_default_browser_id();
+        SystemBrowser {
+            browser: id.as_deref().and_then(browser_from_system_id),
+            id,
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn system_default_browser_id() -> Option<String> {
+    let output = Command::new("defaults")
+        .args(["read", "com.apple.LaunchServices/com.apple.launchservices.secure", "LSHandlers"])
+        .output()
+        .ok()?;
+    let handlers = String::from_utf8_lossy(&output.stdout);
+
+    // The output is an array of dictionaries, each naming a URL scheme and
+    // the bundle id of the app handling it, next to nested version
+    // preferences set to "-".
+    let mut entries = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in handlers.chars() {
+        match c {
+            '{' => {
+                depth += 1;
+                if depth == 1 {
+                    current.clear();
+                    continue;
+                }
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    entries.push(std::mem::take(&mut current));
+                }
+            }
+            _ => {}
+        }
+        if depth > 0 {
+            current.push(c);
+        }
+    }
+
+    entries
+        .iter()
+        .filter(|entry| entry.contains("LSHandlerURLScheme = https;"))
+        .find_map(|entry| {
+            entry
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("LSHandlerRoleAll = "))
+                .map(|value| value.trim_end_matches(';').trim_matches('"').to_string())
+                .find(|value| value != "-")
+        })
+}
+
+#[cfg(target_os = "macos")]
+fn browser_from_system_id(id: &str) -> Option<Browser> {
+    Browser::all().into_iter().find(|browser| {
+        browser
+            .platform()
+            .bundle_id
+            .as_deref()
+            .is_some_and(|bundle_id| bundle_id.eq_ignore_ascii_case(id))
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn system_default_browser_id() -> Option<String> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey("Software\\Microsoft\\Windows\\Shell\\Associations\\UrlAssociations\\https\\UserChoice")
+        .ok()?
+        .get_value("ProgId")
+        .ok()
+}
+
+/// Browsers register ProgIds like `ChromeHTML` or `FirefoxURL-308046B0AF4A39CB`,
+/// some with a suffix per install. The longest matching prefix wins, so a
+/// short one like `Opera` doesn't claim another browser's.
+#[cfg(target_os = "windows")]
+fn browser_from_system_id(id: &str) -> Option<Browser> {
+    Browser::all()
+        .into_iter()
+        .filter_map(|browser| Some((browser, browser.platform().prog_id.as_deref()?)))
+        .filter(|(_, prefix)| id.starts_with(prefix))
+        .max_by_key(|(_, prefix)| prefix.len())
+        .map(|(browser, _)| browser)
+}
+
+#[cfg(target_os = "linux")]
+fn system_default_browser_id() -> Option<String> {
+    let output = Command::new("xdg-settings")
+        .args(["get", "default-web-browser"])
+        .output()
+        .ok()?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!id.is_empty()).then_some(id)
+}
+
+/// Snaps name their entries `<snap>_<app>.desktop`, e.g.
+/// `firefox_firefox.desktop`.
+#[cfg(target_os = "linux")]
+fn browser_from_system_id(id: &str) -> Option<Browser> {
+    let entry = id.rsplit('_').next().unwrap_or(id);
+    Browser::all()
+        .into_iter()
+        .find(|browser| browser.platform().desktop_entries.iter().any(|known| known == entry))
+}
+
+/// The browser the user picked, as long as it's still installed.
+pub fn preferred_browser() -> Option<Browser> {
+    crate::config::load()
+        .ok()?
+        .browser
+        .preferred
+        .filter(|browser| browser.locate().is_some())
+}
+
+/// The preferred browser, or else the browser the OS opens links in when
+/// the extension supports it and it's installed, or else the first
+/// supported browser that is.
+pub fn installed_default_browser() -> Option<Browser> {
+    preferred_browser().or_else(|| {
+        system_default_browser_id()
+            .as_deref()
+            .and_then(browser_from_system_id)
+            .filter(|browser| browser.locate().is_some())
+            .or_else(|| Browser::all().into_iter().find(|browser| browser.locate().is_some()))
+    })
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_preferred_browser() -> Result<Option<Browser>, String> {
+    Ok(crate::config::load()?.browser.preferred)
+}
+
+/// Remembers the browser picked during onboarding, or forgets it with
+/// `None` so the system default is used again.
+#[tauri::command]
+#[apply(audited!)]
+pub fn set_preferred_browser(browser: Option<Browser>) -> Result<(), String> {
+    let mut settings = crate::config::load()?;
+    settings.browser.preferred = browser;
+    crate::config::save(&settings)
+}
+
+/// Like `installed_default_browser`, but Chrome when no supported browser
+/// can be found so the user at least gets a useful error.
+pub fn default_browser() -> Browser {
+    installed_default_browser().unwrap_or(Browser::CHROME)
+}
+
+/// Why a browser page couldn't be opened, for the UI to explain rather than
+/// show a raw error.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "reason")]
+pub enum OpenPageError {
+    /// None of the browsers the extension supports is installed.
+    NoSupportedBrowser,
+    /// Starting the browser failed, e.g. for store installs or executables
+    /// that aren't where they usually are. The page's address is on the
+    /// clipboard when `copied` is set, and `steps` say how to open it by hand.
+    #[serde(rename_all = "camelCase")]
+    LaunchFailed {
+        browser: Browser,
+        url: String,
+        error: String,
+        copied: bool,
+        steps: Vec<String>,
+    },
+}
+
+/// Opens the extensions page of `browser`, or of the default browser when
+/// none is given, and returns the browser that was opened.
+#[tauri::command]
+#[apply(audited!)]
+pub fn open_browser_extensions(app: AppHandle, browser: Option<Browser>) -> Result<Browser, OpenPageError> {
+    let browser = match browser {
+        Some(browser) => browser,
+        None => installed_default_browser().ok_or(OpenPageError::NoSupportedBrowser)?,
+    };
+    open_page(&app, browser, browser.extensions_url())?;
+    Ok(browser)
+}
+
+/// Opens `url` in `browser`. Pages like `chrome://extensions` can't be
+/// opened through the OS, so when starting the browser fails the address is
+/// put on the clipboard and `open-page-failed` tells the UI how to get there.
+pub fn open_page(app: &AppHandle, browser: Browser, url: &str) -> Result<(), OpenPageError> {
+    let Err(error) = browser.open_url(url) else {
+        return Ok(());
+    };
+    tracing::warn!("Could not open {} in {:?}: {}", url, browser, error);
+
+    let copied = match copy_to_clipboard(url) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!("Could not copy {} to the clipboard: {}", url, e);
+            false
+        }
+    };
+    let address_step = if copied {
+        t_with("openPage.pasteUrl", &[("url", url)])
+    } else {
+        t_with("openPage.typeUrl", &[("url", url)])
+    };
+    let failure = OpenPageError::LaunchFailed {
+        browser,
+        url: url.to_string(),
+        error,
+        copied,
+        steps: vec![
+            t_with("openPage.openBrowser", &[("browser", browser.display_name())]),
+            address_step,
+            t("openPage.pressEnter"),
+        ],
+    };
+    let _ = app.emit("open-page-failed", failure.clone());
+    Err(failure)
+}
+
+/// Puts `text` on the clipboard with the tool the OS ships, or on Linux the
+/// first of the usual Wayland and X11 tools that is installed.
+fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    #[cfg(target_os = "macos")]
+    let tools: &[(&str, &[&str])] = &[("pbcopy", &[])];
+    #[cfg(target_os = "windows")]
+    let tools: &[(&str, &[&str])] = &[("clip", &[])];
+    #[cfg(target_os = "linux")]
+    let tools: &[(&str, &[&str])] = &[
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+    ];
+
+    for (program, args) in tools {
+        let mut command = Command::new(program);
+        command.args(*args).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null());
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let Ok(mut child) = command.spawn() else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+        }
+        if child.wait().map_err(|e| e.to_string())?.success() {
+            return Ok(());
+        }
+    }
+    Err("No clipboard tool available".to_string())
+}
+
+const LINKEDIN_URL: &str = "https://www.linkedin.com";
+
+/// Opens LinkedIn in the browser the extension is loaded in, by default the
+/// default browser, and in `profile` (a profile directory such as
+/// `Profile 1`, by default the one the user picked) so the extension sees
+/// the session.
+#[tauri::command]
+#[apply(audited!)]
+pub fn open_linkedin(browser: Option<Browser>, profile: Option<String>) -> Result<Browser, String> {
+    let browser = browser.unwrap_or_else(default_browser);
+    match profile.or_else(|| browser.chosen_profile()) {
+        Some(profile) if browser.is_chromium() => {
+            browser.launch(&[profile_arg(&profile)?, LINKEDIN_URL.to_string()])?
+        }
+        _ => browser.open_url(LINKEDIN_URL)?,
+    }
+    Ok(browser)
+}
+
+/// The flag selecting a Chromium profile, refusing anything that isn't a
+/// plain directory name.
+pub fn profile_arg(profile: &str) -> Result<String, String> {
+    if profile.is_empty() || profile.contains(['/', '\\']) || profile == ".." {
+        return Err(format!("Invalid browser profile {:?}", profile));
+    }
+    Ok(format!("--profile-directory={}", profile))
+}
+
+impl Browser {
+    /// Opens `url` in the browser, in a new tab when it's already running.
+    pub fn open_url(self, url: &str) -> Result<(), String> {
+        #[cfg(target_os = "macos")]
+        {
+            Command::new("open")
+                .args(["-a", self.app_name()?, url])
+                .spawn()
+                .map_err(|e| e.to_string())?;
+        }
+
+        // Both accept the page to open as an argument to the executable.
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        {
+            Command::new(self.executable()?)
+                .arg(url)
+                .spawn()
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    /// Starts the browser with `args`. A browser that is already running
+    /// with the same user data dir hands the launch over to the running
+    /// instance, which ignores most flags.
+    pub fn launch(self, args: &[String]) -> Result<(), String> {
+        #[cfg(target_os = "macos")]
+        {
+            Command::new("open")
+                .args(["-n", "-a", self.app_name()?, "--args"])
+                .args(args)
+                .spawn()
+                .map_err(|e| e.to_string())?;
+        }
+
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        {
+            Command::new(self.executable()?)
+                .args(args)
+                .spawn()
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    fn executable(self) -> Result<PathBuf, String> {
+        self.locate()
+            .ok_or_else(|| t_with("browser.notInstalled", &[("browser", self.display_name())]))
+    }
+}
@@ -0,0 +1,169 @@
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::extension::ExtensionVersion;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Where the app's copy of an extension comes from: a zip in the app
+/// resources, which installs and code-signs much faster than hundreds of
+/// loose files, or a plain directory for downloaded hotfixes and builds
+/// from a checkout.
+pub enum Bundle {
+    Archive(PathBuf),
+    Dir(PathBuf),
+}
+
+impl Bundle {
+    /// The bundle at `path`: `<path>.zip` when there is one, otherwise the
+    /// directory itself if it holds a `manifest.json`.
+    pub fn find(path: &Path) -> Option<Bundle> {
+        let archive = path.with_extension("zip");
+        if archive.is_file() {
+            Some(Bundle::Archive(archive))
+        } else if path.join(MANIFEST_FILE).is_file() {
+            Some(Bundle::Dir(path.to_path_buf()))
+        } else {
+            None
+        }
+    }
+
+    /// Reads the file at `name`, a `/` separated path inside the bundle.
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Bundle::Archive(path) => {
+                let mut archive = open_archive(path)?;
+                let mut file = archive
+                    .by_name(name)
+                    .map_err(|e| format!("{} not found in {}: {}", name, path.display(), e))?;
+                let mut contents = Vec::new();
+                file.read_to_end(&mut contents).map_err(|e| e.to_string())?;
+                Ok(contents)
+            }
+            Bundle::Dir(path) => {
+                let relative = Path::new(name);
+                if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+                    return Err(format!("Invalid extension file path {:?}", name));
+                }
+                fs::read(path.join(relative)).map_err(|e| format!("{} not found in {}: {}", name, path.display(), e))
+            }
+        }
+    }
+
+    pub fn read_to_string(&self, name: &str) -> Result<String, String> {
+        String::from_utf8(self.read(name)?).map_err(|e| e.to_string())
+    }
+
+    pub fn manifest(&self) -> Result<ExtensionVersion, String> {
+        serde_json::from_str(&self.read_to_string(MANIFEST_FILE)?)
+            .map_err(|e| format!("Invalid extension manifest: {}", e))
+    }
+
+    /// Returns the number of files in the bundle and their total size.
+    pub fn size(&self) -> Result<(usize, u64), String> {
+        match self {
+            Bundle::Archive(path) => {
+                let mut archive = open_archive(path)?;
+                let mut files = 0;
+                let mut bytes = 0;
+                for index in 0..archive.len() {
+                    let file = archive.by_index_raw(index).map_err(|e| e.to_string())?;
+                    if !file.is_dir() {
+                        files += 1;
+                        bytes += file.size();
+                    }
+                }
+                Ok((files, bytes))
+            }
+            Bundle::Dir(path) => dir_size(path),
+        }
+    }
+
+    /// Returns the SHA-256 of every file in the bundle, keyed by its `/`
+    /// separated path.
+    pub fn hashes(&self) -> Result<BTreeMap<String, String>, String> {
+        let mut hashes = BTreeMap::new();
+        match self {
+            Bundle::Archive(path) => {
+                let mut archive = open_archive(path)?;
+                for index in 0..archive.len() {
+                    let mut file = archive.by_index(index).map_err(|e| e.to_string())?;
+                    if file.is_dir() {
+                        continue;
+                    }
+                    let mut hasher = Sha256::new();
+                    io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+                    hashes.insert(file.name().to_string(), hex::encode(hasher.finalize()));
+                }
+            }
+            Bundle::Dir(path) => crate::integrity::hash_dir(path, path, &mut hashes)?,
+        }
+        Ok(hashes)
+    }
+
+    /// Writes every file of the bundle into `dst`, calling `on_file` with the
+    /// size of each one. Archive entries are unpacked one at a time without
+    /// reading the whole archive into memory, keeping their permissions.
+    pub fn extract_to(&self, dst: &Path, on_file: &mut dyn FnMut(u64)) -> Result<(), String> {
+        match self {
+            Bundle::Archive(path) => {
+                let mut archive = open_archive(path)?;
+                for index in 0..archive.len() {
+                    let mut file = archive.by_index(index).map_err(|e| e.to_string())?;
+                    // Rejects entries like `../x` that would land outside `dst`.
+                    let name = file
+                        .enclosed_name()
+                        .ok_or_else(|| format!("Invalid path in extension archive: {}", file.name()))?;
+                    let target = dst.join(name);
+
+                    if file.is_dir() {
+                        fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+                        continue;
+                    }
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                    }
+                    let mut out = File::create(&target).map_err(|e| e.to_string())?;
+                    let bytes = io::copy(&mut file, &mut out).map_err(|e| e.to_string())?;
+
+                    #[cfg(unix)]
+                    if let Some(mode) = file.unix_mode() {
+                        use std::os::unix::fs::PermissionsExt;
+                        fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o777))
+                            .map_err(|e| e.to_string())?;
+                    }
+                    on_file(bytes);
+                }
+                Ok(())
+            }
+            Bundle::Dir(path) => crate::extension::copy_dir_recursive(path, dst, on_file),
+        }
+    }
+}
+
+fn open_archive(path: &Path) -> Result<zip::ZipArchive<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("Could not open {}: {}", path.display(), e))?;
+    zip::ZipArchive::new(BufReader::new(file)).map_err(|e| format!("Invalid archive {}: {}", path.display(), e))
+}
+
+/// Returns the number of files under `dir` and their total size.
+fn dir_size(dir: &Path) -> Result<(usize, u64), String> {
+    let mut files = 0;
+    let mut bytes = 0;
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        if path.is_dir() {
+            let (dir_files, dir_bytes) = dir_size(&path)?;
+            files += dir_files;
+            bytes += dir_bytes;
+        } else {
+            files += 1;
+            bytes += entry.metadata().map_err(|e| e.to_string())?.len();
+        }
+    }
+    Ok((files, bytes))
+}
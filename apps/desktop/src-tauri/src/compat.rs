@@ -0,0 +1,175 @@
+use crate::audit::audited;
+use crate::config::{self, ServerConfig};
+use macro_rules_attribute::apply;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Version of the local API contract between the app, the server and the
+/// extension. Must match the server's `PROTOCOL_VERSION`.
+const PROTOCOL_VERSION: u64 = 1;
+
+const COMPATIBLE_SERVER_VERSIONS: &str = ">=0.1.0, <0.2.0";
+const COMPATIBLE_EXTENSION_VERSIONS: &str = ">=0.1.0, <0.2.0";
+
+const VERSION_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the startup check waits for the server to come up.
+const STARTUP_CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatibilityReport {
+    app_version: String,
+    compatible: bool,
+    server: ComponentCompatibility,
+    extension: ComponentCompatibility,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentCompatibility {
+    version: Option<String>,
+    required: &'static str,
+    /// `None` when the version couldn't be determined.
+    compatible: Option<bool>,
+    instructions: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerVersion {
+    version: String,
+    protocol_version: u64,
+}
+
+/// Checks the server and extension versions against the ranges this build
+/// supports, emitting `compatibility-mismatch` when either is out of range.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn check_compatibility(app: AppHandle) -> Result<CompatibilityReport, String> {
+    let server_url = crate::server::current_url(&app)?;
+    let server_config = config::load()?.server;
+    let server = check_server(fetch_server_version(&server_config, &server_url).await);
+    let extension = check_extension(read_extension_version());
+
+    let report = CompatibilityReport {
+        app_version: app.package_info().version.to_string(),
+        compatible: server.compatible != Some(false) && extension.compatible != Some(false),
+        server,
+        extension,
+    };
+
+    if !report.compatible {
+        let _ = app.emit("compatibility-mismatch", report.clone());
+    }
+
+    Ok(report)
+}
+
+/// Waits for the server to answer and then runs the compatibility check once,
+/// so mismatches surface even if the UI never asks.
+pub fn check_on_startup(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let deadline = Instant::now() + STARTUP_CHECK_TIMEOUT;
+        while Instant::now() < deadline {
+            let ready = match (crate::server::current_url(&app), config::load()) {
+                (Ok(url), Ok(settings)) => fetch_server_version(&settings.server, &url).await.is_ok(),
+                _ => false,
+            };
+            if ready {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        if let Err(e) = check_compatibility(app).await {
+            tracing::warn!("Compatibility check failed: {}", e);
+        }
+    });
+}
+
+fn check_server(version: Result<ServerVersion, String>) -> ComponentCompatibility {
+    let version = match version {
+        Ok(version) => version,
+        Err(_) => return ComponentCompatibility::unknown(COMPATIBLE_SERVER_VERSIONS),
+    };
+
+    let compatible = version.protocol_version == PROTOCOL_VERSION
+        && version_matches(&version.version, COMPATIBLE_SERVER_VERSIONS);
+
+    ComponentCompatibility {
+        instructions: (!compatible).then(|| {
+            format!(
+                "The server (version {}) doesn't match this app. Reinstall ClaudIn, or if you \
+                 run your own server, update it to a version matching {}.",
+                version.version, COMPATIBLE_SERVER_VERSIONS
+            )
+        }),
+        version: Some(version.version),
+        required: COMPATIBLE_SERVER_VERSIONS,
+        compatible: Some(compatible),
+    }
+}
+
+fn check_extension(version: Option<String>) -> ComponentCompatibility {
+    let Some(version) = version else {
+        return ComponentCompatibility::unknown(COMPATIBLE_EXTENSION_VERSIONS);
+    };
+
+    let compatible = version_matches(&version, COMPATIBLE_EXTENSION_VERSIONS);
+
+    ComponentCompatibility {
+        instructions: (!compatible).then(|| {
+            format!(
+                "The installed extension (version {}) doesn't match this app. Extract the \
+                 extension again from the setup screen, then click reload on it in \
+                 chrome://extensions.",
+                version
+            )
+        }),
+        version: Some(version),
+        required: COMPATIBLE_EXTENSION_VERSIONS,
+        compatible: Some(compatible),
+    }
+}
+
+impl ComponentCompatibility {
+    fn unknown(required: &'static str) -> Self {
+        ComponentCompatibility {
+            version: None,
+            required,
+            compatible: None,
+            instructions: None,
+        }
+    }
+}
+
+fn version_matches(version: &str, range: &str) -> bool {
+    match (Version::parse(version), VersionReq::parse(range)) {
+        (Ok(version), Ok(range)) => range.matches(&version),
+        _ => false,
+    }
+}
+
+async fn fetch_server_version(server_config: &ServerConfig, base_url: &str) -> Result<ServerVersion, String> {
+    crate::server::server_get(server_config, &format!("{}/version", base_url))
+        .timeout(VERSION_REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .error_for_status()
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn read_extension_version() -> Option<String> {
+    crate::extension::ExtensionRegistry::default_extension()
+        .version()
+        .ok()?
+        .map(|manifest| manifest.version)
+}
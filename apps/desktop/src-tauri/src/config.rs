@@ -0,0 +1,1258 @@
+use macro_rules_attribute::apply;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer};
+use tauri::async_runtime::Receiver;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audit::audited;
+use crate::browser::Browser;
+use crate::i18n::{t, t_with};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Marked a finished setup before it moved into the settings.
+const LEGACY_SETUP_FILE: &str = ".setup_complete";
+
+/// Key of the settings file's layout version, kept out of `Settings` since
+/// only loading and saving deal with it.
+const SCHEMA_VERSION_KEY: &str = "schemaVersion";
+
+/// Layout of the settings this build writes. A change that older files
+/// would be misread under, like renaming or moving a key, bumps it and adds
+/// a migration. New fields don't need one since missing ones get defaults.
+const SCHEMA_VERSION: u64 = 3;
+
+/// Upgrades raw settings by one schema version.
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// Each upgrades settings of the version at its index to the next one.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] =
+    [migrate_setup_marker, migrate_setup_steps, migrate_sparse_settings];
+
+/// Settings for everyone on the machine, like where the extension is
+/// installed, which each user's settings file overrides.
+#[cfg(windows)]
+const MACHINE_SETTINGS_DIR: &str = "ClaudIn";
+#[cfg(target_os = "macos")]
+const MACHINE_SETTINGS_DIR: &str = "/Library/Application Support/ClaudIn";
+#[cfg(target_os = "linux")]
+const MACHINE_SETTINGS_DIR: &str = "/etc/claudin";
+
+/// How long the config dir has to stay quiet before the settings file is
+/// read again, since editors write files in several steps.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Settings as of the last `settings-changed` event, which the next one is
+/// compared against.
+static BROADCAST: Mutex<Option<Value>> = Mutex::new(None);
+
+/// Overrides the config dir, which holds the settings and logs.
+const CONFIG_DIR_VAR: &str = "CLAUDIN_CONFIG_DIR";
+
+/// Overrides where the default server keeps its database. The server reads
+/// it under the same name.
+const DATA_DIR_VAR: &str = "CLAUDIN_DATA_DIR";
+
+/// Environment variables that override a setting, with the path of the
+/// setting they override. Overridden settings are never saved, so the
+/// settings file keeps what the user set.
+const ENV_OVERRIDES: [(&str, &str); 6] = [
+    ("CLAUDIN_SERVER_PORT", "/server/port"),
+    ("CLAUDIN_SERVER_PROFILE", "/server/profile"),
+    ("CLAUDIN_SERVER_URL", "/server/externalUrl"),
+    ("CLAUDIN_API_BASE_URL", "/server/apiBaseUrl"),
+    ("CLAUDIN_LOG_LEVEL", "/server/logLevel"),
+    ("CLAUDIN_EXTENSION_DIR", "/extension/installDir"),
+];
+
+/// Below this the server can't even load its dependencies.
+const MIN_MEMORY_LIMIT_MB: u32 = 128;
+
+/// App settings persisted as JSON in the config dir. Missing fields fall back
+/// to the machine's settings and then their defaults, so the file only holds
+/// what the user changed and older files keep loading.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub server: ServerConfig,
+    pub extension: ExtensionConfig,
+    pub browser: BrowserConfig,
+    pub audit: AuditConfig,
+    pub setup: SetupConfig,
+    /// Language of the strings the app produces itself, like notifications,
+    /// the system's when unset.
+    pub locale: Option<String>,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SetupConfig {
+    /// Steps of the setup wizard the user got through, so an interrupted
+    /// setup picks up where it was left.
+    pub completed_steps: BTreeSet<SetupStep>,
+    /// Version of the app that ran last, to tell a first run or an upgrade
+    /// from a normal start.
+    pub last_run_version: Option<String>,
+    /// Onboarding checklist items the user has done.
+    pub checklist: BTreeSet<ChecklistItem>,
+}
+
+/// Things onboarding asks the user to do beyond the setup wizard's steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChecklistItem {
+    ExtensionLoaded,
+    ExtensionPinned,
+    LinkedInConnected,
+    FirstMessageSent,
+}
+
+impl ChecklistItem {
+    pub const ALL: [ChecklistItem; 4] = [
+        ChecklistItem::ExtensionLoaded,
+        ChecklistItem::ExtensionPinned,
+        ChecklistItem::LinkedInConnected,
+        ChecklistItem::FirstMessageSent,
+    ];
+}
+
+/// Steps of onboarding, in the order the setup wizard goes through them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SetupStep {
+    ExtractExtension,
+    LoadExtension,
+    VerifyConnection,
+    SignInToLinkedIn,
+}
+
+impl SetupStep {
+    pub const ALL: [SetupStep; 4] = [
+        SetupStep::ExtractExtension,
+        SetupStep::LoadExtension,
+        SetupStep::VerifyConnection,
+        SetupStep::SignInToLinkedIn,
+    ];
+}
+
+impl SetupConfig {
+    /// The first step not done yet, `None` once setup is complete.
+    pub fn current_step(&self) -> Option<SetupStep> {
+        SetupStep::ALL.into_iter().find(|step| !self.completed_steps.contains(step))
+    }
+
+    /// The setup wizard isn't shown again once every step is done.
+    pub fn is_complete(&self) -> bool {
+        self.current_step().is_none()
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BrowserConfig {
+    /// Browser the user picked during onboarding, which commands use when
+    /// they aren't given one.
+    pub preferred: Option<Browser>,
+    /// Profile directory the user picked in each browser, which install
+    /// checks and launches stick to.
+    pub profiles: BTreeMap<Browser, String>,
+    /// The user allowed checking the browser's cookies for a LinkedIn
+    /// session during onboarding.
+    pub session_check_allowed: bool,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditConfig {
+    /// Rotated audit logs kept next to the active one, 5 MB each. The
+    /// oldest is deleted past that; 0 keeps them all.
+    pub max_files: u32,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        AuditConfig {
+            max_files: crate::audit::DEFAULT_MAX_FILES,
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExtensionConfig {
+    /// Folder the extension and its backups are extracted into, instead of
+    /// `~/ClaudIn`.
+    pub install_dir: Option<PathBuf>,
+    /// Restores extension files as soon as something else deletes or
+    /// changes them.
+    pub auto_repair: bool,
+    /// Id of the extension's Chrome Web Store listing, for installing it
+    /// from there instead of loading the extracted files.
+    pub store_id: Option<String>,
+}
+
+/// Values handed to the local server when it is spawned.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServerConfig {
+    /// Which server to run; unset means the one matching the build type.
+    pub profile: Option<ServerProfile>,
+    /// Server used by the `custom` profile instead of spawning one.
+    pub external_url: Option<String>,
+    /// Sent as a bearer token to the external server, for shared backends
+    /// that sit behind an authenticating proxy. Kept in the credential store
+    /// when saved, so it's only ever set on configs coming from the UI or
+    /// from a settings file written by an older version; an empty token
+    /// removes the stored one.
+    #[serde(skip_serializing)]
+    pub external_token: Option<String>,
+    /// Overrides the LLM API the server talks to (OpenRouter by default).
+    pub api_base_url: Option<String>,
+    /// Pins the server to a port instead of picking a free one.
+    pub port: Option<u16>,
+    /// Also serves the API over https on this port, with a certificate
+    /// signed by a local authority the user chooses to trust.
+    pub tls_port: Option<u16>,
+    /// Web origins approved to call the local API besides the extension
+    /// and the app itself, like `https://example.com`.
+    pub allowed_origins: Vec<String>,
+    /// Keeps the server's database encrypted on disk, with a key held in
+    /// the OS's credential store. Turning it on or off converts the
+    /// existing database the next time the server starts.
+    pub encrypt_data: bool,
+    pub log_level: LogLevel,
+    /// Defers starting the server until something actually needs it.
+    pub lazy_start: bool,
+    /// Runs the server at a lower scheduling priority so syncs don't make
+    /// the rest of the machine sluggish.
+    pub low_priority: bool,
+    /// Caps the server's JavaScript heap, in megabytes. Only Node reads the
+    /// flag this sets, so it only applies to the dev profile; the bundled
+    /// sidecar is compiled with Bun and ignores it. Release builds don't
+    /// have the setting.
+    #[cfg_attr(not(debug_assertions), serde(skip))]
+    pub memory_limit_mb: Option<u32>,
+    /// Script run by the `dev` profile instead of the workspace sources.
+    /// Relative paths are resolved against the resource dir.
+    pub script_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerProfile {
+    /// Runs the server sources with tsx.
+    Dev,
+    /// Runs the bundled sidecar binary.
+    Prod,
+    /// Doesn't spawn anything and talks to `external_url`.
+    Custom,
+}
+
+/// Whether the app runs its own server or talks to a shared one.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendMode {
+    Local,
+    Remote,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn profile(&self) -> ServerProfile {
+        self.profile.unwrap_or(if cfg!(debug_assertions) {
+            ServerProfile::Dev
+        } else {
+            ServerProfile::Prod
+        })
+    }
+
+    /// The custom profile is the remote mode; every other profile spawns a
+    /// local server.
+    pub fn backend_mode(&self) -> BackendMode {
+        match self.profile() {
+            ServerProfile::Custom => BackendMode::Remote,
+            ServerProfile::Dev | ServerProfile::Prod => BackendMode::Local,
+        }
+    }
+
+    /// Token to authenticate with, which is only ever sent to a remote backend.
+    pub fn auth_token(&self) -> Option<String> {
+        match self.backend_mode() {
+            BackendMode::Remote => match &self.external_token {
+                Some(token) => Some(token.clone()).filter(|token| !token.is_empty()),
+                None => crate::secrets::external_token()
+                    .inspect_err(|e| tracing::warn!("Failed to read the external server token: {}", e))
+                    .ok()
+                    .flatten(),
+            },
+            BackendMode::Local => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(url) = &self.api_base_url {
+            validate_http_url(url, &t("settings.apiBaseUrl"))?;
+        }
+
+        match (&self.external_url, self.profile()) {
+            (Some(url), _) => validate_http_url(url, &t("settings.externalServerUrl"))?,
+            (None, ServerProfile::Custom) => return Err(t("settings.customProfileNeedsUrl")),
+            _ => {}
+        }
+
+        if self.script_path.as_ref().is_some_and(|p| p.as_os_str().is_empty()) {
+            return Err(t("settings.emptyScriptPath"));
+        }
+
+        if self.memory_limit_mb.is_some_and(|mb| mb < MIN_MEMORY_LIMIT_MB) {
+            return Err(t_with("settings.memoryLimit", &[("mb", &MIN_MEMORY_LIMIT_MB.to_string())]));
+        }
+
+        if self.port == Some(0) {
+            return Err(t("settings.port"));
+        }
+
+        for origin in &self.allowed_origins {
+            normalize_origin(origin)?;
+        }
+
+        match self.tls_port {
+            Some(0) => return Err(t("settings.port")),
+            Some(port) if self.port == Some(port) => return Err(t("settings.tlsPortClash")),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// `paths::guard` for the script path when it changed, resolved the way
+    /// the server resolves it.
+    pub fn guard_paths(&self, app: &AppHandle, current: &ServerConfig) -> Result<(), String> {
+        let resource_dir = app.path().resource_dir().map_err(|e| e.to_string())?;
+        let resolve = |script: &PathBuf| resource_dir.join(script);
+        crate::paths::guard_changed(
+            app,
+            current.script_path.as_ref().map(resolve).as_deref(),
+            self.script_path.as_ref().map(resolve).as_deref(),
+        )
+    }
+
+    /// Environment variables the server reads its configuration from.
+    pub fn to_env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![("CLAUDIN_LOG_LEVEL", self.log_level.as_str().to_string())];
+        if self.encrypt_data {
+            env.push(("CLAUDIN_ENCRYPT_DATA", "1".to_string()));
+        }
+        if let Some(url) = &self.api_base_url {
+            env.push(("CLAUDIN_API_BASE_URL", url.clone()));
+        }
+        if let Some(mb) = self.memory_limit_mb.filter(|_| self.profile() == ServerProfile::Dev) {
+            // Keep whatever options the user already runs Node with.
+            let mut options = std::env::var("NODE_OPTIONS").unwrap_or_default();
+            if !options.is_empty() {
+                options.push(' ');
+            }
+            options.push_str(&format!("--max-old-space-size={}", mb));
+            env.push(("NODE_OPTIONS", options));
+        }
+        env
+    }
+}
+
+impl ExtensionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(dir) = &self.install_dir {
+            // Relative paths would depend on where the app happens to be started.
+            if !dir.is_absolute() {
+                return Err(t("settings.relativeInstallDir"));
+            }
+        }
+        if let Some(id) = &self.store_id {
+            if !crate::native_messaging::is_extension_id(id) {
+                return Err(t_with("settings.invalidStoreId", &[("id", &format!("{:?}", id))]));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.server.validate()?;
+        self.extension.validate()?;
+        if let Some(locale) = &self.locale {
+            if !crate::i18n::is_supported(locale) {
+                return Err(t_with("settings.unsupportedLocale", &[("locale", &format!("{:?}", locale))]));
+            }
+        }
+        Ok(())
+    }
+
+    /// Path settings have to get past `paths::guard` when they change, so
+    /// a crafted update can't point the app at arbitrary files.
+    fn guard_paths(&self, app: &AppHandle, current: &Settings) -> Result<(), String> {
+        crate::paths::guard_changed(
+            app,
+            current.extension.install_dir.as_deref(),
+            self.extension.install_dir.as_deref(),
+        )?;
+        self.server.guard_paths(app, &current.server)
+    }
+}
+
+pub fn validate_http_url(url: &str, what: &str) -> Result<(), String> {
+    let parsed = tauri::Url::parse(url)
+        .map_err(|e| t_with("settings.invalidUrl", &[("what", what), ("error", &e.to_string())]))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(t_with("settings.urlScheme", &[("what", what)]));
+    }
+    Ok(())
+}
+
+/// Reduces an origin to the `scheme://host[:port]` form browsers send in
+/// the `Origin` header, rejecting anything with a path or query since that
+/// would never match.
+pub fn normalize_origin(origin: &str) -> Result<String, String> {
+    let invalid = || t_with("settings.invalidOrigin", &[("origin", origin)]);
+    let parsed = tauri::Url::parse(origin.trim()).map_err(|_| invalid())?;
+    let host = parsed.host_str().filter(|host| !host.is_empty()).ok_or_else(invalid)?;
+    if !matches!(parsed.path(), "" | "/") || parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(invalid());
+    }
+    match parsed.scheme() {
+        "http" | "https" => Ok(parsed.origin().ascii_serialization()),
+        "chrome-extension" | "moz-extension" | "safari-web-extension" => Ok(format!("{}://{}", parsed.scheme(), host)),
+        _ => Err(invalid()),
+    }
+}
+
+/// The config dir: `CLAUDIN_CONFIG_DIR` when set, then the portable data
+/// dir, then the OS's config dir.
+pub fn config_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = env_path(CONFIG_DIR_VAR) {
+        return Ok(dir);
+    }
+    if let Some(dir) = crate::portable::data_dir() {
+        return Ok(dir.join("config"));
+    }
+    let config_dir = dirs::config_dir().ok_or("Could not find config directory")?;
+    Ok(config_dir.join("claudin"))
+}
+
+/// Where the default server keeps its database when not where it would by
+/// itself: `CLAUDIN_DATA_DIR` when set, then the portable data dir.
+pub fn server_data_dir() -> Option<PathBuf> {
+    env_path(DATA_DIR_VAR).or_else(|| crate::portable::data_dir().map(|dir| dir.join("server")))
+}
+
+/// The settings file shared by every user of the machine. Portable installs
+/// carry all their settings with them, so they have none.
+pub fn machine_settings_path() -> Option<PathBuf> {
+    if crate::portable::is_enabled() {
+        return None;
+    }
+    let dir = if cfg!(windows) {
+        PathBuf::from(std::env::var_os("ProgramData")?).join(MACHINE_SETTINGS_DIR)
+    } else {
+        PathBuf::from(MACHINE_SETTINGS_DIR)
+    };
+    Some(dir.join(SETTINGS_FILE))
+}
+
+/// The machine's settings as written by an administrator or installer,
+/// without migrations. A file that doesn't parse is ignored so one broken
+/// file doesn't keep every user from starting the app.
+fn machine_settings() -> Map<String, Value> {
+    let Some(path) = machine_settings_path() else {
+        return Map::new();
+    };
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Map::new();
+    };
+    match parse(&contents) {
+        Ok(mut raw) => {
+            raw.remove(SCHEMA_VERSION_KEY);
+            raw
+        }
+        Err(e) => {
+            tracing::warn!("Ignoring machine settings {}: {}", path.display(), e);
+            Map::new()
+        }
+    }
+}
+
+/// What settings the user didn't change resolve to: the defaults with the
+/// machine's settings on top.
+fn base_settings() -> Value {
+    let mut base = serde_json::to_value(Settings::default()).unwrap_or_default();
+    merge_patch(&mut base, Value::Object(machine_settings()));
+    base
+}
+
+/// The parts of `value` that differ from `base`, `None` when nothing does.
+fn diff(base: &Value, value: &Value) -> Option<Value> {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            let changed: Map<String, Value> = value
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), diff(base.get(key).unwrap_or(&Value::Null), value)?)))
+                .collect();
+            Some(Value::Object(changed)).filter(|changed| changed.as_object().is_some_and(|c| !c.is_empty()))
+        }
+        (base, value) if base == value => None,
+        (_, value) => Some(value.clone()),
+    }
+}
+
+fn env_path(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var).filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+/// The environment variables currently overriding a path or setting.
+pub fn active_overrides() -> Vec<&'static str> {
+    [CONFIG_DIR_VAR, DATA_DIR_VAR]
+        .into_iter()
+        .chain(ENV_OVERRIDES.iter().map(|(var, _)| *var))
+        .filter(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+        .collect()
+}
+
+/// Reads the active profile's settings.
+pub fn load() -> Result<Settings, String> {
+    load_profile(&crate::profiles::active())
+}
+
+/// Reads the settings of `profile`, upgrading a file written by an older
+/// version first. The upgraded file is saved right away, after backing up
+/// the config. Environment overrides are applied on top.
+pub fn load_profile(profile: &str) -> Result<Settings, String> {
+    let path = settings_path(profile)?;
+    if !path.exists() {
+        return upgrade(Map::new()).and_then(|(settings, _)| apply_overrides(settings));
+    }
+    let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let (settings, version) = upgrade(parse(&contents)?)?;
+    if version < SCHEMA_VERSION {
+        crate::backup::snapshot("migration")?;
+        save_profile(profile, &settings)?;
+        tracing::info!("Upgraded settings from schema {} to {}", version, SCHEMA_VERSION);
+    } else if settings.server.external_token.is_some() {
+        // Saving moves the token of older versions to the credential store.
+        save_profile(profile, &settings)?;
+        tracing::info!("Moved the external server token to the credential store");
+    }
+    apply_overrides(drop_unapproved_paths(settings))
+}
+
+/// Forgets an extension folder in the settings file the user never picked,
+/// like one from a hand edit, so the default folder is used instead. It
+/// runs before the overrides: folders set through the environment or by
+/// policy come from whoever runs the app, and are trusted.
+fn drop_unapproved_paths(mut settings: Settings) -> Settings {
+    if let Some(dir) = &settings.extension.install_dir {
+        if crate::paths::ensure_allowed(dir).is_err() {
+            tracing::warn!("Ignoring extension folder {}, it wasn't picked in the app", dir.display());
+            settings.extension.install_dir = None;
+        }
+    }
+    settings
+}
+
+/// Applies environment overrides, then the settings an administrator
+/// manages, which win over everything. Environment values that aren't
+/// valid JSON, like most strings, are taken as strings.
+fn apply_overrides(settings: Settings) -> Result<Settings, String> {
+    let overrides: Vec<(&str, &str, String)> = ENV_OVERRIDES
+        .iter()
+        .filter_map(|(var, pointer)| Some((*var, *pointer, std::env::var(var).ok().filter(|v| !v.is_empty())?)))
+        .collect();
+    let managed = managed_settings();
+    if overrides.is_empty() && managed.is_empty() {
+        return Ok(settings);
+    }
+
+    let mut raw = serde_json::to_value(&settings).map_err(|e| e.to_string())?;
+    for (var, pointer, value) in overrides {
+        let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+        set_pointer(&mut raw, pointer, Some(value));
+        let settings: Result<Settings, _> = serde_json::from_value(raw.clone());
+        if let Err(e) = settings {
+            return Err(format!("Invalid {}: {}", var, e));
+        }
+    }
+    // A bad policy mustn't keep the app from starting, so it's skipped.
+    for (key, pointer, value) in managed {
+        let previous = raw.pointer(&pointer).cloned();
+        set_pointer(&mut raw, &pointer, Some(value.clone()));
+        if let Err(e) = serde_json::from_value::<Settings>(raw.clone()) {
+            tracing::warn!("Ignoring invalid policy for {}: {}", key, e);
+            set_pointer(&mut raw, &pointer, previous);
+        }
+    }
+    serde_json::from_value(raw).map_err(|e| e.to_string())
+}
+
+/// Settings pinned by policy, with the JSON pointer of each. Policies for
+/// settings that don't exist are left out.
+fn managed_settings() -> Vec<(String, String, Value)> {
+    let defaults = serde_json::to_value(Settings::default()).unwrap_or_default();
+    crate::policy::app_policies()
+        .iter()
+        .map(|(key, value)| (key.clone(), format!("/{}", key.replace('.', "/")), value.clone()))
+        .filter(|(_, pointer, _)| defaults.pointer(pointer).is_some())
+        .collect()
+}
+
+/// Dotted paths of the settings an administrator manages, which users
+/// can't change.
+pub fn managed_keys() -> Vec<String> {
+    managed_settings().into_iter().map(|(key, _, _)| key).collect()
+}
+
+/// Pointers of the settings the environment currently overrides.
+fn overridden_pointers() -> Vec<&'static str> {
+    ENV_OVERRIDES
+        .iter()
+        .filter(|(var, _)| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+        .map(|(_, pointer)| *pointer)
+        .collect()
+}
+
+/// Sets the value at a JSON pointer whose parent exists, or removes it.
+fn set_pointer(raw: &mut Value, pointer: &str, value: Option<Value>) {
+    let (parent, key) = pointer.rsplit_once('/').unwrap_or(("", pointer));
+    let Some(Value::Object(parent)) = raw.pointer_mut(parent) else {
+        return;
+    };
+    match value {
+        Some(value) => parent.insert(key.to_string(), value),
+        None => parent.remove(key),
+    };
+}
+
+fn parse(contents: &str) -> Result<Map<String, Value>, String> {
+    match serde_json::from_str(contents).map_err(|e| format!("Invalid settings file: {}", e))? {
+        Value::Object(raw) => Ok(raw),
+        _ => Err("Invalid settings file: not an object".to_string()),
+    }
+}
+
+/// Runs the migrations raw settings need and resolves them over the
+/// machine's settings, returning the settings along with the version they
+/// were at.
+fn upgrade(mut raw: Map<String, Value>) -> Result<(Settings, u64), String> {
+    // Files from before versioning have no version.
+    let version = match raw.remove(SCHEMA_VERSION_KEY) {
+        Some(version) => version.as_u64().ok_or("Invalid settings file: bad schema version")?,
+        None => 0,
+    };
+    if version > SCHEMA_VERSION {
+        tracing::warn!(
+            "Settings are from a newer version (schema {}), settings it added are ignored",
+            version
+        );
+    }
+    for migration in MIGRATIONS.iter().skip(version as usize) {
+        migration(&mut raw)?;
+    }
+
+    let mut resolved = base_settings();
+    merge_patch(&mut resolved, Value::Object(raw));
+    let settings = serde_json::from_value(resolved).map_err(|e| format!("Invalid settings file: {}", e))?;
+    Ok((settings, version))
+}
+
+pub fn save(settings: &Settings) -> Result<(), String> {
+    save_profile(&crate::profiles::active(), settings)
+}
+
+pub fn save_profile(profile: &str, settings: &Settings) -> Result<(), String> {
+    if let Some(token) = &settings.server.external_token {
+        crate::secrets::set_external_token(token)?;
+    }
+    let path = settings_path(profile)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, to_file_contents(settings, &path)?).map_err(|e| e.to_string())
+}
+
+/// Only settings that differ from the machine's are written. Overridden and
+/// managed ones are written as they are in the settings file at `path`
+/// rather than with the value from the environment or policy.
+fn to_file_contents(settings: &Settings, path: &Path) -> Result<String, String> {
+    let mut raw = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    let overridden: Vec<String> = overridden_pointers()
+        .into_iter()
+        .map(String::from)
+        .chain(managed_settings().into_iter().map(|(_, pointer, _)| pointer))
+        .collect();
+    if !overridden.is_empty() {
+        let saved = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+            .unwrap_or(Value::Null);
+        for pointer in overridden {
+            set_pointer(&mut raw, &pointer, saved.pointer(&pointer).cloned());
+        }
+    }
+    let mut raw = diff(&base_settings(), &raw).unwrap_or_else(|| Value::Object(Map::new()));
+    if let Value::Object(raw) = &mut raw {
+        raw.insert(SCHEMA_VERSION_KEY.to_string(), Value::from(SCHEMA_VERSION));
+    }
+    serde_json::to_string_pretty(&raw).map_err(|e| e.to_string())
+}
+
+/// 0 to 1: a finished setup was marked by a file of its own.
+fn migrate_setup_marker(raw: &mut Map<String, Value>) -> Result<(), String> {
+    if legacy_setup_file()?.exists() {
+        let setup = raw.entry("setup").or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(setup) = setup {
+            setup.insert("complete".to_string(), Value::Bool(true));
+        }
+    }
+    Ok(())
+}
+
+/// 1 to 2: setup was a single flag rather than a list of steps.
+fn migrate_setup_steps(raw: &mut Map<String, Value>) -> Result<(), String> {
+    let Some(Value::Object(setup)) = raw.get_mut("setup") else {
+        return Ok(());
+    };
+    if setup.remove("complete") == Some(Value::Bool(true)) {
+        let steps = serde_json::to_value(SetupStep::ALL).map_err(|e| e.to_string())?;
+        setup.insert("completedSteps".to_string(), steps);
+    }
+    Ok(())
+}
+
+/// 2 to 3: every setting was written, so the machine's settings could never
+/// apply. Values that are the defaults are taken to be ones the user never
+/// changed.
+fn migrate_sparse_settings(raw: &mut Map<String, Value>) -> Result<(), String> {
+    let defaults = serde_json::to_value(Settings::default()).map_err(|e| e.to_string())?;
+    *raw = match diff(&defaults, &Value::Object(std::mem::take(raw))) {
+        Some(Value::Object(changed)) => changed,
+        _ => Map::new(),
+    };
+    Ok(())
+}
+
+/// The default profile's settings are directly in the config dir, every
+/// other profile's in its own dir.
+fn settings_path(profile: &str) -> Result<PathBuf, String> {
+    Ok(crate::server::profile_dir(profile)?.join(SETTINGS_FILE))
+}
+
+pub fn legacy_setup_file() -> Result<PathBuf, String> {
+    Ok(config_dir()?.join(LEGACY_SETUP_FILE))
+}
+
+/// Settings along with which of them users can't change.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsView {
+    #[serde(flatten)]
+    settings: Settings,
+    /// Dotted paths of the settings an administrator manages.
+    managed: Vec<String>,
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_settings() -> Result<SettingsView, String> {
+    Ok(SettingsView {
+        settings: load()?,
+        managed: managed_keys(),
+    })
+}
+
+/// Where a setting's value comes from, each overriding the ones before.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingSource {
+    Default,
+    Machine,
+    User,
+    Environment,
+    Policy,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingSources {
+    /// `None` in portable mode, which has no machine settings.
+    machine_path: Option<String>,
+    user_path: String,
+    /// Source of every setting, by dotted path.
+    sources: BTreeMap<String, SettingSource>,
+}
+
+/// Tells where each of the active profile's settings comes from, for the
+/// settings screen to say why a value isn't the one the user expects.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_setting_sources() -> Result<SettingSources, String> {
+    let profile = crate::profiles::active();
+    let path = settings_path(&profile)?;
+    let settings = load_profile(&profile)?;
+    let user = match fs::read_to_string(&path) {
+        Ok(contents) => Value::Object(parse(&contents)?),
+        Err(_) => Value::Null,
+    };
+    let machine = Value::Object(machine_settings());
+    let overridden = overridden_pointers();
+    let managed: Vec<String> = managed_settings().into_iter().map(|(_, pointer, _)| pointer).collect();
+
+    let mut keys = Vec::new();
+    leaf_keys(&serde_json::to_value(&settings).map_err(|e| e.to_string())?, String::new(), &mut keys);
+    let sources = keys
+        .into_iter()
+        .map(|key| {
+            let pointer = format!("/{}", key.replace('.', "/"));
+            let source = if managed.contains(&pointer) {
+                SettingSource::Policy
+            } else if overridden.contains(&pointer.as_str()) {
+                SettingSource::Environment
+            } else if user.pointer(&pointer).is_some() {
+                SettingSource::User
+            } else if machine.pointer(&pointer).is_some() {
+                SettingSource::Machine
+            } else {
+                SettingSource::Default
+            };
+            (key, source)
+        })
+        .collect();
+
+    Ok(SettingSources {
+        machine_path: machine_settings_path().map(|path| path.to_string_lossy().to_string()),
+        user_path: path.to_string_lossy().to_string(),
+        sources,
+    })
+}
+
+fn leaf_keys(value: &Value, prefix: String, keys: &mut Vec<String>) {
+    let Value::Object(values) = value else {
+        keys.push(prefix);
+        return;
+    };
+    for (name, value) in values {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        leaf_keys(value, path, keys);
+    }
+}
+
+/// Applies `changes`, a JSON merge patch of the settings where `null` resets
+/// a field to the machine's value or its default, and saves the result if
+/// it's valid. The servers
+/// are restarted when their configuration changed, and the windows hear
+/// about it through `settings-changed`.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn update_settings(app: AppHandle, changes: Value) -> Result<Settings, String> {
+    let current = load()?;
+    let mut merged = serde_json::to_value(&current).map_err(|e| e.to_string())?;
+    merge_patch(&mut merged, changes);
+    let mut resolved = base_settings();
+    merge_patch(&mut resolved, merged);
+    let settings: Settings = serde_json::from_value(resolved).map_err(|e| format!("Invalid settings: {}", e))?;
+    settings.validate()?;
+
+    let mut changed = Vec::new();
+    let before = serde_json::to_value(&current).map_err(|e| e.to_string())?;
+    let after = serde_json::to_value(&settings).map_err(|e| e.to_string())?;
+    changed_keys(&before, &after, String::new(), &mut changed);
+    let managed = managed_keys();
+    if let Some(key) = changed.iter().find(|key| managed.contains(key)) {
+        return Err(t_with("settings.managed", &[("key", key)]));
+    }
+    settings.guard_paths(&app, &current)?;
+
+    save(&settings)?;
+    broadcast(&app, &settings)?;
+
+    if settings.server != current.server {
+        crate::server::apply_config(&app, &settings.server).await?;
+    }
+    Ok(settings)
+}
+
+/// Payload of `settings-changed`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsChanged {
+    /// Dotted paths of the fields that changed, like `server.port`.
+    keys: Vec<String>,
+    settings: Settings,
+}
+
+/// Emits `settings-changed` to every window when `settings` differ from
+/// the ones last broadcast, returning the keys that changed.
+pub fn broadcast(app: &AppHandle, settings: &Settings) -> Result<Vec<String>, String> {
+    crate::i18n::apply(settings.locale.as_deref());
+    crate::audit::apply(&settings.audit);
+    let current = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    let mut last = BROADCAST.lock().unwrap();
+    let mut keys = Vec::new();
+    if let Some(previous) = last.as_ref() {
+        changed_keys(previous, &current, String::new(), &mut keys);
+    }
+    let first = last.is_none();
+    *last = Some(current);
+    drop(last);
+
+    // The first call only records what the windows started out with.
+    if first || keys.is_empty() {
+        return Ok(Vec::new());
+    }
+    tracing::debug!("Settings changed: {}", keys.join(", "));
+    let _ = app.emit("settings-changed", SettingsChanged {
+        keys: keys.clone(),
+        settings: settings.clone(),
+    });
+    // The extension's origin depends on where it's installed and its store id.
+    if keys.iter().any(|key| key.starts_with("extension.")) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { crate::server::refresh_allowed_origins(&app).await });
+    }
+    Ok(keys)
+}
+
+fn changed_keys(previous: &Value, current: &Value, prefix: String, keys: &mut Vec<String>) {
+    let (Value::Object(previous), Value::Object(current)) = (previous, current) else {
+        if previous != current && !prefix.is_empty() {
+            keys.push(prefix);
+        }
+        return;
+    };
+    let mut names: Vec<&String> = previous.keys().chain(current.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        changed_keys(
+            previous.get(name).unwrap_or(&Value::Null),
+            current.get(name).unwrap_or(&Value::Null),
+            path,
+            keys,
+        );
+    }
+}
+
+/// Payload of `settings-invalid`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsInvalid {
+    path: String,
+    error: String,
+}
+
+/// Picks up changes to the settings file, whether a command saved it or
+/// it was edited by hand, and broadcasts them. Commands that have the app
+/// at hand broadcast and apply their changes right away; for everything
+/// else the servers are restarted here when their configuration changed.
+/// A file that doesn't load or validate, or whose paths don't get past
+/// `paths::guard`, emits `settings-invalid` instead and is ignored until it
+/// changes again.
+pub fn start_watcher(app: &AppHandle) {
+    let dir = match config_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("Settings watcher failed: {}", e);
+            return;
+        }
+    };
+    let (tx, rx) = tauri::async_runtime::channel(16);
+    let started = new_debouncer(WATCH_DEBOUNCE, move |events: DebounceEventResult| {
+        let _ = tx.blocking_send(events);
+    })
+    .and_then(|mut debouncer| {
+        // Profiles keep their settings in subfolders.
+        debouncer.watcher().watch(&dir, RecursiveMode::Recursive)?;
+        Ok(debouncer)
+    });
+    match started {
+        Ok(debouncer) => {
+            tauri::async_runtime::spawn(watcher(app.clone(), debouncer, rx));
+        }
+        Err(e) => tracing::warn!("Could not watch {}: {}", dir.display(), e),
+    }
+}
+
+async fn watcher(
+    app: AppHandle,
+    _debouncer: Debouncer<RecommendedWatcher>,
+    mut events: Receiver<DebounceEventResult>,
+) {
+    // Records what the windows start out with.
+    check_settings(&app, None).await;
+    while let Some(changed) = events.recv().await {
+        match changed {
+            Ok(changed) => check_settings(&app, Some(changed)).await,
+            Err(e) => tracing::warn!("Settings watcher failed: {}", e),
+        }
+    }
+}
+
+/// Broadcasts the settings if the active profile's file is among `changed`,
+/// applying server changes.
+async fn check_settings(app: &AppHandle, changed: Option<Vec<DebouncedEvent>>) {
+    let check_app = app.clone();
+    let checked = tauri::async_runtime::spawn_blocking(move || {
+        let path = settings_path(&crate::profiles::active())?;
+        if changed.is_some_and(|changed| !changed.iter().any(|event| event.path == path)) {
+            return Ok(None);
+        }
+        let previous = BROADCAST
+            .lock()
+            .unwrap()
+            .clone()
+            .and_then(|previous| serde_json::from_value::<Settings>(previous).ok());
+        let loaded = load().and_then(|settings| {
+            settings.validate()?;
+            if let Some(previous) = &previous {
+                settings.guard_paths(&check_app, previous)?;
+            }
+            Ok(settings)
+        });
+        match loaded {
+            Ok(settings) => {
+                let keys = broadcast(&check_app, &settings)?;
+                Ok(Some(settings).filter(|_| keys.iter().any(|key| key.starts_with("server."))))
+            }
+            Err(error) => {
+                tracing::warn!("Ignoring invalid settings file {}: {}", path.display(), error);
+                let _ = check_app.emit("settings-invalid", SettingsInvalid {
+                    path: path.to_string_lossy().to_string(),
+                    error,
+                });
+                Ok::<_, String>(None)
+            }
+        }
+    })
+    .await;
+    match checked {
+        Ok(Ok(Some(settings))) => {
+            tracing::info!("Server settings changed on disk, applying them");
+            if let Err(e) = crate::server::apply_config(app, &settings.server).await {
+                tracing::warn!("Failed to apply the server settings: {}", e);
+            }
+        }
+        Ok(Ok(None)) => {}
+        Ok(Err(e)) => tracing::warn!("Settings watcher failed: {}", e),
+        Err(e) => tracing::warn!("Settings watcher failed: {}", e),
+    }
+}
+
+fn merge_patch(target: &mut Value, patch: Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Writes the settings to `path` without secrets like the external server
+/// token, so the file can be shared. Returns the path written.
+#[tauri::command]
+#[apply(audited!)]
+pub fn export_settings(app: AppHandle, path: String) -> Result<String, String> {
+    crate::paths::guard(&app, Path::new(&path))?;
+    // Secrets live in the credential store and are never serialized.
+    let settings = load()?;
+    let contents = to_file_contents(&settings, &settings_path(&crate::profiles::active())?)?;
+    fs::write(&path, contents).map_err(|e| format!("Could not write {}: {}", path, e))?;
+    tracing::info!("Exported settings to {}", path);
+    Ok(path)
+}
+
+/// Replaces the settings with the ones in the file at `path`, upgraded
+/// like the app's own settings file. Secrets are never imported, so the
+/// ones already set here are kept.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn import_settings(app: AppHandle, path: String) -> Result<Settings, String> {
+    crate::paths::guard(&app, Path::new(&path))?;
+    let settings = read_import(Path::new(&path))?;
+    let current = load()?;
+    settings.guard_paths(&app, &current)?;
+    save(&settings)?;
+    broadcast(&app, &settings)?;
+    tracing::info!("Imported settings from {}", path);
+
+    if settings.server != current.server {
+        crate::server::apply_config(&app, &settings.server).await?;
+    }
+    Ok(settings)
+}
+
+/// Reads and validates a settings file to import, leaving out its secrets.
+fn read_import(path: &Path) -> Result<Settings, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let (mut settings, _) = upgrade(parse(&contents)?)?;
+    settings.server.external_token = None;
+    settings.validate()?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn raw(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(raw) => raw,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Held by tests that set environment variables or run the migrations,
+    /// which read them, since tests run in parallel in one process.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn lock_env() -> std::sync::MutexGuard<'static, ()> {
+        ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[test]
+    fn legacy_setup_file_marks_setup_complete() {
+        let _env = lock_env();
+        let dir = std::env::temp_dir().join(format!("claudin-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        std::env::set_var(CONFIG_DIR_VAR, &dir);
+
+        let mut settings = Map::new();
+        migrate_setup_marker(&mut settings).unwrap();
+        assert_eq!(Value::Object(settings), json!({}));
+
+        fs::write(dir.join(LEGACY_SETUP_FILE), "").unwrap();
+        let mut settings = raw(json!({ "locale": "fr" }));
+        migrate_setup_marker(&mut settings).unwrap();
+        assert_eq!(Value::Object(settings), json!({ "locale": "fr", "setup": { "complete": true } }));
+
+        std::env::remove_var(CONFIG_DIR_VAR);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn imports_a_custom_profile_without_its_token() {
+        let _env = lock_env();
+        let path = std::env::temp_dir().join(format!("claudin-import-test-{}.json", std::process::id()));
+        let file = json!({
+            "schemaVersion": SCHEMA_VERSION,
+            "server": {
+                "profile": "custom",
+                "externalUrl": "https://claudin.example.com",
+                "externalToken": "secret",
+            },
+        });
+        fs::write(&path, file.to_string()).unwrap();
+
+        let settings = read_import(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(settings.server.profile() == ServerProfile::Custom);
+        assert_eq!(settings.server.external_url.as_deref(), Some("https://claudin.example.com"));
+        assert_eq!(settings.server.external_token, None);
+    }
+
+    #[test]
+    fn custom_profile_imports_need_a_url() {
+        let _env = lock_env();
+        let path = std::env::temp_dir().join(format!("claudin-import-url-test-{}.json", std::process::id()));
+        fs::write(&path, json!({ "server": { "profile": "custom" } }).to_string()).unwrap();
+
+        let result = read_import(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn extension_folder_from_the_environment_is_kept() {
+        let _env = lock_env();
+        let dir = std::env::temp_dir().join("claudin-env-extension-dir");
+        std::env::set_var("CLAUDIN_EXTENSION_DIR", &dir);
+        let settings = apply_overrides(drop_unapproved_paths(Settings::default()));
+        std::env::remove_var("CLAUDIN_EXTENSION_DIR");
+        assert_eq!(settings.unwrap().extension.install_dir, Some(dir));
+    }
+
+    #[test]
+    fn setup_flag_becomes_the_list_of_steps() {
+        let mut settings = raw(json!({ "setup": { "complete": true } }));
+        migrate_setup_steps(&mut settings).unwrap();
+        assert_eq!(
+            Value::Object(settings),
+            json!({ "setup": { "completedSteps": serde_json::to_value(SetupStep::ALL).unwrap() } })
+        );
+
+        let mut settings = raw(json!({ "setup": { "complete": false } }));
+        migrate_setup_steps(&mut settings).unwrap();
+        assert_eq!(Value::Object(settings), json!({ "setup": {} }));
+    }
+
+    #[test]
+    fn default_values_are_dropped_from_full_files() {
+        let mut settings = raw(serde_json::to_value(Settings::default()).unwrap());
+        settings.insert("locale".to_string(), json!("fr"));
+        migrate_sparse_settings(&mut settings).unwrap();
+        assert_eq!(Value::Object(settings), json!({ "locale": "fr" }));
+    }
+
+    #[test]
+    fn upgrade_runs_the_migrations_a_file_needs() {
+        let _env = lock_env();
+        let (settings, version) = upgrade(raw(json!({ "schemaVersion": 1, "setup": { "complete": true } }))).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(settings.setup.completed_steps, SetupStep::ALL.into_iter().collect());
+
+        let (settings, version) = upgrade(raw(json!({ "schemaVersion": SCHEMA_VERSION, "locale": "fr" }))).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        assert_eq!(settings.locale.as_deref(), Some("fr"));
+    }
+
+    #[test]
+    fn files_from_newer_versions_still_load() {
+        let (_, version) = upgrade(raw(json!({ "schemaVersion": SCHEMA_VERSION + 1 }))).unwrap();
+        assert_eq!(version, SCHEMA_VERSION + 1);
+        assert!(upgrade(raw(json!({ "schemaVersion": "2" }))).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::audit::audited;
+use crate::extension;
+use crate::i18n::t;
+use macro_rules_attribute::apply;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+/// The signing key, as PKCS#8, kept in the config dir. It determines the
+/// extension id, so it is created once and reused for every package.
+const KEY_FILE: &str = "extension-key.pk8";
+
+/// Prefix of the DER `SubjectPublicKeyInfo` for a P-256 key, followed by the
+/// uncompressed point ring hands out.
+const P256_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+const CRX_MAGIC: &[u8] = b"Cr24";
+const CRX_VERSION: u32 = 3;
+const SIGNATURE_CONTEXT: &[u8] = b"CRX3 SignedData\x00";
+
+// Field numbers of the `CrxFileHeader` and `SignedData` protobuf messages.
+const HEADER_SHA256_WITH_ECDSA: u32 = 3;
+const HEADER_SIGNED_HEADER_DATA: u32 = 10000;
+const PROOF_PUBLIC_KEY: u32 = 1;
+const PROOF_SIGNATURE: u32 = 2;
+const SIGNED_DATA_CRX_ID: u32 = 1;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackedExtension {
+    path: String,
+    /// The id Chrome will give the extension, which stays the same across
+    /// packages because the key does.
+    extension_id: String,
+    version: String,
+    /// How to install the package, since Chrome only accepts packages from
+    /// outside the Web Store in some setups.
+    instructions: Vec<String>,
+}
+
+/// Packs the extracted extension into a signed `.crx` next to it, for
+/// machines where developer mode (and so loading unpacked) is disabled.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn package_extension_crx() -> Result<PackedExtension, String> {
+    tauri::async_runtime::spawn_blocking(package)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn package() -> Result<PackedExtension, String> {
+    let extension_dir = extension::get_extension_dir()?;
+    let version = extension::ExtensionRegistry::default_extension()
+        .version()?
+        .ok_or("Extract the extension before packaging it")?
+        .version;
+
+    let key_pair = load_or_create_key()?;
+    let public_key = [P256_SPKI_PREFIX, key_pair.public_key().as_ref()].concat();
+    let crx_id: [u8; 16] = Sha256::digest(&public_key)[..16]
+        .try_into()
+        .map_err(|_| "Invalid public key hash")?;
+
+    let archive = zip_dir(&extension_dir)?;
+    let signed_header_data = field(SIGNED_DATA_CRX_ID, &crx_id);
+
+    let mut signed = SIGNATURE_CONTEXT.to_vec();
+    signed.extend((signed_header_data.len() as u32).to_le_bytes());
+    signed.extend(&signed_header_data);
+    signed.extend(&archive);
+    let signature = key_pair
+        .sign(&SystemRandom::new(), &signed)
+        .map_err(|_| "Could not sign the extension package")?;
+
+    let proof = [
+        field(PROOF_PUBLIC_KEY, &public_key),
+        field(PROOF_SIGNATURE, signature.as_ref()),
+    ]
+    .concat();
+    let header = [
+        field(HEADER_SHA256_WITH_ECDSA, &proof),
+        field(HEADER_SIGNED_HEADER_DATA, &signed_header_data),
+    ]
+    .concat();
+
+    let mut crx = CRX_MAGIC.to_vec();
+    crx.extend(CRX_VERSION.to_le_bytes());
+    crx.extend((header.len() as u32).to_le_bytes());
+    crx.extend(&header);
+    crx.extend(&archive);
+
+    let path = extension_dir.with_file_name(format!("extension-{}.crx", version));
+    fs::write(&path, crx).map_err(|e| e.to_string())?;
+    tracing::info!("Packed extension {} into {}", version, path.display());
+
+    Ok(PackedExtension {
+        path: path.to_string_lossy().to_string(),
+        extension_id: extension::encode_extension_id(&crx_id),
+        version,
+        instructions: instructions(),
+    })
+}
+
+/// Packages created so far, which live next to the extension dir.
+pub fn list_packages() -> Result<Vec<PathBuf>, String> {
+    let extension_dir = extension::get_extension_dir()?;
+    let Some(Ok(entries)) = extension_dir.parent().map(fs::read_dir) else {
+        return Ok(Vec::new());
+    };
+    Ok(entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with("extension-") && name.ends_with(".crx")
+        })
+        .collect())
+}
+
+fn load_or_create_key() -> Result<EcdsaKeyPair, String> {
+    let key_path = crate::get_config_dir()?.join(KEY_FILE);
+    let rng = SystemRandom::new();
+
+    let pkcs8 = match fs::read(&key_path) {
+        Ok(pkcs8) => pkcs8,
+        Err(_) => {
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+                .map_err(|_| "Could not generate a signing key")?;
+            write_private(&key_path, pkcs8.as_ref())?;
+            pkcs8.as_ref().to_vec()
+        }
+    };
+
+    EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &pkcs8, &rng)
+        .map_err(|_| format!("Invalid signing key in {}", key_path.display()))
+}
+
+/// Writes the key readable by the current user only, where the OS allows it.
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| e.to_string())?;
+    file.write_all(contents).map_err(|e| e.to_string())
+}
+
+fn zip_dir(dir: &Path) -> Result<Vec<u8>, String> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    // Stored rather than deflated: the extension is small and Chrome doesn't
+    // mind either way.
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored);
+
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.sort();
+
+    for file in files {
+        let name = file
+            .strip_prefix(dir)
+            .map_err(|e| e.to_string())?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        writer.start_file(name, options).map_err(|e| e.to_string())?;
+        writer
+            .write_all(&fs::read(&file).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(writer.finish().map_err(|e| e.to_string())?.into_inner())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Encodes a length-delimited protobuf field.
+fn field(number: u32, value: &[u8]) -> Vec<u8> {
+    let mut encoded = varint(u64::from(number) << 3 | 2);
+    encoded.extend(varint(value.len() as u64));
+    encoded.extend(value);
+    encoded
+}
+
+fn varint(mut value: u64) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            encoded.push(byte);
+            return encoded;
+        }
+        encoded.push(byte | 0x80);
+    }
+}
+
+fn instructions() -> Vec<String> {
+    let mut steps = vec![t("crx.openExtensions"), t("crx.dragPackage")];
+    if !cfg!(target_os = "linux") {
+        steps.push(t("crx.policy"));
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varints_use_seven_bits_per_byte() {
+        assert_eq!(varint(0), [0x00]);
+        assert_eq!(varint(1), [0x01]);
+        assert_eq!(varint(127), [0x7f]);
+        assert_eq!(varint(128), [0x80, 0x01]);
+        assert_eq!(varint(300), [0xac, 0x02]);
+        let max = varint(u64::MAX);
+        assert_eq!(max.len(), 10);
+        assert_eq!(max.last(), Some(&0x01));
+    }
+
+    #[test]
+    fn fields_are_length_delimited() {
+        assert_eq!(field(SIGNED_DATA_CRX_ID, b"abc"), [0x0a, 0x03, b'a', b'b', b'c']);
+        assert_eq!(field(PROOF_SIGNATURE, &[]), [0x12, 0x00]);
+
+        let value = [0u8; 200];
+        let encoded = field(HEADER_SIGNED_HEADER_DATA, &value);
+        assert_eq!(encoded[..5], [0x82, 0xf1, 0x04, 0xc8, 0x01]);
+        assert_eq!(encoded.len(), 5 + value.len());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::audit::audited;
+use crate::extension::{self, ExtensionRegistry, ExtensionVariant, ExtensionVersion};
+use crate::i18n::{t, t_with};
+use base64::Engine;
+use macro_rules_attribute::apply;
+use semver::Version;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::time::Duration;
+use tauri::AppHandle;
+
+/// Extension hotfixes are published as GitHub releases tagged
+/// `extension-v<version>`, with the archive signed by the updater key.
+const RELEASE_URL: &str = "https://github.com/The-Vibe-Company/ClaudIn/releases/download";
+const ARCHIVE_NAME: &str = "claudin-extension.zip";
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Downloads a newer build of the extension than the one shipped with the
+/// app, checks its signature, and installs it like a bundled update. The
+/// download stays around as the reference for integrity checks and repairs
+/// until the app ships the same or a newer version.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn download_extension(app: AppHandle, version: String) -> Result<ExtensionVersion, String> {
+    let version = Version::parse(&version)
+        .map_err(|e| t_with("download.invalidVersion", &[("error", &e.to_string())]))?
+        .to_string();
+
+    let spec = ExtensionRegistry::default_extension();
+    let shipped = spec.shipped_bundle(&app, ExtensionVariant::Chrome)?.manifest()?;
+    if !extension::is_newer_version(&version, &shipped.version) {
+        return Err(t_with("download.alreadyShipped", &[("version", &shipped.version)]));
+    }
+
+    let url = format!("{}/extension-v{}/{}", RELEASE_URL, version, ARCHIVE_NAME);
+    tracing::info!("Downloading extension {} from {}", version, url);
+    let archive = fetch(&url).await?;
+    let signature = fetch(&format!("{}.sig", url)).await?;
+    verify_signature(&app, &archive, &signature)?;
+
+    tauri::async_runtime::spawn_blocking(move || install(&app, &archive, &version))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .error_for_status()
+        .map_err(|e| e.to_string())?;
+    Ok(response.bytes().await.map_err(|e| e.to_string())?.to_vec())
+}
+
+/// Checks `data` against the updater's public key from the app config,
+/// so hotfixes are trusted exactly as much as app updates. Like the
+/// updater's, the signature file is a base64 encoded minisign signature.
+pub fn verify_signature(app: &AppHandle, data: &[u8], signature: &[u8]) -> Result<(), String> {
+    let invalid = |e: &dyn std::fmt::Display| t_with("download.invalidSignature", &[("error", &e.to_string())]);
+
+    let pubkey = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater["pubkey"].as_str())
+        .ok_or_else(|| t("download.noSigningKey"))?;
+    let pubkey = decode_base64_text(pubkey.as_bytes()).map_err(|e| invalid(&e))?;
+    let pubkey = minisign_verify::PublicKey::decode(&pubkey).map_err(|e| invalid(&e))?;
+
+    let signature = decode_base64_text(signature).map_err(|e| invalid(&e))?;
+    let signature = minisign_verify::Signature::decode(&signature).map_err(|e| invalid(&e))?;
+
+    pubkey.verify(data, &signature, false).map_err(|e| invalid(&e))
+}
+
+fn decode_base64_text(encoded: &[u8]) -> Result<String, String> {
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim_ascii())
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(decoded).map_err(|e| e.to_string())
+}
+
+fn install(app: &AppHandle, archive: &[u8], version: &str) -> Result<ExtensionVersion, String> {
+    let spec = ExtensionRegistry::default_extension();
+    let extension_dir = spec.dir()?;
+    let staging_dir = extension::sibling_dir(&extension_dir, "hotfix-staging");
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+    }
+
+    if let Err(e) = unpack(archive, &staging_dir, version) {
+        let _ = fs::remove_dir_all(&staging_dir);
+        return Err(e);
+    }
+
+    let hotfix_dir = spec.hotfix_dir()?;
+    if hotfix_dir.exists() {
+        fs::remove_dir_all(&hotfix_dir).map_err(|e| e.to_string())?;
+    }
+    fs::rename(&staging_dir, &hotfix_dir).map_err(|e| e.to_string())?;
+
+    extension::extract(app, spec, ExtensionVariant::Chrome)?;
+    extension::read_manifest(&extension_dir.join("manifest.json"))
+}
+
+/// Unpacks the archive into `dir`, checking it holds the version asked for.
+fn unpack(archive: &[u8], dir: &Path, version: &str) -> Result<(), String> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(archive)).map_err(|e| e.to_string())?;
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index).map_err(|e| e.to_string())?;
+        // Rejects entries like `../x` that would land outside `dir`.
+        let name = file
+            .enclosed_name()
+            .ok_or_else(|| t_with("download.invalidPath", &[("path", file.name())]))?;
+        let path = dir.join(name);
+
+        if file.is_dir() {
+            fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut out = fs::File::create(&path).map_err(|e| e.to_string())?;
+        std::io::copy(&mut file, &mut out).map_err(|e| e.to_string())?;
+    }
+
+    let manifest = extension::read_manifest(&dir.join("manifest.json"))?;
+    if manifest.version != version {
+        return Err(t_with(
+            "download.wrongVersion",
+            &[("version", &manifest.version), ("expected", version)],
+        ));
+    }
+    Ok(())
+}
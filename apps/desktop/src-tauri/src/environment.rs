@@ -0,0 +1,104 @@
+use crate::audit::audited;
+use crate::i18n::t;
+use macro_rules_attribute::apply;
+use serde::Serialize;
+
+/// Kind of remote session the app is running in.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteSession {
+    Ssh,
+    Rdp,
+}
+
+/// What's unusual about where the app runs that changes how browsers can be
+/// opened, so setup can explain launch failures up front.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentReport {
+    /// Running in Linux under WSL.
+    wsl: bool,
+    /// WSL can start Windows programs, which it can't when interop is
+    /// turned off in `wsl.conf`.
+    wsl_interop: bool,
+    remote_session: Option<RemoteSession>,
+    /// Whether there is a display to open windows on.
+    display: bool,
+    /// What to do about each of the above, for the setup wizard.
+    guidance: Vec<String>,
+}
+
+/// Checks for WSL, remote sessions and missing displays, where opening a
+/// browser fails or opens it somewhere the user can't see.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_environment_report() -> Result<EnvironmentReport, String> {
+    let wsl = is_wsl();
+    let wsl_interop = wsl && std::path::Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists();
+    let remote_session = remote_session();
+    let display = has_display();
+
+    let mut guidance = Vec::new();
+    if wsl {
+        guidance.push(t("environment.wsl"));
+        if !wsl_interop {
+            guidance.push(t("environment.wslInterop"));
+        }
+    }
+    match remote_session {
+        Some(RemoteSession::Ssh) => guidance.push(t("environment.ssh")),
+        Some(RemoteSession::Rdp) => guidance.push(t("environment.rdp")),
+        None => {}
+    }
+    if !display {
+        guidance.push(t(if wsl {
+            "environment.noDisplayWsl"
+        } else {
+            "environment.noDisplay"
+        }));
+    }
+
+    Ok(EnvironmentReport {
+        wsl,
+        wsl_interop,
+        remote_session,
+        display,
+        guidance,
+    })
+}
+
+/// WSL sets `WSL_DISTRO_NAME` for processes it starts, and its kernel says
+/// it's Microsoft's for those started some other way.
+fn is_wsl() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    std::env::var_os("WSL_DISTRO_NAME").is_some()
+        || std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .is_ok_and(|release| release.to_ascii_lowercase().contains("microsoft"))
+}
+
+/// Remote Desktop names its sessions `RDP-Tcp#<n>` on Windows, and xrdp
+/// sets `XRDP_SESSION` on Linux.
+fn remote_session() -> Option<RemoteSession> {
+    let set = |var| std::env::var_os(var).is_some();
+    let rdp = std::env::var("SESSIONNAME").is_ok_and(|name| name.starts_with("RDP-")) || set("XRDP_SESSION");
+    if rdp {
+        Some(RemoteSession::Rdp)
+    } else if set("SSH_CONNECTION") || set("SSH_CLIENT") || set("SSH_TTY") {
+        Some(RemoteSession::Ssh)
+    } else {
+        None
+    }
+}
+
+/// Windows and macOS always have one for a logged in user. On Linux it's
+/// whatever X11 or Wayland display the app was started with, WSLg's included.
+fn has_display() -> bool {
+    if !cfg!(target_os = "linux") {
+        return true;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+}
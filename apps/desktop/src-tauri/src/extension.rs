@@ -0,0 +1,1507 @@
+use macro_rules_attribute::apply;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audit::audited;
+use crate::browser::{self, Browser, BrowserProfile, Engine, OpenPageError};
+use crate::bundle::Bundle;
+use crate::config;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// The LinkedIn sync extension, which commands act on when no id is given.
+pub const DEFAULT_EXTENSION: &str = "linkedin-sync";
+
+/// Release notes shipped with the extension, newest version first.
+const CHANGELOG_FILE: &str = "changelog.json";
+
+/// How many replaced versions are kept around to roll back to.
+const MAX_BACKUPS: usize = 3;
+
+/// Backups live next to the extension dir as `extension.backup-<version>`.
+const BACKUP_INFIX: &str = ".backup-";
+
+/// Every extension the app ships, keyed by id.
+const EXTENSIONS: &[ExtensionSpec] = &[ExtensionSpec {
+    id: DEFAULT_EXTENSION,
+    dir_name: "extension",
+    resource_name: "extension",
+}];
+
+/// An extension the app ships, extracts and keeps up to date.
+pub struct ExtensionSpec {
+    pub id: &'static str,
+    /// Folder in the install dir the extension is extracted into.
+    dir_name: &'static str,
+    /// Folder in the app resources the extension is bundled in.
+    resource_name: &'static str,
+}
+
+/// The build of an extension to extract. Each is bundled in its own folder
+/// under the extension's resources, since Firefox runs background scripts
+/// instead of a service worker and requires an add-on id, and Safari only
+/// loads extensions that come inside an app.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtensionVariant {
+    /// Chrome and every other Chromium based browser.
+    #[default]
+    Chrome,
+    Firefox,
+    /// The app Xcode wraps the extension in, at the root of the bundle.
+    Safari,
+}
+
+impl ExtensionVariant {
+    pub const ALL: [Self; 3] = [Self::Chrome, Self::Firefox, Self::Safari];
+
+    /// The build a browser takes, from the engine it is built on.
+    pub fn of(browser: Browser) -> Result<Self, String> {
+        match browser.engine() {
+            Engine::Chromium => Ok(Self::Chrome),
+            Engine::Gecko => Ok(Self::Firefox),
+            Engine::WebKit => Ok(Self::Safari),
+            Engine::Other => Err(format!("There is no ClaudIn extension for {}'s engine", browser.display_name())),
+        }
+    }
+
+    /// The build the preferred browser takes, Chrome's when there is none or
+    /// it has no build.
+    pub fn preferred() -> Self {
+        browser::preferred_browser()
+            .and_then(|browser| Self::of(browser).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn folder_name(self) -> &'static str {
+        match self {
+            Self::Chrome => "chrome",
+            Self::Firefox => "firefox",
+            Self::Safari => "safari",
+        }
+    }
+}
+
+/// Looks up the extensions the app manages.
+pub struct ExtensionRegistry;
+
+impl ExtensionRegistry {
+    pub fn all() -> &'static [ExtensionSpec] {
+        EXTENSIONS
+    }
+
+    /// The extension with `id`, or the default one when no id is given.
+    pub fn get(id: Option<&str>) -> Result<&'static ExtensionSpec, String> {
+        let id = id.unwrap_or(DEFAULT_EXTENSION);
+        EXTENSIONS
+            .iter()
+            .find(|spec| spec.id == id)
+            .ok_or_else(|| format!("Unknown extension {:?}", id))
+    }
+
+    pub fn default_extension() -> &'static ExtensionSpec {
+        &EXTENSIONS[0]
+    }
+}
+
+impl ExtensionSpec {
+    /// Where the Chrome variant of the extension is extracted to.
+    pub fn dir(&self) -> Result<PathBuf, String> {
+        Ok(get_install_dir()?.join(self.dir_name))
+    }
+
+    pub fn firefox_dir(&self) -> Result<PathBuf, String> {
+        Ok(get_install_dir()?.join(format!("{}-firefox", self.dir_name)))
+    }
+
+    pub fn safari_dir(&self) -> Result<PathBuf, String> {
+        Ok(get_install_dir()?.join(format!("{}-safari", self.dir_name)))
+    }
+
+    pub fn variant_dir(&self, variant: ExtensionVariant) -> Result<PathBuf, String> {
+        match variant {
+            ExtensionVariant::Chrome => self.dir(),
+            ExtensionVariant::Firefox => self.firefox_dir(),
+            ExtensionVariant::Safari => self.safari_dir(),
+        }
+    }
+
+    /// Whether `variant` has been extracted, the Safari build being an app
+    /// rather than a folder with a manifest.
+    pub fn is_extracted(&self, variant: ExtensionVariant) -> Result<bool, String> {
+        if variant == ExtensionVariant::Safari {
+            return Ok(self.safari_app()?.is_some());
+        }
+        Ok(self.variant_dir(variant)?.join("manifest.json").exists())
+    }
+
+    /// The extracted app containing the Safari extension, if any.
+    pub fn safari_app(&self) -> Result<Option<PathBuf>, String> {
+        let Ok(entries) = fs::read_dir(self.safari_dir()?) else {
+            return Ok(None);
+        };
+        Ok(entries
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "app")))
+    }
+
+    /// Where a downloaded build of the extension is unpacked, see
+    /// `download::download_extension`. Hotfixes only exist for Chrome.
+    pub fn hotfix_dir(&self) -> Result<PathBuf, String> {
+        Ok(sibling_dir(&self.dir()?, "hotfix"))
+    }
+
+    /// Holds the bundled version the user rolled back from, so startup
+    /// doesn't immediately re-extract it.
+    fn pinned_file(&self) -> Result<PathBuf, String> {
+        Ok(sibling_dir(&self.dir()?, "pinned"))
+    }
+
+    /// Returns the name and version of the extracted extension, or `None`
+    /// if it hasn't been extracted yet.
+    pub fn version(&self) -> Result<Option<ExtensionVersion>, String> {
+        let manifest_path = self.dir()?.join(MANIFEST_FILE);
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+        read_manifest(&manifest_path).map(Some)
+    }
+
+    /// Returns the development build the extension dir links to, if any.
+    pub fn dev_build(&self) -> Result<Option<PathBuf>, String> {
+        let extension_dir = self.dir()?;
+        if !is_dir_link(&extension_dir) {
+            return Ok(None);
+        }
+        fs::read_link(&extension_dir).map(Some).map_err(|e| e.to_string())
+    }
+
+    /// The extension files the extracted copy is made from: a downloaded
+    /// hotfix when it is newer than the extension shipped with the app,
+    /// otherwise the shipped one.
+    pub fn bundle(&self, app_handle: &AppHandle, variant: ExtensionVariant) -> Result<Bundle, String> {
+        let shipped = self.shipped_bundle(app_handle, variant)?;
+        if variant != ExtensionVariant::Chrome {
+            return Ok(shipped);
+        }
+        let hotfix = Bundle::Dir(self.hotfix_dir()?);
+        let newer = match (hotfix.manifest(), shipped.manifest()) {
+            (Ok(hotfix), Ok(shipped)) => is_newer_version(&hotfix.version, &shipped.version),
+            _ => false,
+        };
+        Ok(if newer { hotfix } else { shipped })
+    }
+
+    /// The extension shipped with the app, as `<variant>.zip` in its
+    /// resources, or loose files in a checkout that hasn't packed them yet.
+    pub fn shipped_bundle(&self, app_handle: &AppHandle, variant: ExtensionVariant) -> Result<Bundle, String> {
+        let resource_path = app_handle
+            .path()
+            .resource_dir()
+            .map_err(|e: tauri::Error| e.to_string())?
+            .join(self.resource_name)
+            .join(variant.folder_name());
+        
+        let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+        
+        let dev_paths = vec![
+            cwd.join("src-tauri").join("resources").join(self.resource_name).join(variant.folder_name()),
+            cwd.join("resources").join(self.resource_name).join(variant.folder_name()),
+        ];
+        
+        std::iter::once(resource_path)
+            .chain(dev_paths)
+            .find_map(|path| Bundle::find(&path))
+            .ok_or_else(|| "Extension resources not found".to_string())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionSummary {
+    id: &'static str,
+    path: String,
+    installed: Option<ExtensionVersion>,
+}
+
+/// The parts of the extension's `manifest.json` the app cares about.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionVersion {
+    pub name: String,
+    pub version: String,
+    #[serde(alias = "manifest_version")]
+    pub manifest_version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangelogEntry {
+    version: String,
+    date: Option<String>,
+    changes: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtensionUpdatedPayload {
+    id: &'static str,
+    previous_version: String,
+    version: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallReport {
+    /// Paths that were deleted, in the order they were removed.
+    pub removed: Vec<String>,
+    /// Development builds that were only unlinked and left in place.
+    pub unlinked: Vec<String>,
+}
+
+/// Whether the extension is loaded in one profile of a browser.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInstallation {
+    #[serde(flatten)]
+    profile: BrowserProfile,
+    installed: bool,
+    /// The id the browser assigned to the unpacked extension.
+    extension_id: Option<String>,
+}
+
+/// What extracting the extension again would change in the extracted copy.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionPlan {
+    version: String,
+    installed_version: Option<String>,
+    added: Vec<String>,
+    updated: Vec<String>,
+    removed: Vec<String>,
+}
+
+/// How the extension is set up in one profile of a browser.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionBrowserState {
+    #[serde(flatten)]
+    profile: BrowserProfile,
+    installed: bool,
+    extension_id: Option<String>,
+    /// `None` when the extension isn't loaded in the profile.
+    enabled: Option<bool>,
+    /// Whether the extension's button is pinned to the toolbar.
+    pinned: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtractionProgressPayload {
+    files_copied: usize,
+    total_files: usize,
+    bytes_copied: u64,
+    total_bytes: u64,
+}
+
+/// Payload of `extension-verification-failed`, emitted when a bundle's
+/// signature or checksums don't check out and nothing was installed.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationFailedPayload {
+    id: &'static str,
+    variant: ExtensionVariant,
+    error: String,
+}
+
+/// Lists the extensions the app manages and the version of each that is
+/// extracted.
+#[tauri::command]
+#[apply(audited!)]
+pub fn list_extensions() -> Result<Vec<ExtensionSummary>, String> {
+    ExtensionRegistry::all()
+        .iter()
+        .map(|spec| {
+            Ok(ExtensionSummary {
+                id: spec.id,
+                path: spec.dir()?.to_string_lossy().to_string(),
+                installed: spec.version()?,
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_path(id: Option<String>, browser: Option<ExtensionVariant>) -> Result<String, String> {
+    let variant = browser.unwrap_or_else(ExtensionVariant::preferred);
+    let extension_dir = ExtensionRegistry::get(id.as_deref())?.variant_dir(variant)?;
+    Ok(extension_dir.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn is_extension_extracted(id: Option<String>, browser: Option<ExtensionVariant>) -> Result<bool, String> {
+    let variant = browser.unwrap_or_else(ExtensionVariant::preferred);
+    ExtensionRegistry::get(id.as_deref())?.is_extracted(variant)
+}
+
+/// Extracts the build of the extension for `browser`, by default the one
+/// the preferred browser takes, into that build's own folder.
+#[tauri::command]
+#[apply(audited!)]
+pub fn extract_extension(
+    app_handle: tauri::AppHandle,
+    id: Option<String>,
+    browser: Option<ExtensionVariant>,
+) -> Result<String, String> {
+    extract(
+        &app_handle,
+        ExtensionRegistry::get(id.as_deref())?,
+        browser.unwrap_or_else(ExtensionVariant::preferred),
+    )
+}
+
+pub fn extract(app_handle: &AppHandle, spec: &ExtensionSpec, variant: ExtensionVariant) -> Result<String, String> {
+    let extension_dir = spec.variant_dir(variant)?;
+    extract_into(app_handle, spec, variant, &extension_dir)?;
+    if variant == ExtensionVariant::Chrome {
+        let _ = fs::remove_file(spec.pinned_file()?);
+    }
+    // The install dir may have been created just now, and Chromium derives
+    // the extension's id from its path.
+    crate::integrity::watch(app_handle);
+    let app = app_handle.clone();
+    tauri::async_runtime::spawn(async move { crate::server::refresh_allowed_origins(&app).await });
+    Ok(extension_dir.to_string_lossy().to_string())
+}
+
+/// Compares the bundled extension with the extracted copy without touching
+/// either, so the UI can show what an update will change before running it.
+#[tauri::command]
+#[apply(audited!)]
+pub fn extract_extension_dry_run(
+    app_handle: tauri::AppHandle,
+    id: Option<String>,
+    browser: Option<ExtensionVariant>,
+) -> Result<ExtractionPlan, String> {
+    let spec = ExtensionRegistry::get(id.as_deref())?;
+    let variant = browser.unwrap_or_else(ExtensionVariant::preferred);
+    let bundle = spec.bundle(&app_handle, variant)?;
+    let bundled = bundle.hashes()?;
+
+    let extension_dir = spec.variant_dir(variant)?;
+    let mut installed = BTreeMap::new();
+    if extension_dir.exists() {
+        crate::integrity::hash_dir(&extension_dir, &extension_dir, &mut installed)?;
+    }
+
+    let installed_version = read_manifest(&extension_dir.join(MANIFEST_FILE)).ok();
+    let mut plan = ExtractionPlan {
+        version: bundle.manifest()?.version,
+        installed_version: installed_version.map(|manifest| manifest.version),
+        added: Vec::new(),
+        updated: Vec::new(),
+        removed: installed
+            .keys()
+            .filter(|name| !bundled.contains_key(*name))
+            .cloned()
+            .collect(),
+    };
+    for (name, hash) in bundled {
+        match installed.get(&name) {
+            None => plan.added.push(name),
+            Some(installed_hash) if *installed_hash != hash => plan.updated.push(name),
+            Some(_) => {}
+        }
+    }
+    Ok(plan)
+}
+
+/// Copies the bundled `variant` of the extension into `extension_dir`.
+fn extract_into(
+    app_handle: &AppHandle,
+    spec: &ExtensionSpec,
+    variant: ExtensionVariant,
+    extension_dir: &Path,
+) -> Result<(), String> {
+    let bundle = spec.bundle(app_handle, variant)?;
+    if let Err(error) = crate::integrity::verify_bundle(app_handle, &bundle) {
+        tracing::error!("Refusing to install the {} extension: {}", spec.id, error);
+        let _ = app_handle.emit(
+            "extension-verification-failed",
+            VerificationFailedPayload {
+                id: spec.id,
+                variant,
+                error: error.clone(),
+            },
+        );
+        return Err(error);
+    }
+    let staging_dir = sibling_dir(extension_dir, "staging");
+    
+    // Left over from an extraction that was interrupted.
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+    }
+    fs::create_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+    
+    let (total_files, total_bytes) = bundle.size()?;
+    let mut progress = ExtractionProgressPayload {
+        files_copied: 0,
+        total_files,
+        bytes_copied: 0,
+        total_bytes,
+    };
+    let copied = bundle.extract_to(&staging_dir, &mut |bytes| {
+        progress.files_copied += 1;
+        progress.bytes_copied += bytes;
+        let _ = app_handle.emit("extraction-progress", progress.clone());
+    });
+    if let Err(e) = copied {
+        let _ = fs::remove_dir_all(&staging_dir);
+        return Err(e);
+    }
+    
+    swap_into_place(&staging_dir, extension_dir)
+}
+
+/// Replaces the extracted extension with a link to a local build so changes
+/// show up after a reload in Chrome without extracting again. The extracted
+/// copy is discarded; `unlink_extension_dev_build` brings it back.
+#[tauri::command]
+#[apply(audited!)]
+pub fn link_extension_dev_build(app: AppHandle, path: String, id: Option<String>) -> Result<String, String> {
+    let build_dir = crate::paths::guard(&app, Path::new(&path))?;
+    if !build_dir.join(MANIFEST_FILE).exists() {
+        return Err(format!("No {} in {}", MANIFEST_FILE, build_dir.display()));
+    }
+
+    let extension_dir = ExtensionRegistry::get(id.as_deref())?.dir()?;
+    remove_extension_dir(&extension_dir)?;
+    if let Some(parent) = extension_dir.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    create_dir_link(&build_dir, &extension_dir)?;
+
+    tracing::info!("Linked extension to development build at {}", build_dir.display());
+    Ok(extension_dir.to_string_lossy().to_string())
+}
+
+/// Removes the link to a development build and extracts the bundled
+/// extension again.
+#[tauri::command]
+#[apply(audited!)]
+pub fn unlink_extension_dev_build(app_handle: tauri::AppHandle, id: Option<String>) -> Result<String, String> {
+    let spec = ExtensionRegistry::get(id.as_deref())?;
+    let extension_dir = spec.dir()?;
+    if is_dir_link(&extension_dir) {
+        remove_extension_dir(&extension_dir)?;
+    }
+    extract(&app_handle, spec, ExtensionVariant::Chrome)
+}
+
+/// Deletes every copy of the extension the app created, including backups
+/// and leftovers from interrupted extractions, and unregisters the native
+/// messaging host. The browser keeps its own reference to the unpacked
+/// extension, which the user still has to remove there.
+#[tauri::command]
+#[apply(audited!)]
+pub fn uninstall_extension(id: Option<String>) -> Result<UninstallReport, String> {
+    let spec = ExtensionRegistry::get(id.as_deref())?;
+    let extension_dir = spec.dir()?;
+    let variant_dirs = [spec.firefox_dir()?, spec.safari_dir()?];
+    let mut targets = vec![
+        sibling_dir(&extension_dir, "staging"),
+        sibling_dir(&extension_dir, "previous"),
+        spec.hotfix_dir()?,
+        sibling_dir(&extension_dir, "hotfix-staging"),
+    ];
+    for dir in &variant_dirs {
+        targets.extend([sibling_dir(dir, "staging"), sibling_dir(dir, "previous")]);
+        targets.extend(list_backups(dir).into_iter().map(|(_, path)| path));
+    }
+    targets.extend(list_backups(&extension_dir).into_iter().map(|(_, path)| path));
+    targets.extend(variant_dirs);
+    targets.push(extension_dir);
+
+    let mut report = UninstallReport {
+        removed: Vec::new(),
+        unlinked: Vec::new(),
+    };
+    for target in &targets {
+        ensure_removable(target)?;
+        if !target.exists() && !is_dir_link(target) {
+            continue;
+        }
+        let linked_to = fs::read_link(target).ok().filter(|_| is_dir_link(target));
+        remove_extension_dir(target)?;
+        match linked_to {
+            Some(build) => report.unlinked.push(build.to_string_lossy().to_string()),
+            None => report.removed.push(target.to_string_lossy().to_string()),
+        }
+    }
+
+    let mut files = vec![spec.pinned_file()?];
+    // Packages and the native messaging host only exist for the default
+    // extension.
+    let is_default = spec.id == DEFAULT_EXTENSION;
+    if is_default {
+        files.extend(crate::crx::list_packages()?);
+    }
+    for file in &files {
+        ensure_removable(file)?;
+        if fs::remove_file(file).is_ok() {
+            report.removed.push(file.to_string_lossy().to_string());
+        }
+    }
+
+    if is_default {
+        report.removed.extend(crate::native_messaging::unregister_native_messaging_host()?);
+    }
+
+    // Only drop the ClaudIn folder if nothing else (like the user's own
+    // files) ended up in it. A folder the user picked is theirs to keep.
+    let root = get_install_dir()?;
+    if root == default_install_dir()? && fs::remove_dir(&root).is_ok() {
+        report.removed.push(root.to_string_lossy().to_string());
+    }
+    report.removed.extend(remove_legacy_links(&root));
+
+    tracing::info!("Uninstalled extension {}, removed {:?}", spec.id, report.removed);
+    Ok(report)
+}
+
+/// Refuses to delete anything that isn't directly inside the folder the
+/// extension is extracted into, so a bad path can't take the home dir or
+/// anything outside ClaudIn's own folder with it.
+fn ensure_removable(path: &Path) -> Result<(), String> {
+    let root = get_install_dir()?;
+    let inside_root = path.parent() == Some(root.as_path())
+        && path
+            .file_name()
+            .is_some_and(|name| name != ".." && name != ".");
+    if !inside_root {
+        return Err(format!("Refusing to remove {}", path.display()));
+    }
+    Ok(())
+}
+
+/// Versions of the extension that can be rolled back to, newest first.
+#[tauri::command]
+#[apply(audited!)]
+pub fn list_extension_backups(id: Option<String>) -> Result<Vec<String>, String> {
+    Ok(list_backups(&ExtensionRegistry::get(id.as_deref())?.dir()?)
+        .into_iter()
+        .map(|(version, _)| version)
+        .collect())
+}
+
+/// Restores a backed up version of the extension, by default the newest one
+/// older than the installed version. The replaced version is backed up in
+/// turn, and the app stops updating the extension until it is extracted
+/// again.
+#[tauri::command]
+#[apply(audited!)]
+pub fn rollback_extension(
+    app_handle: tauri::AppHandle,
+    version: Option<String>,
+    id: Option<String>,
+) -> Result<ExtensionVersion, String> {
+    let spec = ExtensionRegistry::get(id.as_deref())?;
+    let extension_dir = spec.dir()?;
+    if is_dir_link(&extension_dir) {
+        return Err("The extension is linked to a development build".to_string());
+    }
+
+    let installed = spec.version()?.map(|manifest| manifest.version);
+    let mut backups = list_backups(&extension_dir).into_iter();
+    let (version, backup) = match version {
+        Some(version) => backups
+            .find(|(backup, _)| *backup == version)
+            .ok_or_else(|| format!("No backup of extension version {}", version))?,
+        None => backups
+            .find(|(backup, _)| installed.as_ref().is_none_or(|installed| is_newer_version(installed, backup)))
+            .ok_or("No older extension version to roll back to")?,
+    };
+
+    tracing::info!(
+        "Rolling extension back from {} to {}",
+        installed.as_deref().unwrap_or("nothing"),
+        version
+    );
+    swap_into_place(&backup, &extension_dir)?;
+
+    let bundled = spec.bundle(&app_handle, ExtensionVariant::Chrome)?.manifest()?;
+    fs::write(spec.pinned_file()?, bundled.version).map_err(|e| e.to_string())?;
+
+    read_manifest(&extension_dir.join(MANIFEST_FILE))
+}
+
+/// Returns the development build the extension dir links to, if any.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_dev_build(id: Option<String>) -> Result<Option<String>, String> {
+    let build = ExtensionRegistry::get(id.as_deref())?.dev_build()?;
+    Ok(build.map(|build| build.to_string_lossy().to_string()))
+}
+
+/// Re-extracts every extension the app ships a newer version of than the
+/// extracted one, as happens after an app update, and emits
+/// `extension-updated` so the UI can ask the user to reload it in Chrome.
+pub fn update_if_outdated(app: &AppHandle) {
+    for spec in ExtensionRegistry::all() {
+        match reextract_if_outdated(app, spec) {
+            Ok(Some(payload)) => {
+                let _ = app.emit("extension-updated", payload);
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to update the extracted extension {}: {}", spec.id, e),
+        }
+    }
+}
+
+fn reextract_if_outdated(app: &AppHandle, spec: &ExtensionSpec) -> Result<Option<ExtensionUpdatedPayload>, String> {
+    // A linked development build is managed by the developer.
+    if spec.dev_build()?.is_some() {
+        return Ok(None);
+    }
+    // Extracting for the first time is up to the setup wizard.
+    let Some(installed) = spec.version()? else {
+        return Ok(None);
+    };
+    let bundled = spec.bundle(app, ExtensionVariant::Chrome)?.manifest()?;
+    if !is_newer_version(&bundled.version, &installed.version) {
+        return Ok(None);
+    }
+    let pinned = fs::read_to_string(spec.pinned_file()?).ok();
+    if pinned.as_deref().map(str::trim) == Some(bundled.version.as_str()) {
+        return Ok(None);
+    }
+
+    tracing::info!(
+        "Updating extracted extension from {} to {}",
+        installed.version,
+        bundled.version
+    );
+    extract(app, spec, ExtensionVariant::Chrome)?;
+
+    Ok(Some(ExtensionUpdatedPayload {
+        id: spec.id,
+        previous_version: installed.version,
+        version: bundled.version,
+    }))
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn open_extension_folder(id: Option<String>) -> Result<(), String> {
+    let extension_dir = crate::paths::ensure_allowed(&ExtensionRegistry::get(id.as_deref())?.dir()?)?;
+    
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg(&extension_dir)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("explorer")
+            .arg(&extension_dir)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        std::process::Command::new("xdg-open")
+            .arg(&extension_dir)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+    }
+    
+    Ok(())
+}
+
+/// Opens the page Firefox loads unpacked add-ons from. Unsigned add-ons can
+/// only be loaded temporarily there, via "Load Temporary Add-on...".
+#[tauri::command]
+#[apply(audited!)]
+pub fn open_firefox_addons(app: AppHandle) -> Result<(), OpenPageError> {
+    browser::open_page(&app, Browser::FIREFOX, Browser::FIREFOX.extensions_url())
+}
+
+const STORE_URL: &str = "https://chromewebstore.google.com/detail";
+
+/// Opens the extension's Chrome Web Store listing in `browser`, or in the
+/// default browser, as an alternative to loading it unpacked. Every
+/// Chromium-based browser can install from the store.
+#[tauri::command]
+#[apply(audited!)]
+pub fn open_extension_store_page(browser: Option<Browser>) -> Result<Browser, String> {
+    let browser = browser.unwrap_or_else(browser::default_browser);
+    if !browser.is_chromium() {
+        return Err(format!("{} can't install extensions from the Chrome Web Store", browser.display_name()));
+    }
+    let store_id = config::load()?
+        .extension
+        .store_id
+        .ok_or("The extension isn't published to the Chrome Web Store yet")?;
+
+    browser.open_url(&format!("{}/{}", STORE_URL, store_id))?;
+    Ok(browser)
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_store_id() -> Result<Option<String>, String> {
+    Ok(config::load()?.extension.store_id)
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn set_extension_store_id(app: AppHandle, store_id: Option<String>) -> Result<(), String> {
+    let mut settings = config::load()?;
+    settings.extension.store_id = store_id;
+    settings.extension.validate()?;
+    config::save(&settings)?;
+    config::broadcast(&app, &settings)?;
+    Ok(())
+}
+
+/// Returns the name and version of the extracted extension, or `None` if it
+/// hasn't been extracted yet.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_version(id: Option<String>) -> Result<Option<ExtensionVersion>, String> {
+    ExtensionRegistry::get(id.as_deref())?.version()
+}
+
+/// Checks every profile of `browser` (the default browser when not given)
+/// for an unpacked extension loaded from the extension dir. The browser's
+/// preferences are only read, never written.
+#[tauri::command]
+#[apply(audited!)]
+pub fn is_extension_installed_in_browser(
+    browser: Option<Browser>,
+    id: Option<String>,
+) -> Result<Vec<ProfileInstallation>, String> {
+    let browser = browser.unwrap_or_else(browser::default_browser);
+    let paths = load_paths(&ExtensionRegistry::get(id.as_deref())?.dir()?);
+
+    Ok(browser
+        .relevant_profiles()
+        .into_iter()
+        .map(|profile| {
+            let extension_id = find_unpacked_extension(&profile.path, &paths).map(|(id, _)| id);
+            ProfileInstallation {
+                installed: extension_id.is_some(),
+                extension_id,
+                profile,
+            }
+        })
+        .collect())
+}
+
+/// Reports for every profile of `browser` (the default browser when not
+/// given) whether the extension is loaded, enabled and pinned, so onboarding
+/// can tell the user what's left to do.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_browser_state(
+    browser: Option<Browser>,
+    id: Option<String>,
+) -> Result<Vec<ExtensionBrowserState>, String> {
+    let browser = browser.unwrap_or_else(browser::default_browser);
+    let paths = load_paths(&ExtensionRegistry::get(id.as_deref())?.dir()?);
+
+    Ok(browser
+        .relevant_profiles()
+        .into_iter()
+        .map(|profile| {
+            let found = find_unpacked_extension(&profile.path, &paths);
+            let pinned = found
+                .as_ref()
+                .is_some_and(|(extension_id, _)| is_pinned(&profile.path, extension_id));
+            let (extension_id, enabled) = match found {
+                Some((extension_id, settings)) => (Some(extension_id), Some(is_enabled(&settings))),
+                None => (None, None),
+            };
+            ExtensionBrowserState {
+                installed: extension_id.is_some(),
+                extension_id,
+                enabled,
+                pinned,
+                profile,
+            }
+        })
+        .collect())
+}
+
+/// The browser records whichever path was picked in "Load unpacked", which
+/// for a dev build link may be either side of it, and for an install moved
+/// out of `~/ClaudIn` the old path.
+fn load_paths(extension_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![extension_dir.to_path_buf()];
+    let canonical = dunce::canonicalize(extension_dir).ok();
+    let legacy = legacy_install_dir()
+        .ok()
+        .zip(extension_dir.file_name())
+        .map(|(dir, name)| dir.join(name))
+        .filter(|legacy| legacy != extension_dir);
+    if let Some(legacy) = legacy {
+        if canonical.is_some() && dunce::canonicalize(&legacy).ok() == canonical {
+            paths.push(legacy);
+        }
+    }
+    paths.extend(canonical);
+    paths
+}
+
+/// Starts a Chromium browser with the extension loaded through
+/// `--load-extension`, skipping the extensions page altogether. `profile`
+/// picks a profile directory such as `Profile 1`, by default the one the
+/// user picked. With `isolated`, the browser gets a user data dir of its own
+/// in the app's config dir, which also works while it's already running.
+#[tauri::command]
+#[apply(audited!)]
+pub fn launch_browser_with_extension(
+    browser: Option<Browser>,
+    profile: Option<String>,
+    isolated: Option<bool>,
+) -> Result<Browser, String> {
+    let browser = browser.unwrap_or_else(browser::default_browser);
+    if !browser.is_chromium() {
+        return Err(format!("{} can't load extensions from the command line", browser.display_name()));
+    }
+    let extension_dir = get_extension_dir()?;
+    if !extension_dir.join(MANIFEST_FILE).exists() {
+        return Err("Extract the extension before loading it".to_string());
+    }
+
+    let mut args = vec![
+        format!("--load-extension={}", extension_dir.display()),
+        // Branded Chrome ignores --load-extension since version 137 unless
+        // this feature is turned off.
+        "--disable-features=DisableLoadExtensionCommandLineSwitch".to_string(),
+    ];
+    if isolated.unwrap_or(false) {
+        let user_data_dir = crate::get_config_dir()?
+            .join("browser-profiles")
+            .join(browser.id());
+        fs::create_dir_all(&user_data_dir).map_err(|e| e.to_string())?;
+        args.push(format!("--user-data-dir={}", user_data_dir.display()));
+        args.push("--no-first-run".to_string());
+    }
+    if let Some(profile) = profile.or_else(|| browser.chosen_profile()) {
+        args.push(browser::profile_arg(&profile)?);
+    }
+
+    tracing::info!("Launching {:?} with {:?}", browser, args);
+    browser.launch(&args)?;
+    Ok(browser)
+}
+
+/// Looks through a profile's preferences for an unpacked extension rooted at
+/// one of `paths` and returns its id and settings.
+fn find_unpacked_extension(profile_dir: &Path, paths: &[PathBuf]) -> Option<(String, serde_json::Value)> {
+    [browser::SECURE_PREFERENCES_FILE, browser::PREFERENCES_FILE]
+        .iter()
+        .filter_map(|file| browser::read_preferences(&profile_dir.join(file)))
+        .find_map(|mut prefs| {
+            let settings = prefs["extensions"]["settings"].as_object_mut()?;
+            let id = settings.iter().find_map(|(id, extension)| {
+                let path = extension["path"].as_str()?;
+                let matches = paths.iter().any(|candidate| same_path(Path::new(path), candidate));
+                matches.then(|| id.clone())
+            })?;
+            let extension = settings.remove(&id)?;
+            Some((id, extension))
+        })
+}
+
+/// Chrome used to record a state (1 for enabled) and now only the reasons an
+/// extension is disabled, as a bit mask or, in newer versions, a list.
+fn is_enabled(settings: &serde_json::Value) -> bool {
+    let has_disable_reasons = match &settings["disable_reasons"] {
+        serde_json::Value::Number(reasons) => reasons.as_u64() != Some(0),
+        serde_json::Value::Array(reasons) => !reasons.is_empty(),
+        _ => false,
+    };
+    let disabled_state = settings["state"].as_u64().is_some_and(|state| state != 1);
+    !has_disable_reasons && !disabled_state
+}
+
+fn is_pinned(profile_dir: &Path, extension_id: &str) -> bool {
+    [browser::PREFERENCES_FILE, browser::SECURE_PREFERENCES_FILE]
+        .iter()
+        .filter_map(|file| browser::read_preferences(&profile_dir.join(file)))
+        .any(|prefs| {
+            prefs["extensions"]["pinned_extensions"]
+                .as_array()
+                .is_some_and(|pinned| pinned.iter().any(|id| id.as_str() == Some(extension_id)))
+        })
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    if cfg!(windows) {
+        a.to_string_lossy().eq_ignore_ascii_case(&b.to_string_lossy())
+    } else {
+        a == b
+    }
+}
+
+/// Returns the id Chrome gives the extension when it's loaded unpacked from
+/// the extension dir, which the app and server can check message origins
+/// against and link to with `chrome://extensions/?id=`.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_id(id: Option<String>) -> Result<String, String> {
+    Ok(unpacked_extension_id(&ExtensionRegistry::get(id.as_deref())?.dir()?))
+}
+
+/// Chrome derives the id of an unpacked extension from the SHA-256 of its
+/// absolute path. On Windows the path is hashed as UTF-16 with the drive
+/// letter upper-cased.
+pub fn unpacked_extension_id(path: &Path) -> String {
+    #[cfg(windows)]
+    let bytes: Vec<u8> = {
+        use std::os::windows::ffi::OsStrExt;
+        let mut wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+        if wide.get(1) == Some(&(b':' as u16)) {
+            if let Some(drive) = char::from_u32(u32::from(wide[0])) {
+                wide[0] = drive.to_ascii_uppercase() as u16;
+            }
+        }
+        wide.iter().flat_map(|unit| unit.to_le_bytes()).collect()
+    };
+    #[cfg(not(windows))]
+    let bytes = path.as_os_str().as_encoded_bytes().to_vec();
+
+    encode_extension_id(&Sha256::digest(&bytes)[..16])
+}
+
+/// Chrome spells extension ids with the letters a-p instead of hex digits.
+pub fn encode_extension_id(hash: &[u8]) -> String {
+    hex::encode(hash)
+        .chars()
+        .map(|c| (b'a' + c.to_digit(16).unwrap_or(0) as u8) as char)
+        .collect()
+}
+
+/// Returns the changelog of the bundled extension, limited to versions newer
+/// than `since_version` when given, e.g. the `previousVersion` of an
+/// `extension-updated` event.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_changelog(
+    app_handle: tauri::AppHandle,
+    since_version: Option<String>,
+    id: Option<String>,
+) -> Result<Vec<ChangelogEntry>, String> {
+    let spec = ExtensionRegistry::get(id.as_deref())?;
+    let contents = spec
+        .bundle(&app_handle, ExtensionVariant::Chrome)?
+        .read_to_string(CHANGELOG_FILE)
+        .map_err(|e| format!("Extension changelog not found: {}", e))?;
+    let entries: Vec<ChangelogEntry> =
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid extension changelog: {}", e))?;
+
+    Ok(match since_version {
+        Some(since) => entries
+            .into_iter()
+            .filter(|entry| is_newer_version(&entry.version, &since))
+            .collect(),
+        None => entries,
+    })
+}
+
+/// Ids the default extension was given in every browser profile it's
+/// loaded in, starting with the one Chrome derives from the extension dir so
+/// a profile that hasn't loaded it yet is covered too.
+pub fn installed_extension_ids() -> Result<Vec<String>, String> {
+    let mut ids = vec![unpacked_extension_id(&get_extension_dir()?)];
+    for browser in Browser::all() {
+        for installation in is_extension_installed_in_browser(Some(browser), None)? {
+            if let Some(id) = installation.extension_id {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Where the default extension is extracted to.
+pub fn get_extension_dir() -> Result<PathBuf, String> {
+    ExtensionRegistry::default_extension().dir()
+}
+
+/// The folder holding the extensions, their Firefox builds and backups.
+pub fn get_install_dir() -> Result<PathBuf, String> {
+    match config::load()?.extension.install_dir {
+        Some(dir) => Ok(dir),
+        None => default_install_dir(),
+    }
+}
+
+/// The folders the app extracts the extension into unless told otherwise:
+/// the default one and the one used before that. A configured folder has
+/// to have been picked by the user instead.
+pub fn install_dirs() -> Result<Vec<PathBuf>, String> {
+    Ok(vec![default_install_dir()?, legacy_install_dir()?])
+}
+
+/// Linux keeps the extension with the rest of the app's data, in
+/// `$XDG_DATA_HOME/claudin`, rather than in a folder of the home dir.
+fn default_install_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = crate::portable::data_dir() {
+        return Ok(dir.join("extensions"));
+    }
+    if cfg!(target_os = "linux") {
+        let data_dir = dirs::data_dir().ok_or("Could not find data directory")?;
+        return Ok(data_dir.join("claudin"));
+    }
+    legacy_install_dir()
+}
+
+/// Where the extension was extracted to by default before Linux moved to
+/// the data dir.
+fn legacy_install_dir() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Could not find home directory")?;
+    Ok(home.join("ClaudIn"))
+}
+
+/// Moves an install from `~/ClaudIn` to the data dir on Linux, once. The
+/// browser still loads the extension from the old path, so links are left
+/// there: one for the whole folder, or one per moved entry when the folder
+/// holds files of the user's too.
+pub fn migrate_legacy_install_dir() -> Result<(), String> {
+    // A portable install has nothing to do with what's in the home dir.
+    if !cfg!(target_os = "linux") || crate::portable::is_enabled() || config::load()?.extension.install_dir.is_some() {
+        return Ok(());
+    }
+    let from = legacy_install_dir()?;
+    let to = default_install_dir()?;
+    if is_dir_link(&from) || !from.is_dir() {
+        return Ok(());
+    }
+
+    let moved = migrate_install_dir(&from, &to)?;
+    if moved.is_empty() {
+        return Ok(());
+    }
+    if !from.exists() {
+        create_dir_link(&to, &from)?;
+    } else {
+        for entry in &moved {
+            create_dir_link(&to.join(entry.file_name().unwrap_or_default()), entry)?;
+        }
+    }
+    tracing::info!("Moved the extension from {} to {}", from.display(), to.display());
+    Ok(())
+}
+
+/// Removes the links `migrate_legacy_install_dir` left in `~/ClaudIn` once
+/// what they point to in `root` is gone, returning the paths removed.
+fn remove_legacy_links(root: &Path) -> Vec<String> {
+    let Ok(legacy) = legacy_install_dir() else {
+        return Vec::new();
+    };
+    let is_dangling_link = |path: &Path| {
+        is_dir_link(path) && fs::read_link(path).is_ok_and(|target| target.starts_with(root) && !target.exists())
+    };
+
+    let mut removed = Vec::new();
+    if is_dangling_link(&legacy) {
+        if fs::remove_file(&legacy).is_ok() {
+            removed.push(legacy.to_string_lossy().to_string());
+        }
+        return removed;
+    }
+    if is_dir_link(&legacy) || legacy == root {
+        return removed;
+    }
+    for entry in fs::read_dir(&legacy).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if is_dangling_link(&path) && fs::remove_file(&path).is_ok() {
+            removed.push(path.to_string_lossy().to_string());
+        }
+    }
+    removed
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_extension_install_dir() -> Result<String, String> {
+    Ok(get_install_dir()?.to_string_lossy().to_string())
+}
+
+/// Moves everything the app extracted to `dir`, or back to the default
+/// folder when `dir` is `None`, and remembers the new location. Browsers
+/// keep loading the old path, so the extension has to be loaded again from
+/// the new one.
+#[tauri::command]
+#[apply(audited!)]
+pub fn set_extension_install_dir(app: AppHandle, dir: Option<String>) -> Result<String, String> {
+    let mut settings = config::load()?;
+    let mut extension_config = settings.extension.clone();
+    extension_config.install_dir = dir.map(PathBuf::from);
+    extension_config.validate()?;
+    crate::paths::guard_changed(
+        &app,
+        settings.extension.install_dir.as_deref(),
+        extension_config.install_dir.as_deref(),
+    )?;
+
+    let from = get_install_dir()?;
+    let to = match &extension_config.install_dir {
+        Some(dir) => dir.clone(),
+        None => default_install_dir()?,
+    };
+
+    if from != to {
+        migrate_install_dir(&from, &to)?;
+    }
+
+    settings.extension = extension_config;
+    config::save(&settings)?;
+    crate::integrity::watch(&app);
+    Ok(to.to_string_lossy().to_string())
+}
+
+/// Moves the extension's files from one install dir to another, checking
+/// first that none of them would overwrite something already there.
+/// Returns where the moved files used to be.
+fn migrate_install_dir(from: &Path, to: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries: Vec<PathBuf> = match fs::read_dir(from) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.starts_with("extension") || name.starts_with(".extension")
+            })
+            .map(|entry| entry.path())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    fs::create_dir_all(to).map_err(|e| e.to_string())?;
+    if let Some(existing) = entries
+        .iter()
+        .map(|entry| to.join(entry.file_name().unwrap_or_default()))
+        .find(|target| target.exists() || is_dir_link(target))
+    {
+        return Err(format!("{} already exists", existing.display()));
+    }
+
+    for entry in &entries {
+        let target = to.join(entry.file_name().unwrap_or_default());
+        tracing::info!("Moving {} to {}", entry.display(), target.display());
+        move_entry(entry, &target)?;
+    }
+
+    // Only drop the old folder if nothing else ended up in it.
+    let _ = fs::remove_dir(from);
+    Ok(entries)
+}
+
+/// Renames `from` to `to`, copying instead when they are on different
+/// volumes. Links to development builds are recreated rather than copied.
+fn move_entry(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if is_dir_link(from) {
+        let build = fs::read_link(from).map_err(|e| e.to_string())?;
+        create_dir_link(&build, to)?;
+        return remove_extension_dir(from);
+    }
+    if from.is_dir() {
+        copy_dir_recursive(from, to, &mut |_| {})?;
+        return fs::remove_dir_all(from).map_err(|e| e.to_string());
+    }
+    fs::copy(from, to).map_err(|e| e.to_string())?;
+    fs::remove_file(from).map_err(|e| e.to_string())
+}
+
+/// Copies `src` into `dst`, calling `on_file` with the size of every file
+/// copied. `dst` has to be inside the app's directories.
+pub fn copy_dir_recursive(src: &Path, dst: &Path, on_file: &mut dyn FnMut(u64)) -> Result<(), String> {
+    crate::paths::ensure_allowed(dst)?;
+    copy_dir_entries(src, dst, on_file)
+}
+
+fn copy_dir_entries(src: &Path, dst: &Path, on_file: &mut dyn FnMut(u64)) -> Result<(), String> {
+    if !dst.exists() {
+        fs::create_dir_all(dst).map_err(|e| e.to_string())?;
+    }
+    
+    for entry in fs::read_dir(src).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        let dest_path = dst.join(entry.file_name());
+        
+        if path.is_dir() {
+            copy_dir_entries(&path, &dest_path, on_file)?;
+        } else {
+            let bytes = fs::copy(&path, &dest_path).map_err(|e| e.to_string())?;
+            on_file(bytes);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Replaces `target` with the fully copied `staged` directory. Both are
+/// renames within the same parent directory, so `target` is either the old
+/// or the new install and never a partial copy.
+fn swap_into_place(staged: &Path, target: &Path) -> Result<(), String> {
+    let previous = sibling_dir(target, "previous");
+    if previous.exists() {
+        fs::remove_dir_all(&previous).map_err(|e| e.to_string())?;
+    }
+    if target.exists() {
+        // Windows won't rename a directory while the browser has files in it
+        // open, so fall back to updating it in place.
+        if let Err(e) = fs::rename(target, &previous) {
+            tracing::warn!("Could not replace {}, syncing it instead: {}", target.display(), e);
+            sync_dir(staged, target, target)?;
+            return fs::remove_dir_all(staged).map_err(|e| e.to_string());
+        }
+    }
+
+    if let Err(e) = fs::rename(staged, target) {
+        if previous.exists() {
+            let _ = fs::rename(&previous, target);
+        }
+        return Err(e.to_string());
+    }
+
+    if previous.exists() {
+        if let Err(e) = keep_backup(target, &previous) {
+            tracing::warn!("Could not keep a backup of the previous extension: {}", e);
+            let _ = fs::remove_dir_all(&previous);
+        }
+    }
+    Ok(())
+}
+
+/// Moves the copy `target` replaced to `<target>.backup-<version>`, dropping
+/// the oldest backups beyond `MAX_BACKUPS`.
+fn keep_backup(target: &Path, previous: &Path) -> Result<(), String> {
+    let version = read_manifest(&previous.join(MANIFEST_FILE))?.version;
+    let backup = backup_dir(target, &version)?;
+    if backup.exists() {
+        fs::remove_dir_all(&backup).map_err(|e| e.to_string())?;
+    }
+    fs::rename(previous, &backup).map_err(|e| e.to_string())?;
+
+    for (_, old) in list_backups(target).into_iter().skip(MAX_BACKUPS) {
+        let _ = fs::remove_dir_all(old);
+    }
+    Ok(())
+}
+
+fn backup_dir(target: &Path, version: &str) -> Result<PathBuf, String> {
+    // The version ends up in a path, so only allow what versions are made of.
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') {
+        return Err(format!("Invalid extension version {:?}", version));
+    }
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    Ok(target.with_file_name(format!("{}{}{}", name, BACKUP_INFIX, version)))
+}
+
+/// Backups of `target` with their version, newest version first.
+fn list_backups(target: &Path) -> Vec<(String, PathBuf)> {
+    let prefix = format!("{}{}", target.file_name().unwrap_or_default().to_string_lossy(), BACKUP_INFIX);
+    let Some(Ok(entries)) = target.parent().map(fs::read_dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let version = name.strip_prefix(&prefix)?.to_string();
+            Some((version, entry.path()))
+        })
+        .collect();
+    backups.sort_by(|(a, _), (b, _)| {
+        if is_newer_version(a, b) {
+            std::cmp::Ordering::Less
+        } else if is_newer_version(b, a) {
+            std::cmp::Ordering::Greater
+        } else {
+            a.cmp(b)
+        }
+    });
+    backups
+}
+
+/// `~/ClaudIn/extension` becomes `~/ClaudIn/.extension-<suffix>`, keeping
+/// temporary copies on the same volume so renames between them are atomic.
+pub fn sibling_dir(dir: &Path, suffix: &str) -> PathBuf {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    dir.with_file_name(format!(".{}-{}", name, suffix))
+}
+
+fn is_dir_link(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// Removes the extension dir, or only the link if it points to a
+/// development build, leaving the build itself alone.
+fn remove_extension_dir(path: &Path) -> Result<(), String> {
+    if is_dir_link(path) {
+        #[cfg(unix)]
+        return fs::remove_file(path).map_err(|e| e.to_string());
+        #[cfg(windows)]
+        return fs::remove_dir(path).map_err(|e| e.to_string());
+    }
+    if path.exists() {
+        fs::remove_dir_all(path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_dir_link(target: &Path, link: &Path) -> Result<(), String> {
+    std::os::unix::fs::symlink(target, link).map_err(|e| e.to_string())
+}
+
+/// Uses a junction, which unlike a symlink doesn't need admin rights or
+/// developer mode.
+#[cfg(windows)]
+fn create_dir_link(target: &Path, link: &Path) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let output = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg("mklink")
+        .arg("/J")
+        .arg(link)
+        .arg(target)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to create junction: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Makes `dst` an exact copy of `src`: files are overwritten and anything
+/// `src` doesn't have is deleted, so files dropped from the extension don't
+/// linger. Nothing outside `root` is ever deleted, and links are removed
+/// rather than followed.
+fn sync_dir(src: &Path, dst: &Path, root: &Path) -> Result<(), String> {
+    if !dst.starts_with(root) || is_dir_link(dst) {
+        return Err(format!("Refusing to sync into {}", dst.display()));
+    }
+    fs::create_dir_all(dst).map_err(|e| e.to_string())?;
+
+    for entry in fs::read_dir(dst).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        if !path.starts_with(root) {
+            return Err(format!("Refusing to remove {}", path.display()));
+        }
+        let metadata = fs::symlink_metadata(&path).map_err(|e| e.to_string())?;
+        let source = src.join(entry.file_name());
+        let source_kind = fs::symlink_metadata(&source).ok().map(|m| m.is_dir());
+
+        let keep = match source_kind {
+            Some(source_is_dir) => source_is_dir == metadata.is_dir() && !metadata.file_type().is_symlink(),
+            None => false,
+        };
+        if keep {
+            continue;
+        }
+        // Directory links on Windows can only be removed with `remove_dir`,
+        // which like `remove_file` on a link leaves its target alone.
+        let removed = if metadata.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path).or_else(|_| fs::remove_dir(&path))
+        };
+        removed.map_err(|e| e.to_string())?;
+    }
+
+    for entry in fs::read_dir(src).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        let dest_path = dst.join(entry.file_name());
+        if path.is_dir() {
+            sync_dir(&path, &dest_path, root)?;
+        } else {
+            fs::copy(&path, &dest_path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Compares extension versions, which Chrome allows to have up to four
+/// dot-separated numbers, so they aren't necessarily semver.
+/// Missing trailing numbers count as zero, so "1.0" and "1.0.0" are equal.
+pub fn is_newer_version(candidate: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Option<Vec<u64>> {
+        version.split('.').map(|part| part.parse().ok()).collect()
+    };
+    match (parts(candidate), parts(current)) {
+        (Some(mut candidate), Some(mut current)) => {
+            let len = candidate.len().max(current.len());
+            candidate.resize(len, 0);
+            current.resize(len, 0);
+            candidate > current
+        }
+        _ => false,
+    }
+}
+
+pub fn read_manifest(path: &Path) -> Result<ExtensionVersion, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid extension manifest: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions_number_by_number() {
+        assert!(is_newer_version("1.2.0", "1.1.9"));
+        assert!(is_newer_version("1.10.0", "1.9.0"));
+        assert!(is_newer_version("2.0", "1.99.99"));
+        assert!(!is_newer_version("1.1.9", "1.2.0"));
+        assert!(!is_newer_version("1.2.0", "1.2.0"));
+    }
+
+    #[test]
+    fn missing_trailing_numbers_count_as_zero() {
+        assert!(!is_newer_version("1.0", "1.0.0"));
+        assert!(!is_newer_version("1.0.0", "1.0"));
+        assert!(is_newer_version("1.0.1", "1.0"));
+        assert!(!is_newer_version("1.0", "1.0.1"));
+    }
+
+    #[test]
+    fn versions_that_arent_numbers_are_never_newer() {
+        assert!(!is_newer_version("1.2.0-beta", "1.1.0"));
+        assert!(!is_newer_version("2.0.0", "dev"));
+        assert!(!is_newer_version("", "1.0.0"));
+    }
+}
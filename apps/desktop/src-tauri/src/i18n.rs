@@ -0,0 +1,111 @@
+use crate::audit::audited;
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Used for unset locales, unsupported ones and strings a catalog lacks.
+const FALLBACK_LOCALE: &str = "en";
+
+/// Message catalogs by locale, with the language's own name. Messages are
+/// keyed by id and fill in `{name}` placeholders.
+const CATALOGS: [(&str, &str, &str); 2] = [
+    ("en", "English", include_str!("../resources/locales/en.json")),
+    ("fr", "Français", include_str!("../resources/locales/fr.json")),
+];
+
+static PARSED: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+
+/// The locale set in the settings, kept here so translating never has to
+/// read them.
+static LOCALE: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Locale {
+    id: &'static str,
+    name: &'static str,
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn list_locales() -> Vec<Locale> {
+    CATALOGS.iter().map(|(id, name, _)| Locale { id, name }).collect()
+}
+
+/// The locale strings from the app are in, for the webview to match.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_locale() -> &'static str {
+    current()
+}
+
+/// Switches to the locale from the settings, the system's when it's unset.
+pub fn apply(locale: Option<&str>) {
+    *LOCALE.write().unwrap() = locale.map(str::to_string);
+}
+
+pub fn is_supported(locale: &str) -> bool {
+    catalogs().contains_key(locale)
+}
+
+/// The message `key` in the current locale.
+pub fn t(key: &str) -> String {
+    t_with(key, &[])
+}
+
+/// The message `key` in the current locale with its placeholders filled in.
+pub fn t_with(key: &str, args: &[(&str, &str)]) -> String {
+    let catalogs = catalogs();
+    let message = [current(), FALLBACK_LOCALE]
+        .iter()
+        .find_map(|locale| catalogs.get(locale)?.get(key))
+        .cloned();
+    let Some(mut message) = message else {
+        tracing::warn!("Missing message {}", key);
+        return key.to_string();
+    };
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), value);
+    }
+    message
+}
+
+fn current() -> &'static str {
+    let configured = LOCALE.read().unwrap().clone();
+    configured
+        .into_iter()
+        .chain(system_locale())
+        .find_map(|locale| supported(&locale))
+        .unwrap_or(FALLBACK_LOCALE)
+}
+
+/// The supported locale for a tag like `fr_FR.UTF-8` or `fr-CA`.
+fn supported(tag: &str) -> Option<&'static str> {
+    let language = tag.split(['_', '-', '.']).next()?.to_ascii_lowercase();
+    CATALOGS.iter().map(|(id, _, _)| *id).find(|id| *id == language)
+}
+
+/// What the user's environment asks for. Windows doesn't set these, so
+/// there the setting is how to get anything but English.
+fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+}
+
+fn catalogs() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    PARSED.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(id, _, catalog)| {
+                let messages = serde_json::from_str(catalog).unwrap_or_else(|e| {
+                    tracing::error!("Invalid message catalog {}: {}", id, e);
+                    HashMap::new()
+                });
+                (*id, messages)
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,469 @@
+use crate::audit::audited;
+use crate::bundle::Bundle;
+use crate::extension::{self, ExtensionRegistry, ExtensionSpec, ExtensionVariant};
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Shipped with the bundled extension by `scripts/extension-checksums.mjs`,
+/// mapping each file's path to its SHA-256.
+pub const CHECKSUMS_FILE: &str = "checksums.json";
+
+/// Signature of the checksums by the updater's key, written by release
+/// builds.
+pub const SIGNATURE_FILE: &str = "checksums.json.sig";
+
+/// How long the install dir has to stay quiet before a change is checked,
+/// so an extraction or a repair is only checked once it is done.
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// The install dir being watched, and its watcher.
+static WATCHER: Mutex<Option<(PathBuf, Debouncer<RecommendedWatcher>)>> = Mutex::new(None);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    id: &'static str,
+    variant: ExtensionVariant,
+    pub ok: bool,
+    checked: usize,
+    pub missing: Vec<String>,
+    pub modified: Vec<String>,
+    pub extra: Vec<String>,
+    /// Whether the extension was re-extracted because of a problem.
+    repaired: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    /// Whether the extension matches the bundled one after the repair.
+    ok: bool,
+    /// Files restored from the bundled extension.
+    restored: Vec<String>,
+    /// Files that aren't part of the extension and were deleted.
+    removed: Vec<String>,
+    failures: Vec<RepairFailure>,
+    /// Whether the whole extension had to be extracted again because there
+    /// was nothing to repair file by file.
+    reextracted: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairFailure {
+    path: String,
+    error: String,
+}
+
+/// Hashes every extracted file of the build for `browser`, by default the
+/// one the preferred browser takes, and compares it against the checksums
+/// shipped with the app. With `repair`, a damaged install is extracted again
+/// and the report describes the result.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn verify_extension_integrity(
+    app: AppHandle,
+    repair: Option<bool>,
+    id: Option<String>,
+    browser: Option<ExtensionVariant>,
+) -> Result<IntegrityReport, String> {
+    let spec = ExtensionRegistry::get(id.as_deref())?;
+    let variant = browser.unwrap_or_else(ExtensionVariant::preferred);
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = verify(&app, spec, variant)?;
+        if report.ok || !repair.unwrap_or(false) {
+            return Ok(report);
+        }
+
+        tracing::info!(
+            "Repairing extension {} ({}): {} missing, {} modified, {} extra files",
+            spec.id,
+            variant.folder_name(),
+            report.missing.len(),
+            report.modified.len(),
+            report.extra.len()
+        );
+        extension::extract(&app, spec, variant)?;
+        Ok(IntegrityReport {
+            repaired: true,
+            ..verify(&app, spec, variant)?
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Puts back missing and modified extension files from the bundled copy and
+/// deletes files that don't belong, leaving intact files alone so a loaded
+/// extension keeps its id and settings. Repairs the build for `browser`, by
+/// default the one the preferred browser takes.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn repair_extension(
+    app: AppHandle,
+    id: Option<String>,
+    browser: Option<ExtensionVariant>,
+) -> Result<RepairReport, String> {
+    let spec = ExtensionRegistry::get(id.as_deref())?;
+    let variant = browser.unwrap_or_else(ExtensionVariant::preferred);
+    tauri::async_runtime::spawn_blocking(move || repair(&app, spec, variant))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn repair(app: &AppHandle, spec: &ExtensionSpec, variant: ExtensionVariant) -> Result<RepairReport, String> {
+    if is_dev_build(spec, variant)? {
+        return Err("The extension is linked to a development build".to_string());
+    }
+
+    let extension_dir = spec.variant_dir(variant)?;
+    let mut report = RepairReport {
+        ok: true,
+        restored: Vec::new(),
+        removed: Vec::new(),
+        failures: Vec::new(),
+        reextracted: false,
+    };
+
+    if !spec.is_extracted(variant)? {
+        extension::extract(app, spec, variant)?;
+        report.reextracted = true;
+        report.ok = verify(app, spec, variant)?.ok;
+        return Ok(report);
+    }
+
+    let problems = verify(app, spec, variant)?;
+    if problems.ok {
+        return Ok(report);
+    }
+
+    let bundle = spec.bundle(app, variant)?;
+    verify_bundle(app, &bundle)?;
+    // After a rollback the bundled files may be of another version, and
+    // only the ones matching the installed checksums can be put back.
+    let (expected, _) = expected_checksums(app, spec, variant, &extension_dir)?;
+    for name in problems.missing.iter().chain(&problems.modified) {
+        let result = relative_path(name).and_then(|path| {
+            let contents = bundle.read(name)?;
+            let is_checksums = name == CHECKSUMS_FILE;
+            if !is_checksums && expected.get(name) != Some(&hex::encode(Sha256::digest(&contents))) {
+                return Err("The bundled extension has another version of this file".to_string());
+            }
+            let target = extension_dir.join(path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            fs::write(target, contents).map_err(|e| e.to_string())?;
+            if is_checksums {
+                // The signature goes with the checksums it signs.
+                match bundle.read(SIGNATURE_FILE) {
+                    Ok(signature) => fs::write(extension_dir.join(SIGNATURE_FILE), signature),
+                    Err(_) => fs::remove_file(extension_dir.join(SIGNATURE_FILE)).or(Ok(())),
+                }
+                .map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(_) => report.restored.push(name.clone()),
+            Err(error) => report.failures.push(RepairFailure {
+                path: name.clone(),
+                error,
+            }),
+        }
+    }
+
+    for name in &problems.extra {
+        let result = relative_path(name)
+            .and_then(|path| fs::remove_file(extension_dir.join(path)).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => report.removed.push(name.clone()),
+            Err(error) => report.failures.push(RepairFailure {
+                path: name.clone(),
+                error,
+            }),
+        }
+    }
+
+    tracing::info!(
+        "Repaired extension {} ({}): {} restored, {} removed, {} failed",
+        spec.id,
+        variant.folder_name(),
+        report.restored.len(),
+        report.removed.len(),
+        report.failures.len()
+    );
+    report.ok = verify(app, spec, variant)?.ok;
+    Ok(report)
+}
+
+/// Only the Chrome build can be linked to a development build.
+fn is_dev_build(spec: &ExtensionSpec, variant: ExtensionVariant) -> Result<bool, String> {
+    Ok(variant == ExtensionVariant::Chrome && spec.dev_build()?.is_some())
+}
+
+/// Turns a checksums file key back into a path, refusing anything that would
+/// point outside the extension dir.
+fn relative_path(name: &str) -> Result<&Path, String> {
+    let path = Path::new(name);
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path)
+    } else {
+        Err(format!("Invalid extension file path {:?}", name))
+    }
+}
+
+/// Watches every extracted build of the extensions for files that get
+/// deleted or modified, for instance by antivirus tools, and emits
+/// `extension-tampered` when a build no longer matches its checksums. With `auto_repair` set the
+/// files are restored right away.
+pub fn start_watcher(app: &AppHandle) {
+    watch(app);
+}
+
+/// Starts watching the install dir once it exists, and again after it
+/// moved. Called whenever something is extracted or the dir changes.
+pub fn watch(app: &AppHandle) {
+    let dir = match extension::get_install_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("Extension watcher failed: {}", e);
+            return;
+        }
+    };
+    let mut watcher = WATCHER.lock().unwrap();
+    if !dir.is_dir() || watcher.as_ref().is_some_and(|(watched, _)| *watched == dir) {
+        return;
+    }
+
+    let handler_app = app.clone();
+    let started = new_debouncer(WATCH_DEBOUNCE, move |events| on_change(&handler_app, events)).and_then(
+        |mut debouncer| {
+            debouncer.watcher().watch(&dir, RecursiveMode::Recursive)?;
+            Ok(debouncer)
+        },
+    );
+    match started {
+        Ok(debouncer) => *watcher = Some((dir, debouncer)),
+        Err(e) => tracing::warn!("Could not watch {}: {}", dir.display(), e),
+    }
+}
+
+/// Checks every extension build with a file among the changed ones.
+fn on_change(app: &AppHandle, events: DebounceEventResult) {
+    let events = match events {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::warn!("Extension watcher failed: {}", e);
+            return;
+        }
+    };
+    for spec in ExtensionRegistry::all() {
+        for variant in ExtensionVariant::ALL {
+            let changed = spec
+                .variant_dir(variant)
+                .is_ok_and(|dir| events.iter().any(|event| event.path.starts_with(&dir)));
+            if changed {
+                if let Err(e) = check(app, spec, variant) {
+                    tracing::warn!(
+                        "Extension watcher failed for {} ({}): {}",
+                        spec.id,
+                        variant.folder_name(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Verifies an extension build that changed, repairing it if the user wants
+/// that.
+fn check(app: &AppHandle, spec: &ExtensionSpec, variant: ExtensionVariant) -> Result<(), String> {
+    // Development builds change all the time.
+    if is_dev_build(spec, variant)? || !spec.variant_dir(variant)?.exists() {
+        return Ok(());
+    }
+
+    // Also the case right after the app extracted it again itself.
+    let mut report = verify(app, spec, variant)?;
+    if report.ok {
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "Extension {} ({}) was tampered with: {} missing, {} modified, {} extra files",
+        spec.id,
+        variant.folder_name(),
+        report.missing.len(),
+        report.modified.len(),
+        report.extra.len()
+    );
+    if crate::config::load()?.extension.auto_repair {
+        report.repaired = repair(app, spec, variant)?.ok;
+    }
+    let _ = app.emit("extension-tampered", report);
+    Ok(())
+}
+
+pub fn verify(app: &AppHandle, spec: &ExtensionSpec, variant: ExtensionVariant) -> Result<IntegrityReport, String> {
+    let extension_dir = spec.variant_dir(variant)?;
+    let (expected, checksums_problem) = expected_checksums(app, spec, variant, &extension_dir)?;
+
+    let mut actual = BTreeMap::new();
+    if extension_dir.exists() {
+        hash_dir(&extension_dir, &extension_dir, &mut actual)?;
+    }
+    actual.remove(CHECKSUMS_FILE);
+    actual.remove(SIGNATURE_FILE);
+
+    let mut missing = Vec::new();
+    let mut modified = Vec::new();
+    match checksums_problem {
+        Some(ChecksumsProblem::Missing) => missing.push(CHECKSUMS_FILE.to_string()),
+        Some(ChecksumsProblem::Invalid) => modified.push(CHECKSUMS_FILE.to_string()),
+        None => {}
+    }
+    for (name, hash) in &expected {
+        match actual.get(name) {
+            None => missing.push(name.clone()),
+            Some(actual_hash) if actual_hash != hash => modified.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    let extra: Vec<String> = actual
+        .keys()
+        .filter(|name| !expected.contains_key(*name))
+        .cloned()
+        .collect();
+
+    Ok(IntegrityReport {
+        id: spec.id,
+        variant,
+        ok: missing.is_empty() && modified.is_empty() && extra.is_empty(),
+        checked: expected.len(),
+        missing,
+        modified,
+        extra,
+        repaired: false,
+    })
+}
+
+/// What is wrong with the checksums in the extension dir.
+enum ChecksumsProblem {
+    Missing,
+    /// Unreadable, or not signed by the updater's key.
+    Invalid,
+}
+
+/// The checksums the extracted extension has to match: the ones extracted
+/// along with it, so a version that was rolled back to checks out too.
+/// When those are missing or don't verify, the bundled extension's are
+/// used instead and the problem is reported.
+fn expected_checksums(
+    app: &AppHandle,
+    spec: &ExtensionSpec,
+    variant: ExtensionVariant,
+    extension_dir: &Path,
+) -> Result<(BTreeMap<String, String>, Option<ChecksumsProblem>), String> {
+    let installed = match fs::read(extension_dir.join(CHECKSUMS_FILE)) {
+        Ok(checksums) => {
+            let signature = fs::read(extension_dir.join(SIGNATURE_FILE)).map_err(|e| e.to_string());
+            check_signature(app, &checksums, signature)
+                .and_then(|()| parse_checksums(&checksums))
+                .map_err(|e| {
+                    tracing::warn!("Ignoring the checksums in {}: {}", extension_dir.display(), e);
+                    ChecksumsProblem::Invalid
+                })
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(ChecksumsProblem::Missing),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    match installed {
+        Ok(checksums) => Ok((checksums, None)),
+        Err(problem) => {
+            let bundled = spec
+                .bundle(app, variant)?
+                .read(CHECKSUMS_FILE)
+                .map_err(|e| format!("Extension checksums not found: {}", e))?;
+            Ok((parse_checksums(&bundled)?, Some(problem)))
+        }
+    }
+}
+
+fn parse_checksums(contents: &[u8]) -> Result<BTreeMap<String, String>, String> {
+    serde_json::from_slice(contents).map_err(|e| format!("Invalid extension checksums: {}", e))
+}
+
+/// Checks that checksums carry a valid signature. Builds without a signing
+/// key only get a warning in debug builds.
+fn check_signature(app: &AppHandle, checksums: &[u8], signature: Result<Vec<u8>, String>) -> Result<(), String> {
+    match signature {
+        Ok(signature) => crate::download::verify_signature(app, checksums, &signature),
+        Err(_) if cfg!(debug_assertions) => {
+            tracing::warn!("Extension checksums aren't signed, which only debug builds allow");
+            Ok(())
+        }
+        Err(e) => Err(format!("The extension isn't signed: {}", e)),
+    }
+}
+
+/// Checks a bundle before anything is copied out of it: its checksums have
+/// to carry a valid signature, and every file has to match them.
+pub fn verify_bundle(app: &AppHandle, bundle: &Bundle) -> Result<(), String> {
+    let checksums = bundle.read(CHECKSUMS_FILE)?;
+    check_signature(app, &checksums, bundle.read(SIGNATURE_FILE))?;
+
+    let expected = parse_checksums(&checksums)?;
+    let mut actual = bundle.hashes()?;
+    actual.remove(CHECKSUMS_FILE);
+    actual.remove(SIGNATURE_FILE);
+    if let Some(name) = actual
+        .keys()
+        .chain(expected.keys())
+        .find(|name| actual.get(*name) != expected.get(*name))
+    {
+        return Err(format!("The extension file {} doesn't match its signed checksum", name));
+    }
+    Ok(())
+}
+
+/// Collects the SHA-256 of every file under `dir`, keyed by its path relative
+/// to `root` with `/` separators, matching the checksums file.
+pub fn hash_dir(root: &Path, dir: &Path, hashes: &mut BTreeMap<String, String>) -> Result<(), String> {
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            hash_dir(root, &path, hashes)?;
+            continue;
+        }
+
+        let name = path
+            .strip_prefix(root)
+            .map_err(|e| e.to_string())?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        hashes.insert(name, hash_file(&path)?);
+    }
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -1,236 +1,241 @@
-use std::fs;
-use std::path::PathBuf;
-use tauri::Manager;
-
-#[tauri::command]
-fn get_extension_path() -> Result<String, String> {
-    let extension_dir = get_extension_dir()?;
-    Ok(extension_dir.to_string_lossy().to_string())
-}
-
-#[tauri::command]
-fn is_extension_extracted() -> Result<bool, String> {
-    let extension_dir = get_extension_dir()?;
-    let manifest_path = extension_dir.join("manifest.json");
-    Ok(manifest_path.exists())
-}
-
-#[tauri::command]
-fn extract_extension(app_handle: tauri::AppHandle) -> Result<String, String> {
-    let extension_dir = get_extension_dir()?;
-    
-    if extension_dir.exists() {
-        fs::remove_dir_all(&extension_dir).map_err(|e| e.to_string())?;
-    }
-    fs::create_dir_all(&extension_dir).map_err(|e| e.to_string())?;
-    
-    let resource_path = app_handle
-        .path()
-        .resource_dir()
-        .map_err(|e: tauri::Error| e.to_string())?
-        .join("extension");
-    
-    let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
-    
-    let dev_paths = vec![
-        cwd.join("src-tauri").join("resources").join("extension"),
-        cwd.join("resources").join("extension"),
-    ];
-    
-    let source_path = if resource_path.join("manifest.json").exists() {
-        resource_path
-    } else {
-        dev_paths
-            .into_iter()
-            .find(|p| p.join("manifest.json").exists())
-            .ok_or("Extension resources not found")?
-    };
-    
-    copy_dir_recursive(&source_path, &extension_dir)?;
-    
-    Ok(extension_dir.to_string_lossy().to_string())
-}
+mod audit;
+mod backup;
+mod browser;
+mod bundle;
+mod compat;
+mod config;
+mod crx;
+mod download;
+mod environment;
+mod extension;
+mod i18n;
+mod integrity;
+mod logs;
+mod native_messaging;
+mod paths;
+mod permissions;
+mod policy;
+mod portable;
+mod prerequisites;
+mod profiles;
+mod redact;
+mod reset;
+mod runtime;
+mod safari;
+mod secrets;
+mod server;
+mod session;
+mod setup;
+mod tls;
+mod uninstall;
 
-#[tauri::command]
-fn open_extension_folder() -> Result<(), String> {
-    let extension_dir = get_extension_dir()?;
-    
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .arg(&extension_dir)
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    
-    #[cfg(target_os = "windows")]
-    {
-        std::process::Command::new("explorer")
-            .arg(&extension_dir)
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("xdg-open")
-            .arg(&extension_dir)
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    
-    Ok(())
-}
+use std::path::PathBuf;
+use tauri::RunEvent;
 
-#[tauri::command]
-fn open_chrome_extensions() -> Result<(), String> {
-    let url = "chrome://extensions";
-    
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .args(["-a", "Google Chrome", url])
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    
-    #[cfg(target_os = "windows")]
-    {
-        std::process::Command::new("cmd")
-            .args(["/C", "start", "chrome", url])
-            .spawn()
-            .map_err(|e| e.to_string())?;
-    }
-    
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("google-chrome")
-            .arg(url)
-            .spawn()
-            .or_else(|_| {
-                std::process::Command::new("chromium-browser")
-                    .arg(url)
-                    .spawn()
-            })
-            .map_err(|e| e.to_string())?;
-    }
-    
-    Ok(())
+pub fn get_config_dir() -> Result<PathBuf, String> {
+    config::config_dir()
 }
 
-#[tauri::command]
-fn mark_setup_complete() -> Result<(), String> {
-    let config_dir = dirs::config_dir()
-        .ok_or("Could not find config directory")?
-        .join("claudin");
-    
-    fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
-    
-    let setup_file = config_dir.join(".setup_complete");
-    fs::write(&setup_file, "1").map_err(|e| e.to_string())?;
-    
-    Ok(())
+/// Whether a browser started the app as the extension's native messaging
+/// host rather than the user opening it.
+pub fn is_native_messaging_launch() -> bool {
+    native_messaging::is_host_launch()
 }
 
-#[tauri::command]
-fn is_setup_complete() -> Result<bool, String> {
-    let config_dir = dirs::config_dir()
-        .ok_or("Could not find config directory")?
-        .join("claudin");
-    
-    let setup_file = config_dir.join(".setup_complete");
-    Ok(setup_file.exists())
+pub fn run_native_messaging_host() {
+    native_messaging::run_host()
 }
 
-fn get_extension_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("Could not find home directory")?;
-    Ok(home.join("ClaudIn").join("extension"))
+/// Whether the installer started the app to clean up before it's removed.
+pub fn is_uninstall_launch() -> bool {
+    uninstall::is_uninstall_launch()
 }
 
-fn copy_dir_recursive(src: &PathBuf, dst: &PathBuf) -> Result<(), String> {
-    if !dst.exists() {
-        fs::create_dir_all(dst).map_err(|e| e.to_string())?;
-    }
-    
-    for entry in fs::read_dir(src).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-        let dest_path = dst.join(entry.file_name());
-        
-        if path.is_dir() {
-            copy_dir_recursive(&path, &dest_path)?;
-        } else {
-            fs::copy(&path, &dest_path).map_err(|e| e.to_string())?;
-        }
-    }
-    
-    Ok(())
+pub fn run_uninstall() {
+    uninstall::run_for_installer()
 }
 
-fn start_server(app: &tauri::AppHandle) {
-    use tauri_plugin_shell::ShellExt;
-    
-    let server_script = if cfg!(debug_assertions) {
-        std::env::current_dir()
-            .unwrap()
-            .parent()
-            .unwrap()
-            .parent()
-            .unwrap()
-            .join("server")
-            .join("src")
-            .join("index.ts")
-    } else {
-        std::env::current_dir()
-            .unwrap()
-            .join("server")
-            .join("index.js")
-    };
-
-    println!("Starting server from: {:?}", server_script);
-
-    let result = app
-        .shell()
-        .command("npx")
-        .args(["tsx", server_script.to_str().unwrap()])
-        .spawn();
-
-    match result {
-        Ok((_rx, child)) => {
-            println!("Server started with PID: {:?}", child.pid());
-        }
-        Err(e) => {
-            eprintln!("Failed to start server: {}", e);
+/// Records commands in the audit log and runs them through the permission
+/// check before dispatching them.
+fn guarded<R: tauri::Runtime>(
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let call = audit::Call::new(&invoke);
+        match permissions::check(invoke) {
+            Some(invoke) => {
+                // Sync commands run within the handler, so the call has to
+                // be waiting for them before it's called.
+                let command = invoke.message.command().to_string();
+                call.dispatch();
+                let handled = handler(invoke);
+                if !handled {
+                    if let Some(call) = audit::take(&command) {
+                        call.record("unknown");
+                    }
+                }
+                handled
+            }
+            None => {
+                call.record("denied");
+                true
+            }
         }
     }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logs::init();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_process::init())
-        .invoke_handler(tauri::generate_handler![
-            get_extension_path,
-            is_extension_extracted,
-            extract_extension,
-            open_extension_folder,
-            open_chrome_extensions,
-            mark_setup_complete,
-            is_setup_complete,
-        ])
+        .plugin(tauri_plugin_notification::init())
+        .manage(server::ServerState::default())
+        .invoke_handler(guarded(tauri::generate_handler![
+            extension::list_extensions,
+            extension::get_extension_path,
+            extension::get_extension_install_dir,
+            extension::set_extension_install_dir,
+            extension::open_extension_store_page,
+            extension::get_extension_store_id,
+            extension::set_extension_store_id,
+            extension::is_extension_extracted,
+            extension::extract_extension,
+            extension::extract_extension_dry_run,
+            extension::open_extension_folder,
+            browser::detect_installed_browsers,
+            browser::get_system_default_browser,
+            browser::open_browser_extensions,
+            browser::open_linkedin,
+            browser::is_browser_running,
+            browser::relaunch_browser,
+            browser::check_browser_compatibility,
+            browser::get_preferred_browser,
+            browser::set_preferred_browser,
+            policy::check_browser_policies,
+            policy::get_browser_capabilities,
+            safari::detect_safari,
+            safari::install_safari_extension,
+            safari::open_safari_extensions_preferences,
+            session::check_linkedin_session,
+            session::set_linkedin_session_consent,
+            environment::get_environment_report,
+            browser::list_browser_profiles,
+            browser::get_browser_profile,
+            browser::set_browser_profile,
+            extension::open_firefox_addons,
+            extension::get_extension_version,
+            extension::get_extension_id,
+            extension::get_extension_changelog,
+            extension::is_extension_installed_in_browser,
+            extension::get_extension_browser_state,
+            extension::launch_browser_with_extension,
+            extension::link_extension_dev_build,
+            extension::unlink_extension_dev_build,
+            extension::get_extension_dev_build,
+            extension::uninstall_extension,
+            extension::list_extension_backups,
+            extension::rollback_extension,
+            integrity::verify_extension_integrity,
+            integrity::repair_extension,
+            crx::package_extension_crx,
+            download::download_extension,
+            native_messaging::register_native_messaging_host,
+            native_messaging::unregister_native_messaging_host,
+            config::get_settings,
+            config::update_settings,
+            config::export_settings,
+            config::import_settings,
+            config::get_setting_sources,
+            paths::get_app_paths,
+            backup::list_config_backups,
+            backup::restore_config_backup,
+            setup::get_setup_progress,
+            setup::complete_setup_step,
+            setup::mark_setup_complete,
+            setup::is_setup_complete,
+            setup::get_run_context,
+            setup::get_checklist,
+            setup::set_checklist_item,
+            prerequisites::validate_setup,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::switch_profile,
+            server::get_server_status,
+            server::list_servers,
+            server::get_server_port,
+            server::get_server_metrics,
+            server::restart_server,
+            server::stop_server,
+            server::ensure_server_running,
+            server::wait_for_server_ready,
+            server::get_server_log_path,
+            server::read_server_log_tail,
+            audit::export_audit_log,
+            server::get_server_config,
+            server::set_server_config,
+            server::get_allowed_origins,
+            server::set_allowed_origins,
+            server::set_server_profile,
+            server::validate_server_connection,
+            compat::check_compatibility,
+            secrets::set_api_key,
+            secrets::get_api_key_status,
+            secrets::delete_api_key,
+            secrets::get_bridge_token,
+            secrets::get_secret_storage_status,
+            tls::get_tls_certificate,
+            tls::get_tls_trust_guidance,
+            reset::reset_app,
+            uninstall::prepare_uninstall,
+            runtime::check_runtime_dependencies,
+            i18n::list_locales,
+            i18n::get_locale,
+            permissions::request_permission,
+        ]))
         .setup(|app| {
+            if let Some(dir) = portable::data_dir() {
+                tracing::info!("Running in portable mode from {}", dir.display());
+            }
+            let overrides = config::active_overrides();
+            if !overrides.is_empty() {
+                tracing::info!("Overridden by the environment: {}", overrides.join(", "));
+            }
+            i18n::apply(config::load().ok().and_then(|settings| settings.locale).as_deref());
+            setup::record_run();
+
             let app_handle = app.handle().clone();
             
-            std::thread::spawn(move || {
-                start_server(&app_handle);
+            tauri::async_runtime::spawn_blocking(move || {
+                server::reap_orphaned_servers();
+                server::launch(&app_handle);
             });
             
+            let extension_handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                if let Err(e) = extension::migrate_legacy_install_dir() {
+                    tracing::warn!("Failed to move the extension to the data dir: {}", e);
+                }
+                extension::update_if_outdated(&extension_handle);
+            });
+            
+            server::start_watchdog(app.handle());
+            integrity::start_watcher(app.handle());
+            config::start_watcher(app.handle());
+            compat::check_on_startup(app.handle());
+            
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let RunEvent::Exit = event {
+                server::stop_all(app_handle);
+            }
+        });
 }
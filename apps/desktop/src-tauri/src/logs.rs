@@ -0,0 +1,287 @@
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Log file shared by the app and the server output it forwards.
+const LOG_FILE: &str = "claudin.log";
+
+/// Size at which the active log file is rotated.
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated files kept next to the active one (`server.log.1` ...).
+const MAX_ROTATED_FILES: u32 = 3;
+
+/// An append-only log file that rotates itself once it grows past
+/// `MAX_LOG_BYTES`, keeping the last `generations` rotated files, or all of
+/// them when it's 0.
+pub struct RotatingLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    generations: u32,
+    /// Rotated files deleted since `take_dropped` was last called.
+    dropped: u32,
+}
+
+impl RotatingLog {
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        Self::with_generations(path, MAX_ROTATED_FILES)
+    }
+
+    /// Opens a log that keeps `generations` rotated files instead of the
+    /// default, dropping the oldest past that. 0 keeps every file.
+    pub fn with_generations(path: PathBuf, generations: u32) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let file = open_append(&path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+        Ok(RotatingLog {
+            path,
+            file,
+            size,
+            generations,
+            dropped: 0,
+        })
+    }
+
+    /// Changes how many rotated files are kept from the next rotation on.
+    pub fn set_generations(&mut self, generations: u32) {
+        self.generations = generations;
+    }
+
+    /// How many rotated files were deleted to stay within `generations`
+    /// since the last call.
+    pub fn take_dropped(&mut self) -> u32 {
+        std::mem::take(&mut self.dropped)
+    }
+
+    pub fn write_line(&mut self, line: &str) -> Result<(), String> {
+        if self.size >= MAX_LOG_BYTES {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{}", line).map_err(|e| e.to_string())?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        let existing = rotated_files(&self.path).len() as u32;
+        let kept = if self.generations == 0 { existing + 1 } else { self.generations };
+        // The oldest files are deleted, including any past a cap lowered
+        // since they were written.
+        for generation in (kept..=existing).rev() {
+            fs::remove_file(rotated_path(&self.path, generation)).map_err(|e| e.to_string())?;
+            self.dropped += 1;
+        }
+        for generation in (1..kept).rev() {
+            let from = rotated_path(&self.path, generation);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, generation + 1))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1)).map_err(|e| e.to_string())?;
+
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Installs the global `tracing` subscriber, which writes every event to
+/// stderr and to the rotating log file. Events with the `server` target are
+/// the server's own output, so both end up interleaved in one file.
+pub fn init() {
+    let max_level = if cfg!(debug_assertions) { Level::DEBUG } else { Level::INFO };
+
+    let log = match log_path().and_then(RotatingLog::open) {
+        Ok(log) => Some(log),
+        Err(e) => {
+            eprintln!("Failed to open log file, logging to stderr only: {}", e);
+            None
+        }
+    };
+
+    let subscriber = LogSubscriber {
+        max_level,
+        log: Mutex::new(log),
+        next_span_id: AtomicU64::new(1),
+    };
+    if tracing::subscriber::set_global_default(subscriber).is_err() {
+        eprintln!("A tracing subscriber was already installed");
+    }
+}
+
+pub fn log_path() -> Result<PathBuf, String> {
+    Ok(get_log_dir()?.join(LOG_FILE))
+}
+
+/// Formats events as `<timestamp> <LEVEL> <target>: <message> key=value...`.
+/// Spans aren't used by the app, so they are only given ids.
+struct LogSubscriber {
+    max_level: Level,
+    log: Mutex<Option<RotatingLog>>,
+    next_span_id: AtomicU64,
+}
+
+impl Subscriber for LogSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.max_level
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+
+        let line = format!(
+            "{} {:>5} {}: {}{}",
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            metadata.level(),
+            metadata.target(),
+            fields.message,
+            fields.extra
+        );
+        // The server's output is forwarded here too, so this covers it.
+        let line = crate::redact::text(&line);
+
+        eprintln!("{}", line);
+        if let Some(log) = self.log.lock().unwrap().as_mut() {
+            if let Err(e) = log.write_line(&line) {
+                eprintln!("Failed to write log file: {}", e);
+            }
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[derive(Default)]
+struct EventFields {
+    message: String,
+    extra: String,
+}
+
+impl Visit for EventFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.extra, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.extra, " {}={}", field.name(), value);
+        }
+    }
+}
+
+/// Returns the last `lines` lines of the file at `path`.
+pub fn read_tail(path: &Path, lines: usize) -> Result<Vec<String>, String> {
+    if lines == 0 || !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut tail = std::collections::VecDeque::with_capacity(lines);
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if tail.len() == lines {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+
+    Ok(tail.into())
+}
+
+pub fn get_log_dir() -> Result<PathBuf, String> {
+    Ok(crate::get_config_dir()?.join("logs"))
+}
+
+/// The rotated files next to the log at `path`, newest first.
+pub fn rotated_files(path: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map(|generation| rotated_path(path, generation))
+        .take_while(|rotated| rotated.exists())
+        .collect()
+}
+
+fn rotated_path(path: &Path, generation: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", generation));
+    PathBuf::from(name)
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("claudin-logs-test-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("test.log")
+    }
+
+    #[test]
+    fn rotation_drops_the_oldest_files_past_the_cap() {
+        let path = temp_log("cap");
+        let mut log = RotatingLog::with_generations(path.clone(), 2).unwrap();
+        for _ in 0..3 {
+            log.rotate().unwrap();
+        }
+        assert_eq!(rotated_files(&path).len(), 2);
+        assert_eq!(log.take_dropped(), 1);
+        assert_eq!(log.take_dropped(), 0);
+
+        log.set_generations(1);
+        log.rotate().unwrap();
+        assert_eq!(rotated_files(&path).len(), 1);
+        assert_eq!(log.take_dropped(), 2);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rotation_keeps_every_file_without_a_cap() {
+        let path = temp_log("uncapped");
+        let mut log = RotatingLog::with_generations(path.clone(), 0).unwrap();
+        for _ in 0..5 {
+            log.rotate().unwrap();
+        }
+        assert_eq!(rotated_files(&path).len(), 5);
+        assert_eq!(log.take_dropped(), 0);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -2,5 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    if claudin_lib::is_native_messaging_launch() {
+        return claudin_lib::run_native_messaging_host();
+    }
+    if claudin_lib::is_uninstall_launch() {
+        return claudin_lib::run_uninstall();
+    }
     claudin_lib::run()
 }
@@ -0,0 +1,283 @@
+use crate::audit::audited;
+use crate::browser::Browser;
+use crate::extension;
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+/// Name the extension connects to with `chrome.runtime.connectNative`.
+pub const HOST_NAME: &str = "app.claudin.host";
+
+/// Chrome refuses larger messages from a host.
+const MAX_OUTGOING_MESSAGE: usize = 1024 * 1024;
+
+/// Chrome never sends messages larger than this to a host.
+const MAX_INCOMING_MESSAGE: usize = 64 * 1024 * 1024;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HostManifest {
+    name: &'static str,
+    description: &'static str,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    allowed_origins: Vec<String>,
+}
+
+/// Registers the app as the extension's native messaging host in Chrome,
+/// Edge, Brave and Arc. Without `extension_ids`, the id derived from the extension
+/// dir and the ids of the extension in every browser profile it's loaded in
+/// are allowed. Returns the manifests (and on Windows, registry keys) written.
+#[tauri::command]
+#[apply(audited!)]
+pub fn register_native_messaging_host(extension_ids: Option<Vec<String>>) -> Result<Vec<String>, String> {
+    let extension_ids = match extension_ids {
+        Some(ids) => ids,
+        None => extension::installed_extension_ids()?,
+    };
+    if extension_ids.is_empty() {
+        return Err("Load the extension in your browser before connecting it to the app".to_string());
+    }
+    if let Some(id) = extension_ids.iter().find(|id| !is_extension_id(id)) {
+        return Err(format!("Invalid extension id {:?}", id));
+    }
+
+    let manifest = HostManifest {
+        name: HOST_NAME,
+        description: "ClaudIn",
+        path: host_path()?.to_string_lossy().to_string(),
+        kind: "stdio",
+        allowed_origins: extension_ids
+            .iter()
+            .map(|id| format!("chrome-extension://{}/", id))
+            .collect(),
+    };
+    let contents = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+
+    let registered = register(&contents)?;
+    tracing::info!("Registered native messaging host for {:?}", extension_ids);
+    Ok(registered)
+}
+
+/// Removes everything `register_native_messaging_host` wrote, returning what
+/// was removed.
+#[tauri::command]
+#[apply(audited!)]
+pub fn unregister_native_messaging_host() -> Result<Vec<String>, String> {
+    unregister()
+}
+
+/// Whether the process was started by a browser as a native messaging host,
+/// which passes the extension's origin as the first argument.
+pub fn is_host_launch() -> bool {
+    std::env::args()
+        .nth(1)
+        .is_some_and(|arg| arg.starts_with("chrome-extension://"))
+}
+
+/// Answers messages from the extension on stdin until the browser closes
+/// the connection. Stdout belongs to the protocol, so nothing else may be
+/// printed to it.
+pub fn run_host() {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+
+    while let Ok(Some(message)) = read_message(&mut input) {
+        let response = handle_message(&message);
+        if write_message(&mut output, &response).is_err() {
+            break;
+        }
+    }
+}
+
+fn handle_message(message: &Value) -> Value {
+    let mut response = match message["type"].as_str() {
+        Some("ping") => json!({
+            "type": "pong",
+            "version": env!("CARGO_PKG_VERSION"),
+        }),
+        Some("getStatus") => json!({
+            "type": "status",
+            "setupComplete": crate::config::load().is_ok_and(|settings| settings.setup.is_complete()),
+            "extensionVersion": extension::ExtensionRegistry::default_extension()
+                .version()
+                .ok()
+                .flatten()
+                .map(|manifest| manifest.version),
+        }),
+        // The browser only lets the extensions allowed in the manifest talk
+        // to the host, so the token stays between the app and the extension.
+        Some("getBridgeToken") => match crate::secrets::bridge_token() {
+            Ok(token) => json!({
+                "type": "bridgeToken",
+                "token": token,
+            }),
+            Err(error) => json!({
+                "type": "error",
+                "error": error,
+            }),
+        },
+        _ => json!({
+            "type": "error",
+            "error": "Unknown message type",
+        }),
+    };
+
+    // Lets the extension match responses to requests.
+    if let Some(id) = message.get("id") {
+        response["id"] = id.clone();
+    }
+    response
+}
+
+/// Messages are JSON preceded by their length as a native-endian `u32`.
+fn read_message(input: &mut impl Read) -> io::Result<Option<Value>> {
+    let mut length = [0u8; 4];
+    match input.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let length = u32::from_ne_bytes(length) as usize;
+    if length > MAX_INCOMING_MESSAGE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Message too large"));
+    }
+    let mut message = vec![0u8; length];
+    input.read_exact(&mut message)?;
+    serde_json::from_slice(&message)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let message = serde_json::to_vec(message)?;
+    if message.len() > MAX_OUTGOING_MESSAGE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Message too large"));
+    }
+    output.write_all(&(message.len() as u32).to_ne_bytes())?;
+    output.write_all(&message)?;
+    output.flush()
+}
+
+/// The app binary doubles as the host, started in host mode by `main`.
+fn host_path() -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    dunce::canonicalize(exe).map_err(|e| e.to_string())
+}
+
+pub fn is_extension_id(id: &str) -> bool {
+    id.len() == 32 && id.chars().all(|c| ('a'..='p').contains(&c))
+}
+
+fn manifest_file_name() -> String {
+    format!("{}.json", HOST_NAME)
+}
+
+/// Browsers on macOS and Linux pick up manifests from a directory in their
+/// user data dir.
+#[cfg(not(windows))]
+fn register(contents: &str) -> Result<Vec<String>, String> {
+    let mut registered = Vec::new();
+    for browser in Browser::all().into_iter().filter(|browser| browser.platform().native_messaging) {
+        let Some(dir) = browser.user_data_dir().map(|dir| dir.join("NativeMessagingHosts")) else {
+            continue;
+        };
+        // Don't create the browser's config for a browser that isn't there.
+        if !dir.parent().is_some_and(|parent| parent.exists()) {
+            continue;
+        }
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let path = dir.join(manifest_file_name());
+        fs::write(&path, contents).map_err(|e| e.to_string())?;
+        registered.push(path.to_string_lossy().to_string());
+    }
+    Ok(registered)
+}
+
+#[cfg(not(windows))]
+fn unregister() -> Result<Vec<String>, String> {
+    let mut removed = Vec::new();
+    for browser in Browser::all().into_iter().filter(|browser| browser.platform().native_messaging) {
+        let Some(dir) = browser.user_data_dir().map(|dir| dir.join("NativeMessagingHosts")) else {
+            continue;
+        };
+        let path = dir.join(manifest_file_name());
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+            removed.push(path.to_string_lossy().to_string());
+        }
+    }
+    Ok(removed)
+}
+
+/// On Windows the manifest can live anywhere, with a registry key per
+/// browser vendor pointing at it.
+#[cfg(windows)]
+fn registry_keys() -> Vec<String> {
+    let mut keys = Vec::new();
+    for browser in Browser::all() {
+        let Some(vendor) = &browser.platform().native_messaging_key else {
+            continue;
+        };
+        let key = format!("Software\\{}\\NativeMessagingHosts\\{}", vendor, HOST_NAME);
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+#[cfg(windows)]
+fn windows_manifest_path() -> Result<PathBuf, String> {
+    Ok(crate::get_config_dir()?
+        .join("native-messaging")
+        .join(manifest_file_name()))
+}
+
+#[cfg(windows)]
+fn register(contents: &str) -> Result<Vec<String>, String> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    let path = windows_manifest_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, contents).map_err(|e| e.to_string())?;
+    let mut registered = vec![path.to_string_lossy().to_string()];
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    for key_path in registry_keys() {
+        let (key, _) = hkcu.create_subkey(&key_path).map_err(|e| e.to_string())?;
+        key.set_value("", &path.to_string_lossy().to_string())
+            .map_err(|e| e.to_string())?;
+        registered.push(format!("HKCU\\{}", key_path));
+    }
+    Ok(registered)
+}
+
+#[cfg(windows)]
+fn unregister() -> Result<Vec<String>, String> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    let mut removed = Vec::new();
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    for key_path in registry_keys() {
+        if hkcu.delete_subkey(&key_path).is_ok() {
+            removed.push(format!("HKCU\\{}", key_path));
+        }
+    }
+
+    let path = windows_manifest_path()?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+        removed.push(path.to_string_lossy().to_string());
+    }
+    Ok(removed)
+}
@@ -0,0 +1,215 @@
+//! Where the app keeps its files, and the guard every path from outside
+//! (command arguments, settings) goes through before the app touches it.
+
+use crate::audit::audited;
+use crate::i18n::t_with;
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_fs::FsExt;
+
+/// Paths the user picked in a file dialog, which the app's own file
+/// operations may then use even though they're outside its directories,
+/// like a new extension folder. Read from the credential store on first use
+/// and kept there, so they're still trusted after a restart.
+static APPROVED: Mutex<Option<Vec<PathBuf>>> = Mutex::new(None);
+
+/// Every directory the app uses, for support to point users at.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppPaths {
+    /// Settings, profiles, logs and backups.
+    config_dir: String,
+    /// Settings shared by every user of the machine, if there can be any.
+    machine_settings: Option<String>,
+    /// Where the active profile's server keeps its database.
+    data_dir: String,
+    /// Where the extension is extracted to.
+    extension_dir: String,
+    log_dir: String,
+    backups_dir: String,
+    /// Bundled resources like the extension archives.
+    resource_dir: String,
+    /// What the server is started from, `None` for a custom server.
+    server_program: Option<String>,
+    portable: bool,
+}
+
+/// Resolves each path the way the rest of the app does, so overrides from
+/// the environment, portable mode and the settings are taken into account.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_app_paths(app: AppHandle) -> Result<AppPaths, String> {
+    let display = |path: std::path::PathBuf| path.to_string_lossy().to_string();
+    Ok(AppPaths {
+        config_dir: display(crate::config::config_dir()?),
+        machine_settings: crate::config::machine_settings_path().map(display),
+        data_dir: display(crate::server::database_dir(&crate::profiles::active())?),
+        extension_dir: crate::extension::get_extension_install_dir()?,
+        log_dir: display(crate::logs::get_log_dir()?),
+        backups_dir: display(crate::backup::backups_dir()?),
+        resource_dir: display(app.path().resource_dir().map_err(|e| e.to_string())?),
+        server_program: crate::server::server_program(&app)?.map(display),
+        portable: crate::portable::is_enabled(),
+    })
+}
+
+/// Checks a path that came from the webview or the settings: it has to be
+/// inside one of the app's directories or the bundled resources, or have
+/// been picked by the user in a file dialog, now or before. Returns it
+/// resolved, with symlinks and `..` gone, and lets the app's own file
+/// operations use a picked path from then on.
+pub fn guard(app: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+    let refuse = || t_with("paths.outsideAppDirs", &[("path", &path.to_string_lossy())]);
+    if !path.is_absolute() || path.components().any(|component| component == Component::ParentDir) {
+        return Err(refuse());
+    }
+    let resolved = resolve(path)?;
+
+    let resource_dir = app.path().resource_dir().map_err(|e| e.to_string())?;
+    let trusted = is_within(&resolved, &[resource_dir]) || is_within(&resolved, &app_dirs()?);
+    if trusted || with_approved(|approved| is_within(&resolved, approved)) {
+        return Ok(resolved);
+    }
+    // Dialogs add what the user picked to the fs scope. The path as given is
+    // checked too, since the scope only resolves paths that exist.
+    let picked = app.fs_scope().is_allowed(path) || app.fs_scope().is_allowed(&resolved);
+    if !picked {
+        tracing::warn!("Refused a path outside the app's directories: {}", path.display());
+        return Err(refuse());
+    }
+
+    with_approved(|approved| {
+        if !approved.contains(&resolved) {
+            approved.push(resolved.clone());
+            if let Err(e) = crate::secrets::set_approved_paths(approved) {
+                tracing::warn!("Could not remember {}, it has to be picked again next time: {}", path.display(), e);
+            }
+        }
+    });
+    Ok(resolved)
+}
+
+/// `guard` for a path setting that changed from `before`, which is left
+/// alone when it's the same.
+pub fn guard_changed(app: &AppHandle, before: Option<&Path>, after: Option<&Path>) -> Result<(), String> {
+    match after {
+        Some(after) if before != Some(after) => guard(app, after).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+/// For the app's own file operations and paths read from the settings:
+/// refuses paths outside its directories and the ones the user picked,
+/// e.g. ones from a tampered setting.
+pub fn ensure_allowed(path: &Path) -> Result<PathBuf, String> {
+    let resolved = resolve(path)?;
+    if is_within(&resolved, &app_dirs()?) || with_approved(|approved| is_within(&resolved, approved)) {
+        return Ok(resolved);
+    }
+    tracing::warn!("Refused a path outside the app's directories: {}", path.display());
+    Err(t_with("paths.outsideAppDirs", &[("path", &path.to_string_lossy())]))
+}
+
+fn with_approved<T>(f: impl FnOnce(&mut Vec<PathBuf>) -> T) -> T {
+    let mut approved = APPROVED.lock().unwrap();
+    let approved = approved.get_or_insert_with(|| {
+        crate::secrets::approved_paths().unwrap_or_else(|e| {
+            tracing::warn!("Could not read the approved paths: {}", e);
+            Vec::new()
+        })
+    });
+    f(approved)
+}
+
+/// Directories the app owns and writes to. A configured extension folder
+/// isn't one of them: it's only trusted once the user picked it.
+fn app_dirs() -> Result<Vec<PathBuf>, String> {
+    let mut dirs = vec![
+        crate::config::config_dir()?,
+        crate::server::database_dir(&crate::profiles::active())?,
+    ];
+    dirs.extend(crate::extension::install_dirs()?);
+    Ok(dirs.iter().filter_map(|dir| resolve(dir).ok()).collect())
+}
+
+fn is_within(path: &Path, dirs: &[PathBuf]) -> bool {
+    dirs.iter().any(|dir| path.starts_with(dir))
+}
+
+/// `path` made absolute with symlinks and `..` resolved. For a path that
+/// doesn't exist yet, like a file about to be written, its closest existing
+/// ancestor is resolved instead and the rest appended.
+fn resolve(path: &Path) -> Result<PathBuf, String> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        match dunce::canonicalize(existing) {
+            Ok(resolved) => {
+                return Ok(rest.iter().rev().fold(resolved, |resolved, part| resolved.join(part)));
+            }
+            Err(e) => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(format!("Invalid path {}: {}", path.display(), e));
+                };
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("claudin-paths-test-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dunce::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn resolves_existing_paths() {
+        let dir = temp_dir("existing");
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        assert_eq!(resolve(&dir.join("a/b")).unwrap(), dir.join("a/b"));
+        assert_eq!(resolve(&dir.join("a/b/../b/..")).unwrap(), dir.join("a"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resolves_missing_paths_from_their_closest_ancestor() {
+        let dir = temp_dir("missing");
+        assert_eq!(resolve(&dir.join("new/file.txt")).unwrap(), dir.join("new").join("file.txt"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_symlinks_out_of_an_allowed_dir() {
+        let dir = temp_dir("symlink");
+        let allowed = dir.join("allowed");
+        let outside = dir.join("outside");
+        fs::create_dir_all(&allowed).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, allowed.join("link")).unwrap();
+
+        let resolved = resolve(&allowed.join("link/secret.txt")).unwrap();
+        assert_eq!(resolved, outside.join("secret.txt"));
+        assert!(!is_within(&resolved, &[allowed]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn is_within_compares_whole_components() {
+        let dirs = [PathBuf::from("/data/claudin")];
+        assert!(is_within(Path::new("/data/claudin"), &dirs));
+        assert!(is_within(Path::new("/data/claudin/extensions/x"), &dirs));
+        assert!(!is_within(Path::new("/data/claudin-evil/x"), &dirs));
+        assert!(!is_within(Path::new("/data"), &dirs));
+    }
+}
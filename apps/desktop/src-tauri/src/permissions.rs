@@ -0,0 +1,140 @@
+//! Destructive commands only run with a token from `request_permission`,
+//! which the user has to approve in a native dialog. The webview can't
+//! click through that dialog, so a compromised page can't wipe data by
+//! itself. The check runs before the command is dispatched, in the handler
+//! `lib.rs` wraps around the generated one.
+
+use crate::audit::audited;
+use crate::i18n::t;
+use macro_rules_attribute::apply;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::{AppHandle, Runtime};
+
+/// How long a token can be used for once the user approved.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60);
+
+/// The argument gated commands take their token in.
+const TOKEN_ARG: &str = "confirmToken";
+
+/// The last token handed out for each action, along with when.
+static TOKENS: Mutex<Option<HashMap<Action, (String, Instant)>>> = Mutex::new(None);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    ResetApp,
+    UninstallApp,
+    UninstallExtension,
+    DeleteApiKey,
+    RestoreConfigBackup,
+    LinkExtensionDevBuild,
+    ImportSettings,
+    RollbackExtension,
+    SetExtensionInstallDir,
+    UnregisterNativeMessagingHost,
+}
+
+impl Action {
+    /// The action a command needs permission for, if any.
+    fn of_command(command: &str) -> Option<Action> {
+        match command {
+            "reset_app" => Some(Action::ResetApp),
+            "prepare_uninstall" => Some(Action::UninstallApp),
+            "uninstall_extension" => Some(Action::UninstallExtension),
+            "delete_api_key" => Some(Action::DeleteApiKey),
+            "restore_config_backup" => Some(Action::RestoreConfigBackup),
+            "link_extension_dev_build" => Some(Action::LinkExtensionDevBuild),
+            "import_settings" => Some(Action::ImportSettings),
+            "rollback_extension" => Some(Action::RollbackExtension),
+            "set_extension_install_dir" => Some(Action::SetExtensionInstallDir),
+            "unregister_native_messaging_host" => Some(Action::UnregisterNativeMessagingHost),
+            _ => None,
+        }
+    }
+
+    fn message_key(self) -> &'static str {
+        match self {
+            Action::ResetApp => "permission.resetApp",
+            Action::UninstallApp => "permission.uninstallApp",
+            Action::UninstallExtension => "permission.uninstallExtension",
+            Action::DeleteApiKey => "permission.deleteApiKey",
+            Action::RestoreConfigBackup => "permission.restoreConfigBackup",
+            Action::LinkExtensionDevBuild => "permission.linkExtensionDevBuild",
+            Action::ImportSettings => "permission.importSettings",
+            Action::RollbackExtension => "permission.rollbackExtension",
+            Action::SetExtensionInstallDir => "permission.setExtensionInstallDir",
+            Action::UnregisterNativeMessagingHost => "permission.unregisterNativeMessagingHost",
+        }
+    }
+}
+
+/// Asks the user to approve `action` and returns the token the command has
+/// to be called with as `confirmToken`, or `None` when they declined. Each
+/// token works once, for that action only, within a minute.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn request_permission(app: AppHandle, action: Action) -> Result<Option<String>, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let approved = tauri::async_runtime::spawn_blocking(move || {
+        app.dialog()
+            .message(t(action.message_key()))
+            .title(t("permission.title"))
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                t("permission.confirm"),
+                t("permission.cancel"),
+            ))
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    if !approved {
+        tracing::info!("Permission for {:?} declined", action);
+        return Ok(None);
+    }
+
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Could not generate a confirmation token".to_string())?;
+    let token = hex::encode(bytes);
+    TOKENS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(action, (token.clone(), Instant::now()));
+    tracing::info!("Permission for {:?} granted", action);
+    Ok(Some(token))
+}
+
+/// Lets `invoke` through unless it's for a gated command without a valid
+/// token, which is rejected without running. Hands `invoke` back when it
+/// may run.
+pub fn check<R: Runtime>(invoke: Invoke<R>) -> Option<Invoke<R>> {
+    let Some(action) = Action::of_command(invoke.message.command()) else {
+        return Some(invoke);
+    };
+    let token = match invoke.message.payload() {
+        InvokeBody::Json(args) => args.get(TOKEN_ARG).and_then(|token| token.as_str()).map(str::to_string),
+        InvokeBody::Raw(_) => None,
+    };
+    if token.is_some_and(|token| consume(action, &token)) {
+        return Some(invoke);
+    }
+    tracing::warn!("Refused {} without permission", invoke.message.command());
+    invoke.resolver.reject(t("permission.denied"));
+    None
+}
+
+/// Whether `token` is the unexpired token for `action`, which is used up
+/// either way so a wrong guess can't be retried.
+fn consume(action: Action, token: &str) -> bool {
+    let issued = TOKENS.lock().unwrap().as_mut().and_then(|tokens| tokens.remove(&action));
+    matches!(issued, Some((issued, at)) if issued == token && at.elapsed() < TOKEN_LIFETIME)
+}
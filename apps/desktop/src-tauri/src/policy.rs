@@ -0,0 +1,395 @@
+use crate::audit::audited;
+use crate::browser::{self, Browser};
+use crate::extension::{self, ExtensionRegistry};
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+
+/// Where administrators manage ClaudIn itself, next to the browsers'
+/// policies.
+#[cfg(windows)]
+const APP_POLICIES: &str = "ClaudIn";
+#[cfg(target_os = "macos")]
+const APP_POLICIES: &str = "com.claudin.app";
+#[cfg(target_os = "linux")]
+const APP_POLICY_FILE: &str = "/etc/claudin/policy.json";
+
+static APP_POLICY_VALUES: OnceLock<Map<String, Value>> = OnceLock::new();
+
+/// What an administrator's policies allow on this machine, checked before
+/// asking the user to load the extension, which managed browsers otherwise
+/// refuse without saying why.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyReport {
+    browser: Browser,
+    /// Whether any policy is set for the browser at all.
+    managed: bool,
+    /// Developer mode, and so "Load unpacked", is turned off.
+    developer_mode_blocked: bool,
+    extension_blocked: bool,
+    reasons: Vec<String>,
+}
+
+/// Reads the policies of `browser` (the default browser when not given)
+/// from the registry, managed preferences or policy files, and reports
+/// whether they keep the extension from being loaded.
+#[tauri::command]
+#[apply(audited!)]
+pub fn check_browser_policies(browser: Option<Browser>) -> Result<PolicyReport, String> {
+    let browser = browser.unwrap_or_else(browser::default_browser);
+    let spec = ExtensionRegistry::default_extension();
+    let extension_id = extension::unpacked_extension_id(&spec.dir()?);
+
+    let policies = policies(browser);
+    let report = evaluate(browser, &policies, &extension_id);
+    if report.developer_mode_blocked || report.extension_blocked {
+        tracing::warn!("Policies block the extension in {:?}: {:?}", browser, report.reasons);
+    }
+    Ok(report)
+}
+
+/// What setup can do in a browser locked down by a school or company, so
+/// onboarding can offer another way in when the usual one won't work.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserCapabilities {
+    browser: Browser,
+    /// Policies are set for the whole machine rather than by the user.
+    machine_managed: bool,
+    /// The Mac is enrolled in device management, which can push policies
+    /// to it at any time.
+    mdm_enrolled: bool,
+    can_load_unpacked: bool,
+    /// Whether pages like the extensions page can be opened, which
+    /// `URLBlocklist` can prevent.
+    can_open_internal_urls: bool,
+    reasons: Vec<String>,
+}
+
+/// Looks for signs that `browser` (the default browser when not given) is
+/// managed and reports which of the setup steps its policies allow.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn get_browser_capabilities(browser: Option<Browser>) -> Result<BrowserCapabilities, String> {
+    let browser = browser.unwrap_or_else(browser::default_browser);
+    tauri::async_runtime::spawn_blocking(move || {
+        let spec = ExtensionRegistry::default_extension();
+        let extension_id = extension::unpacked_extension_id(&spec.dir()?);
+        let policies = policies(browser);
+        let report = evaluate(browser, &policies, &extension_id);
+
+        let mut reasons = report.reasons;
+        let url = browser.extensions_url();
+        let can_open_internal_urls = !is_url_blocked(&policies, url);
+        if !can_open_internal_urls {
+            reasons.push(format!("URLBlocklist keeps {} from opening", url));
+        }
+
+        let machine_managed = browser
+            .platform()
+            .policies
+            .as_deref()
+            .is_some_and(|location| has_machine_policies(location, &policies));
+        Ok(BrowserCapabilities {
+            browser,
+            machine_managed,
+            mdm_enrolled: is_mdm_enrolled(),
+            can_load_unpacked: can_open_internal_urls && !report.developer_mode_blocked && !report.extension_blocked,
+            can_open_internal_urls,
+            reasons,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Vivaldi, Opera and Arc don't read Chromium's policies, and Firefox's are
+/// a different format, so the registry has no location for them.
+fn policies(browser: Browser) -> Map<String, Value> {
+    match &browser.platform().policies {
+        Some(location) => read(location),
+        None => Map::new(),
+    }
+}
+
+/// Whether `URLBlocklist` keeps `url` from opening. Patterns are compared by
+/// prefix without their trailing `*`, and a matching `URLAllowlist` entry
+/// wins, roughly like the browser picking the most specific pattern.
+fn is_url_blocked(policies: &Map<String, Value>, url: &str) -> bool {
+    let matches = |pattern: &str| {
+        let prefix = pattern.trim_end_matches('*').trim_end_matches('/');
+        pattern == "*" || (!prefix.is_empty() && url.starts_with(prefix))
+    };
+    list(policies, "URLBlocklist").any(matches) && !list(policies, "URLAllowlist").any(matches)
+}
+
+fn evaluate(browser: Browser, policies: &Map<String, Value>, extension_id: &str) -> PolicyReport {
+    let mut reasons = Vec::new();
+
+    let developer_tools_disabled = policies
+        .get("DeveloperToolsAvailability")
+        .and_then(Value::as_u64)
+        == Some(2);
+    let developer_mode_disallowed = policies
+        .get("ExtensionDeveloperModeSettings")
+        .and_then(Value::as_u64)
+        == Some(1);
+    if developer_tools_disabled {
+        reasons.push("Developer tools are disabled by policy, which also turns off developer mode".to_string());
+    }
+    if developer_mode_disallowed {
+        reasons.push("Developer mode on the extensions page is disabled by policy".to_string());
+    }
+
+    let allowed = list(policies, "ExtensionInstallAllowlist").any(|id| id == extension_id);
+    let mut extension_blocked = false;
+    if !allowed {
+        if let Some(entry) = list(policies, "ExtensionInstallBlocklist").find(|id| *id == "*" || *id == extension_id) {
+            extension_blocked = true;
+            reasons.push(if entry == "*" {
+                "All extensions not explicitly allowed are blocked by ExtensionInstallBlocklist".to_string()
+            } else {
+                "The extension is on the ExtensionInstallBlocklist".to_string()
+            });
+        }
+    }
+
+    let allowed_types: Vec<&str> = list(policies, "ExtensionAllowedTypes").collect();
+    if policies.contains_key("ExtensionAllowedTypes") && !allowed_types.contains(&"extension") {
+        extension_blocked = true;
+        reasons.push("ExtensionAllowedTypes doesn't allow regular extensions".to_string());
+    }
+
+    if let Some(settings) = extension_settings(policies) {
+        let mode = |key: &str| settings.get(key).and_then(|entry| entry["installation_mode"].as_str());
+        match mode(extension_id) {
+            Some("blocked" | "removed") => {
+                extension_blocked = true;
+                reasons.push("ExtensionSettings blocks the extension".to_string());
+            }
+            Some(_) => {}
+            None if matches!(mode("*"), Some("blocked" | "removed")) && !allowed => {
+                extension_blocked = true;
+                reasons.push("ExtensionSettings blocks all extensions not explicitly allowed".to_string());
+            }
+            None => {}
+        }
+
+        let types = settings
+            .get("*")
+            .and_then(|entry| entry["allowed_types"].as_array());
+        if let Some(types) = types {
+            if !types.iter().any(|kind| kind == "extension") {
+                extension_blocked = true;
+                reasons.push("ExtensionSettings doesn't allow regular extensions".to_string());
+            }
+        }
+    }
+
+    PolicyReport {
+        browser,
+        managed: !policies.is_empty(),
+        developer_mode_blocked: developer_tools_disabled || developer_mode_disallowed,
+        extension_blocked,
+        reasons,
+    }
+}
+
+/// The strings of a list policy.
+fn list<'a>(policies: &'a Map<String, Value>, name: &str) -> impl Iterator<Item = &'a str> {
+    policies
+        .get(name)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// `ExtensionSettings` is a dictionary, which the registry can only hold as
+/// a JSON string.
+fn extension_settings(policies: &Map<String, Value>) -> Option<Map<String, Value>> {
+    match policies.get("ExtensionSettings")? {
+        Value::Object(settings) => Some(settings.clone()),
+        Value::String(json) => serde_json::from_str(json).ok(),
+        _ => None,
+    }
+}
+
+/// Settings an administrator pinned, by dotted path like
+/// `server.externalUrl`, read once per launch. Nested objects in a policy
+/// file are the same as their dotted paths.
+pub fn app_policies() -> &'static Map<String, Value> {
+    APP_POLICY_VALUES.get_or_init(|| {
+        let mut flattened = Map::new();
+        for (key, value) in read_app_policies() {
+            flatten(key, value, &mut flattened);
+        }
+        if !flattened.is_empty() {
+            tracing::info!("Settings managed by policy: {:?}", flattened.keys().collect::<Vec<_>>());
+        }
+        flattened
+    })
+}
+
+fn flatten(key: String, value: Value, flattened: &mut Map<String, Value>) {
+    match value {
+        Value::Object(values) => {
+            for (name, value) in values {
+                flatten(format!("{}.{}", key, name), value, flattened);
+            }
+        }
+        value => {
+            flattened.insert(key, value);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_app_policies() -> Map<String, Value> {
+    read(APP_POLICIES)
+}
+
+#[cfg(target_os = "linux")]
+fn read_app_policies() -> Map<String, Value> {
+    let Ok(contents) = std::fs::read_to_string(APP_POLICY_FILE) else {
+        return Map::new();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring unreadable policy file {}: {}", APP_POLICY_FILE, e);
+        Map::new()
+    })
+}
+
+/// Machine policies win over user policies, like in the browser.
+#[cfg(windows)]
+fn read(location: &str) -> Map<String, Value> {
+    use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, REG_DWORD, REG_QWORD};
+    use winreg::types::FromRegValue;
+    use winreg::RegKey;
+
+    let mut policies = Map::new();
+    let path = format!("Software\\Policies\\{}", location);
+    for root in [HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE] {
+        let Ok(key) = RegKey::predef(root).open_subkey(&path) else {
+            continue;
+        };
+        for (name, value) in key.enum_values().flatten() {
+            let value = match value.vtype {
+                REG_DWORD => u32::from_reg_value(&value).map(Value::from),
+                REG_QWORD => u64::from_reg_value(&value).map(Value::from),
+                _ => String::from_reg_value(&value).map(Value::from),
+            };
+            if let Ok(value) = value {
+                policies.insert(name, value);
+            }
+        }
+        // List policies are subkeys with a value per entry, named 1, 2...
+        for name in key.enum_keys().flatten() {
+            let Ok(list) = key.open_subkey(&name) else {
+                continue;
+            };
+            let mut entries: Vec<(u32, String)> = list
+                .enum_values()
+                .flatten()
+                .filter_map(|(index, value)| Some((index.parse().ok()?, String::from_reg_value(&value).ok()?)))
+                .collect();
+            entries.sort();
+            policies.insert(name, entries.into_iter().map(|(_, entry)| Value::from(entry)).collect());
+        }
+    }
+    policies
+}
+
+/// Configuration profiles install managed preferences for all users and
+/// for each user, the latter winning.
+#[cfg(target_os = "macos")]
+fn read(location: &str) -> Map<String, Value> {
+    let dir = std::path::Path::new("/Library/Managed Preferences");
+    let mut files = vec![dir.join(format!("{}.plist", location))];
+    if let Ok(user) = std::env::var("USER") {
+        files.push(dir.join(user).join(format!("{}.plist", location)));
+    }
+
+    let mut policies = Map::new();
+    for file in files.iter().filter(|file| file.is_file()) {
+        let output = std::process::Command::new("plutil")
+            .args(["-convert", "json", "-o", "-"])
+            .arg(file)
+            .output();
+        let Ok(output) = output else {
+            continue;
+        };
+        if let Ok(Value::Object(values)) = serde_json::from_slice(&output.stdout) {
+            policies.extend(values);
+        }
+    }
+    policies
+}
+
+/// Only `managed` policies are enforced; `recommended` ones can be
+/// changed by the user.
+#[cfg(target_os = "linux")]
+fn read(location: &str) -> Map<String, Value> {
+    let dir = std::path::Path::new(location).join("managed");
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Map::new();
+    };
+    let mut files: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    let mut policies = Map::new();
+    for file in files {
+        let values = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Map<String, Value>>(&contents).ok());
+        match values {
+            Some(values) => policies.extend(values),
+            None => tracing::warn!("Ignoring unreadable policy file {}", file.display()),
+        }
+    }
+    policies
+}
+
+/// Policies under HKLM come from the machine, usually Group Policy.
+#[cfg(windows)]
+fn has_machine_policies(location: &str, _policies: &Map<String, Value>) -> bool {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(format!("Software\\Policies\\{}", location))
+        .is_ok()
+}
+
+/// The preferences outside a user's folder apply to every user.
+#[cfg(target_os = "macos")]
+fn has_machine_policies(location: &str, _policies: &Map<String, Value>) -> bool {
+    std::path::Path::new("/Library/Managed Preferences")
+        .join(format!("{}.plist", location))
+        .is_file()
+}
+
+/// Policy dirs are only writable by root, so any policy is the machine's.
+#[cfg(target_os = "linux")]
+fn has_machine_policies(_location: &str, policies: &Map<String, Value>) -> bool {
+    !policies.is_empty()
+}
+
+#[cfg(target_os = "macos")]
+fn is_mdm_enrolled() -> bool {
+    std::process::Command::new("profiles")
+        .args(["status", "-type", "enrollment"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("MDM enrollment: Yes"))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn is_mdm_enrolled() -> bool {
+    false
+}
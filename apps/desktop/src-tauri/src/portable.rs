@@ -0,0 +1,31 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Next to the executable, turns portable mode on for every launch,
+/// including those by a browser for native messaging.
+const FLAG_FILE: &str = "portable.flag";
+
+/// Turns portable mode on for one launch.
+const FLAG_ARG: &str = "--portable";
+
+/// Folder beside the executable that holds everything in portable mode.
+const DATA_DIR: &str = "data";
+
+static DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// In portable mode the config, extension, logs and server data all live
+/// in a `data` folder beside the executable rather than in the user's
+/// home and config dirs, so the app can run from a USB stick or where it
+/// isn't allowed to write anywhere else.
+pub fn data_dir() -> Option<&'static Path> {
+    DIR.get_or_init(|| {
+        let exe_dir = dunce::canonicalize(std::env::current_exe().ok()?).ok()?.parent()?.to_path_buf();
+        let enabled = exe_dir.join(FLAG_FILE).exists() || std::env::args().skip(1).any(|arg| arg == FLAG_ARG);
+        enabled.then(|| exe_dir.join(DATA_DIR))
+    })
+    .as_deref()
+}
+
+pub fn is_enabled() -> bool {
+    data_dir().is_some()
+}
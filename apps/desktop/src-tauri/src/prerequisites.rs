@@ -0,0 +1,283 @@
+use crate::audit::audited;
+use crate::browser::{self, Browser};
+use crate::extension::{ExtensionRegistry, ExtensionVariant};
+use crate::i18n::{t, t_with};
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sysinfo::Disks;
+use tauri::AppHandle;
+
+/// Less free space than this and extracting or updating the extension, or the
+/// server's database growing, may fail.
+const MIN_FREE_SPACE_MB: u64 = 100;
+
+/// Below this the user is warned before it becomes a problem.
+const LOW_FREE_SPACE_MB: u64 = 500;
+
+const API_KEY_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupValidation {
+    /// Whether nothing failed; warnings don't keep the app from working.
+    ok: bool,
+    checks: Vec<SetupCheck>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupCheck {
+    id: CheckId,
+    status: CheckStatus,
+    message: String,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckId {
+    ExtensionExtracted,
+    ExtensionIntegrity,
+    Browser,
+    Server,
+    ApiKey,
+    DiskSpace,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl SetupCheck {
+    fn new(id: CheckId, status: CheckStatus, message: String) -> Self {
+        SetupCheck { id, status, message }
+    }
+}
+
+/// Checks everything ClaudIn needs to work, in the order the setup wizard
+/// sets it up, for the UI to show as a checklist.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn validate_setup(app: AppHandle) -> Result<SetupValidation, String> {
+    let local_app = app.clone();
+    let mut checks = tauri::async_runtime::spawn_blocking(move || -> Result<Vec<SetupCheck>, String> {
+        let mut checks = check_extension(&local_app)?;
+        checks.push(check_browser(&local_app));
+        Ok(checks)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let server = check_server(&app).await;
+    let server_healthy = server.status == CheckStatus::Pass;
+    checks.push(server);
+    checks.push(check_api_key(&app, server_healthy).await);
+    checks.push(
+        tauri::async_runtime::spawn_blocking(check_disk_space)
+            .await
+            .map_err(|e| e.to_string())?,
+    );
+
+    Ok(SetupValidation {
+        ok: checks.iter().all(|check| check.status != CheckStatus::Fail),
+        checks,
+    })
+}
+
+/// Checks the build the preferred browser takes. Integrity isn't checked
+/// for a linked development build.
+fn check_extension(app: &AppHandle) -> Result<Vec<SetupCheck>, String> {
+    let spec = ExtensionRegistry::default_extension();
+    let variant = ExtensionVariant::preferred();
+    if !spec.is_extracted(variant)? {
+        return Ok(vec![SetupCheck::new(
+            CheckId::ExtensionExtracted,
+            CheckStatus::Fail,
+            t("check.extension.missing"),
+        )]);
+    }
+    let mut checks = vec![SetupCheck::new(
+        CheckId::ExtensionExtracted,
+        CheckStatus::Pass,
+        t("check.extension.extracted"),
+    )];
+
+    if variant == ExtensionVariant::Chrome && spec.dev_build()?.is_some() {
+        checks.push(SetupCheck::new(
+            CheckId::ExtensionIntegrity,
+            CheckStatus::Warn,
+            t("check.extension.devBuild"),
+        ));
+    } else {
+        checks.push(match crate::integrity::verify(app, spec, variant) {
+            Ok(report) if report.ok => SetupCheck::new(
+                CheckId::ExtensionIntegrity,
+                CheckStatus::Pass,
+                t("check.extension.intact"),
+            ),
+            Ok(report) => {
+                let count = report.missing.len() + report.modified.len() + report.extra.len();
+                SetupCheck::new(
+                    CheckId::ExtensionIntegrity,
+                    CheckStatus::Fail,
+                    t_with("check.extension.damaged", &[("count", &count.to_string())]),
+                )
+            }
+            Err(e) => SetupCheck::new(
+                CheckId::ExtensionIntegrity,
+                CheckStatus::Warn,
+                t_with("check.extension.unverified", &[("error", &e)]),
+            ),
+        });
+    }
+    Ok(checks)
+}
+
+fn check_browser(app: &AppHandle) -> SetupCheck {
+    let Some(browser) = browser::installed_default_browser() else {
+        return SetupCheck::new(CheckId::Browser, CheckStatus::Fail, t("check.browser.missing"));
+    };
+    let name = browser.display_name();
+    match browser::compatibility(app, browser) {
+        Ok(compatibility) => match compatibility.compatible {
+            Some(true) => SetupCheck::new(
+                CheckId::Browser,
+                CheckStatus::Pass,
+                t_with("check.browser.compatible", &[("browser", name)]),
+            ),
+            Some(false) => SetupCheck::new(
+                CheckId::Browser,
+                CheckStatus::Fail,
+                t_with(
+                    "check.browser.outdated",
+                    &[
+                        ("browser", name),
+                        ("minimum", compatibility.minimum_version.as_deref().unwrap_or_default()),
+                        ("version", compatibility.version.as_deref().unwrap_or_default()),
+                    ],
+                ),
+            ),
+            None => unknown_browser_version(browser),
+        },
+        Err(e) => {
+            tracing::warn!("Failed to check {} compatibility: {}", name, e);
+            unknown_browser_version(browser)
+        }
+    }
+}
+
+fn unknown_browser_version(browser: Browser) -> SetupCheck {
+    SetupCheck::new(
+        CheckId::Browser,
+        CheckStatus::Warn,
+        t_with("check.browser.unknownVersion", &[("browser", browser.display_name())]),
+    )
+}
+
+async fn check_server(app: &AppHandle) -> SetupCheck {
+    let error = match crate::server::current_health(app).await {
+        Ok(health) if health.healthy => {
+            return SetupCheck::new(CheckId::Server, CheckStatus::Pass, t("check.server.healthy"));
+        }
+        Ok(health) => health.error.unwrap_or_default(),
+        Err(e) => e,
+    };
+    SetupCheck::new(
+        CheckId::Server,
+        CheckStatus::Fail,
+        t_with("check.server.down", &[("error", &error)]),
+    )
+}
+
+/// The key is stored by the server, so this can only be checked while it
+/// answers.
+async fn check_api_key(app: &AppHandle, server_healthy: bool) -> SetupCheck {
+    if !server_healthy {
+        return SetupCheck::new(CheckId::ApiKey, CheckStatus::Warn, t("check.apiKey.serverDown"));
+    }
+    match fetch_has_api_key(app).await {
+        Ok(true) => SetupCheck::new(CheckId::ApiKey, CheckStatus::Pass, t("check.apiKey.present")),
+        Ok(false) => SetupCheck::new(CheckId::ApiKey, CheckStatus::Fail, t("check.apiKey.missing")),
+        Err(e) => SetupCheck::new(
+            CheckId::ApiKey,
+            CheckStatus::Warn,
+            t_with("check.apiKey.unknown", &[("error", &e)]),
+        ),
+    }
+}
+
+async fn fetch_has_api_key(app: &AppHandle) -> Result<bool, String> {
+    let url = format!("{}/api/settings/status", crate::server::current_url(app)?);
+    let server_config = crate::config::load()?.server;
+    let body = crate::server::server_get(&server_config, &url)
+        .timeout(API_KEY_REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(body.get("hasApiKey").and_then(|value| value.as_bool()).unwrap_or(false))
+}
+
+/// Checks the disks the extension and the app's data are on, reporting the
+/// one with the least space left.
+fn check_disk_space() -> SetupCheck {
+    let disks = Disks::new_with_refreshed_list();
+    let paths: Vec<PathBuf> = [
+        ExtensionRegistry::default_extension().dir(),
+        crate::config::config_dir(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let tightest = paths
+        .iter()
+        .filter_map(|path| Some((path, free_space(&disks, path)?)))
+        .min_by_key(|(_, free)| *free);
+
+    let Some((path, free)) = tightest else {
+        return SetupCheck::new(CheckId::DiskSpace, CheckStatus::Warn, t("check.diskSpace.unknown"));
+    };
+    let free_mb = free / (1024 * 1024);
+    let args = [("mb", free_mb.to_string()), ("path", path.display().to_string())];
+    let args: Vec<(&str, &str)> = args.iter().map(|(name, value)| (*name, value.as_str())).collect();
+    if free_mb < MIN_FREE_SPACE_MB {
+        SetupCheck::new(
+            CheckId::DiskSpace,
+            CheckStatus::Fail,
+            t_with("check.diskSpace.low", &args),
+        )
+    } else if free_mb < LOW_FREE_SPACE_MB {
+        SetupCheck::new(
+            CheckId::DiskSpace,
+            CheckStatus::Warn,
+            t_with("check.diskSpace.low", &args),
+        )
+    } else {
+        SetupCheck::new(
+            CheckId::DiskSpace,
+            CheckStatus::Pass,
+            t_with("check.diskSpace.enough", &args),
+        )
+    }
+}
+
+/// Space left on the disk mounted closest to `path`, which needn't exist
+/// yet.
+fn free_space(disks: &Disks, path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    let existing = dunce::canonicalize(existing).ok()?;
+    disks
+        .list()
+        .iter()
+        .filter(|disk| existing.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
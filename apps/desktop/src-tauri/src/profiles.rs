@@ -0,0 +1,180 @@
+use crate::audit::audited;
+use crate::config;
+use crate::server::{self, ProfileId, DEFAULT_PROFILE};
+use macro_rules_attribute::apply;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+/// File in the config dir remembering the active profile and the names of
+/// all of them.
+const PROFILES_FILE: &str = "profiles.json";
+
+/// Longest profile name accepted, which ids are derived from.
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ProfileRegistry {
+    /// Profile the app works in, the default one when unset.
+    active: Option<ProfileId>,
+    /// Names the user gave their profiles, by id.
+    names: BTreeMap<ProfileId, String>,
+}
+
+/// A workspace with its own settings, data dir and server, such as one per
+/// business the user does outreach for.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    id: ProfileId,
+    name: String,
+    active: bool,
+    /// Where the profile's server keeps its database.
+    data_dir: String,
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn list_profiles() -> Result<Vec<Profile>, String> {
+    let registry = read_registry()?;
+    let active = active();
+    server::known_profiles()
+        .into_iter()
+        .map(|id| profile(&registry, &active, id))
+        .collect()
+}
+
+/// Creates a profile named `name`, with default settings and no data. The
+/// id is derived from the name.
+#[tauri::command]
+#[apply(audited!)]
+pub fn create_profile(name: String) -> Result<Profile, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "Profile names must be between 1 and {} characters",
+            MAX_NAME_LENGTH
+        ));
+    }
+
+    let mut registry = read_registry()?;
+    let known = server::known_profiles();
+    let taken = |id: &str| known.iter().any(|known| known == id) || registry.names.contains_key(id);
+    let base = slug(&name);
+    let id = (1..)
+        .map(|n| if n == 1 { base.clone() } else { format!("{}-{}", base, n) })
+        .find(|id| !taken(id))
+        .unwrap();
+    let id = server::profile_id(Some(id))?;
+
+    fs::create_dir_all(server::profile_dir(&id)?).map_err(|e| e.to_string())?;
+    config::save_profile(&id, &config::load_profile(&id)?)?;
+    registry.names.insert(id.clone(), name);
+    write_registry(&registry)?;
+    tracing::info!("Created profile {}", id);
+
+    profile(&registry, &active(), id)
+}
+
+/// Makes `id` the profile the app works in. Its server is started in place
+/// of the previous profile's, and the windows get `profile-switched` along
+/// with `settings-changed` for the settings that differ.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn switch_profile(app: AppHandle, id: String) -> Result<Profile, String> {
+    let id = server::profile_id(Some(id))?;
+    if !server::known_profiles().contains(&id) {
+        return Err(format!("No profile {:?}", id));
+    }
+
+    let previous = active();
+    let mut registry = read_registry()?;
+    if previous != id {
+        registry.active = Some(id.clone()).filter(|id| id != DEFAULT_PROFILE);
+        write_registry(&registry)?;
+        tracing::info!("Switched from profile {} to {}", previous, id);
+
+        let handle = app.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            server::stop_profile(&handle, &previous);
+            server::launch(&handle);
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        config::broadcast(&app, &config::load()?)?;
+    }
+
+    let switched = profile(&registry, &id, id.clone())?;
+    let _ = app.emit("profile-switched", switched.clone());
+    Ok(switched)
+}
+
+/// The profile the app works in, which settings are read from and commands
+/// that don't name a profile act on. Falls back to the default profile when
+/// the active one was deleted.
+pub fn active() -> ProfileId {
+    read_registry()
+        .ok()
+        .and_then(|registry| registry.active)
+        .filter(|id| server::profile_id(Some(id.clone())).is_ok())
+        .filter(|id| server::profile_dir(id).is_ok_and(|dir| dir.exists()))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn profile(registry: &ProfileRegistry, active: &str, id: ProfileId) -> Result<Profile, String> {
+    let name = match registry.names.get(&id) {
+        Some(name) => name.clone(),
+        None if id == DEFAULT_PROFILE => "Default".to_string(),
+        None => id.clone(),
+    };
+    Ok(Profile {
+        data_dir: server::database_dir(&id)?.to_string_lossy().to_string(),
+        active: id == active,
+        id,
+        name,
+    })
+}
+
+/// Lowercase letters, digits and dashes for anything else, which makes a
+/// valid profile id.
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_end_matches('-').chars().take(48).collect();
+    if slug.is_empty() || slug == DEFAULT_PROFILE {
+        format!("profile-{}", slug).trim_end_matches('-').to_string()
+    } else {
+        slug
+    }
+}
+
+fn registry_path() -> Result<PathBuf, String> {
+    Ok(crate::get_config_dir()?.join(PROFILES_FILE))
+}
+
+fn read_registry() -> Result<ProfileRegistry, String> {
+    let path = registry_path()?;
+    if !path.exists() {
+        return Ok(ProfileRegistry::default());
+    }
+    let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid profiles file: {}", e))
+}
+
+fn write_registry(registry: &ProfileRegistry) -> Result<(), String> {
+    let path = registry_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let contents = serde_json::to_string_pretty(registry).map_err(|e| e.to_string())?;
+    fs::write(path, contents).map_err(|e| e.to_string())
+}
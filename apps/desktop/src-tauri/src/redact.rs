@@ -0,0 +1,209 @@
+//! Scrubs secrets and personal data from what the app writes to disk or
+//! hands out for support: API keys, the bridge token and other stored
+//! secrets, bearer tokens, cookie values and email addresses.
+
+use regex::Regex;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::{LazyLock, Mutex};
+
+pub const REDACTED: &str = "<redacted>";
+
+/// Field names whose values are secrets wherever they show up, in JSON or in
+/// `name=value` text. Only whole names count, written in any case and with
+/// or without the underscore, so `apiKey` is one but `monkey` isn't.
+const SECRET_NAMES: &[&str] = &[
+    "token",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "auth_token",
+    "session_token",
+    "csrf_token",
+    "bridge_token",
+    "external_token",
+    "api_key",
+    "secret_key",
+    "private_key",
+    "data_key",
+    "secret",
+    "client_secret",
+    "password",
+    "passwd",
+    "cookie",
+    "authorization",
+];
+
+/// Secrets the app read from the credential store, matched exactly since
+/// they don't have a recognizable shape.
+static KNOWN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Patterns and what they're replaced with, applied in order.
+static PATTERNS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    // `apiKey=...`, `"token": "..."` and the like.
+    let names: Vec<String> = SECRET_NAMES.iter().map(|name| name.replace('_', "[_-]?")).collect();
+    let secret_field = format!(r#"(?i)\b({})(["']?\s*[:=]\s*["']?)[^\s"'&,;]+"#, names.join("|"));
+    [
+        // OpenRouter and OpenAI-style keys.
+        (r"\bsk-[A-Za-z0-9_-]{16,}", REDACTED),
+        (r"(?i)\b(bearer\s+)[A-Za-z0-9._~+/=-]+", "${1}<redacted>"),
+        // A whole cookie header, and LinkedIn's session cookies on their own.
+        (r"(?i)\b((?:set-)?cookie:\s*)[^\r\n]+", "${1}<redacted>"),
+        (
+            r"(?i)\b(li_at|li_rm|jsessionid|bcookie|bscookie|liap)=(\x22?)[^;\s\x22]+",
+            "${1}=${2}<redacted>",
+        ),
+        (secret_field.as_str(), "${1}${2}<redacted>"),
+        (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "<email>"),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).expect("invalid redaction pattern"), replacement))
+    .collect()
+});
+
+/// Makes sure `secret` never shows up in logs or exports, for secrets read
+/// from the credential store.
+pub fn register(secret: &str) {
+    // Short values would match all over the place.
+    if secret.len() < 8 {
+        return;
+    }
+    let mut known = KNOWN.lock().unwrap();
+    if !known.iter().any(|known| known == secret) {
+        known.push(secret.to_string());
+    }
+}
+
+/// `text` with secrets and email addresses replaced.
+pub fn text(text: &str) -> Cow<'_, str> {
+    let mut redacted = Cow::Borrowed(text);
+    for secret in KNOWN.lock().unwrap().iter() {
+        if redacted.contains(secret.as_str()) {
+            redacted = Cow::Owned(redacted.replace(secret.as_str(), REDACTED));
+        }
+    }
+    for (pattern, replacement) in PATTERNS.iter() {
+        if let Cow::Owned(replaced) = pattern.replace_all(&redacted, *replacement) {
+            redacted = Cow::Owned(replaced);
+        }
+    }
+    redacted
+}
+
+/// `value` with the values of secret fields replaced and every string
+/// passed through `text`.
+pub fn json(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| {
+                    let value = if is_secret_name(name) && !value.is_null() {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        json(value)
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(json).collect()),
+        Value::String(string) => Value::String(text(string).into_owned()),
+        _ => value.clone(),
+    }
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let normalize = |name: &str| name.to_ascii_lowercase().replace(['_', '-'], "");
+    let name = normalize(name);
+    SECRET_NAMES.iter().any(|secret| normalize(secret) == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redacts_bearer_tokens_and_api_keys() {
+        assert_eq!(
+            text("Authorization: Bearer abc.def-123"),
+            "Authorization: <redacted> <redacted>"
+        );
+        assert_eq!(text("sent bearer abc.def-123 along"), "sent bearer <redacted> along");
+        assert_eq!(text("using sk-or-v1-0123456789abcdef0123"), "using <redacted>");
+    }
+
+    #[test]
+    fn redacts_secret_fields_in_text() {
+        assert_eq!(text(r#"{"apiKey": "abc123"}"#), r#"{"apiKey": "<redacted>"}"#);
+        assert_eq!(
+            text("api_key=abc123 access-token=def456"),
+            "api_key=<redacted> access-token=<redacted>"
+        );
+        assert_eq!(text("PASSWORD: hunter2"), "PASSWORD: <redacted>");
+        assert_eq!(text("Cookie: li_at=abc; JSESSIONID=def"), "Cookie: <redacted>");
+        assert_eq!(text("li_at=\"abc\"; lang=en"), "li_at=\"<redacted>\"; lang=en");
+    }
+
+    #[test]
+    fn redacts_secrets_in_urls() {
+        assert_eq!(
+            text("GET https://example.com/callback?access_token=abc123&state=xyz"),
+            "GET https://example.com/callback?access_token=<redacted>&state=xyz"
+        );
+        assert_eq!(
+            text("https://example.com/?token=abc123"),
+            "https://example.com/?token=<redacted>"
+        );
+        assert_eq!(text("mail sent to jane.doe@example.com"), "mail sent to <email>");
+    }
+
+    #[test]
+    fn leaves_names_that_only_contain_a_secret_name_alone() {
+        for line in [
+            "Failed to read key: permission denied",
+            "monkey=banana",
+            "keyboard_layout=azerty",
+            "tokens_used=512",
+            "secretary: Jane",
+            "passwords_checked=3",
+        ] {
+            assert_eq!(text(line), line);
+        }
+    }
+
+    #[test]
+    fn redacts_registered_secrets() {
+        register("s3cr3t-value-from-the-store");
+        assert_eq!(text("got s3cr3t-value-from-the-store back"), "got <redacted> back");
+        // Too short to be matched safely.
+        register("abc");
+        assert_eq!(text("abc"), "abc");
+    }
+
+    #[test]
+    fn redacts_secret_json_fields_by_whole_name() {
+        let value = json!({
+            "apiKey": "abc123",
+            "bridge_token": "def456",
+            "Authorization": "Bearer ghi789",
+            "storeKey": "not a secret",
+            "monkey": "banana",
+            "password": null,
+            "nested": [{ "accessToken": "jkl012", "email": "jane@example.com" }],
+        });
+        assert_eq!(
+            json(&value),
+            json!({
+                "apiKey": REDACTED,
+                "bridge_token": REDACTED,
+                "Authorization": REDACTED,
+                "storeKey": "not a secret",
+                "monkey": "banana",
+                "password": null,
+                "nested": [{ "accessToken": REDACTED, "email": "<email>" }],
+            })
+        );
+    }
+}
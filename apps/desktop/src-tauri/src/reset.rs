@@ -0,0 +1,120 @@
+use crate::audit::audited;
+use crate::extension::{self, ExtensionRegistry};
+use macro_rules_attribute::apply;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+/// What `reset_app` deletes. Nothing is unless asked for.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResetOptions {
+    /// Every extracted extension with its backups, and the native messaging
+    /// host registration.
+    pub extension: bool,
+    /// Everything in the config dir, like the settings, except the logs
+    /// unless `logs` is set too. The config is backed up first, and the
+    /// backups are kept.
+    pub config: bool,
+    pub logs: bool,
+    /// The servers' databases.
+    pub database: bool,
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetReport {
+    /// Where the config was backed up to before it was deleted.
+    backup: Option<String>,
+    /// Paths that were deleted, in the order they were removed.
+    pub removed: Vec<String>,
+    /// What couldn't be deleted, with why.
+    pub failed: Vec<ResetFailure>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Stops the servers and deletes what `options` asks for, to get back to
+/// the state of a fresh install. A step that fails doesn't stop the others;
+/// the report says what was removed and what wasn't. Needs permission for
+/// `resetApp` from `request_permission`.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn reset_app(app: AppHandle, options: ResetOptions) -> Result<ResetReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::server::stop_all(&app);
+        reset(&options)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// The extension goes first and the config last, since where the others
+/// are depends on the settings.
+pub fn reset(options: &ResetOptions) -> Result<ResetReport, String> {
+    let mut report = ResetReport::default();
+    if options.config {
+        let backup = crate::backup::snapshot("reset")?;
+        report.backup = Some(backup.to_string_lossy().to_string());
+    }
+
+    if options.extension {
+        for spec in ExtensionRegistry::all() {
+            match extension::uninstall_extension(Some(spec.id.to_string())) {
+                Ok(uninstalled) => report.removed.extend(uninstalled.removed),
+                Err(error) => report.failed.push(ResetFailure {
+                    path: spec.id.to_string(),
+                    error,
+                }),
+            }
+        }
+    }
+    if options.database {
+        for dir in crate::server::data_dirs()? {
+            remove(&dir, &mut report);
+        }
+    }
+    if options.logs {
+        remove(&crate::logs::get_log_dir()?, &mut report);
+    }
+    if options.config {
+        let backups_dir = crate::backup::backups_dir()?;
+        let log_dir = crate::logs::get_log_dir()?;
+        for entry in fs::read_dir(crate::get_config_dir()?).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path != backups_dir && (options.logs || path != log_dir) {
+                remove(&path, &mut report);
+            }
+        }
+    }
+
+    tracing::info!(
+        "Reset the app, removed {:?}, failed to remove {}",
+        report.removed,
+        report.failed.len()
+    );
+    Ok(report)
+}
+
+pub fn remove(path: &Path, report: &mut ResetReport) {
+    let removed = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        return;
+    };
+    match removed {
+        Ok(()) => report.removed.push(path.to_string_lossy().to_string()),
+        Err(e) => report.failed.push(ResetFailure {
+            path: path.to_string_lossy().to_string(),
+            error: e.to_string(),
+        }),
+    }
+}
@@ -0,0 +1,214 @@
+use crate::audit::audited;
+use crate::config::{self, ServerProfile};
+use macro_rules_attribute::apply;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Node versions tsx and the server support.
+const SUPPORTED_NODE_VERSIONS: &str = ">=18";
+
+/// Where Node usually lives on macOS and Linux when it was installed outside
+/// the system package manager. GUI apps launched from Finder don't inherit
+/// the shell `PATH`, so these are checked even when they aren't on it.
+#[cfg(not(windows))]
+const COMMON_NODE_DIRS: &[&str] = &[
+    "/opt/homebrew/bin",
+    "/usr/local/bin",
+    "~/.volta/bin",
+    "~/.asdf/shims",
+    "~/.local/share/fnm/aliases/default/bin",
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDiagnostics {
+    /// Whether the configured server profile needs Node at all.
+    required: bool,
+    ok: bool,
+    path: String,
+    tools: Vec<ToolCheck>,
+    /// Human readable problems with a suggested fix, for the setup wizard.
+    issues: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCheck {
+    name: &'static str,
+    found: bool,
+    version: Option<String>,
+    location: Option<String>,
+}
+
+/// Checks that node, npx and tsx can be run the way the dev server profile
+/// runs them, and explains what to do about anything missing.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn check_runtime_dependencies() -> Result<RuntimeDiagnostics, String> {
+    let required = config::load()?.server.profile() == ServerProfile::Dev;
+
+    tauri::async_runtime::spawn_blocking(move || diagnose(required))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn diagnose(required: bool) -> RuntimeDiagnostics {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut issues = Vec::new();
+
+    let node = check_tool("node", &path, &["--version"], None);
+    let npx = check_tool("npx", &path, &["--version"], None);
+    let server_dir = crate::server::dev_server_script()
+        .parent()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf);
+    let tsx = check_tool(
+        "tsx",
+        &path,
+        &["--no-install", "tsx", "--version"],
+        server_dir.as_deref(),
+    );
+
+    if !node.found {
+        issues.push(match find_outside_path("node") {
+            Some(dir) => format!(
+                "Node is installed in {} but that folder isn't on the PATH ClaudIn was started \
+                 with. Apps opened from the Dock or Finder don't see your shell's PATH; start \
+                 ClaudIn from a terminal, or install Node from nodejs.org.",
+                dir.display()
+            ),
+            None => "Node isn't installed. Install the LTS version from https://nodejs.org and \
+                     restart ClaudIn."
+                .to_string(),
+        });
+    } else if let Some(version) = &node.version {
+        if !node_version_supported(version) {
+            issues.push(format!(
+                "Node {} is too old. Install a version matching {} from https://nodejs.org.",
+                version, SUPPORTED_NODE_VERSIONS
+            ));
+        }
+    }
+
+    if node.found && !npx.found {
+        issues.push(
+            "npx wasn't found next to Node. Reinstall Node from https://nodejs.org, which \
+             ships npx with it."
+                .to_string(),
+        );
+    }
+
+    if npx.found && !tsx.found {
+        issues.push(
+            "tsx isn't installed for the server. Run `pnpm install` in the repository root."
+                .to_string(),
+        );
+    }
+
+    RuntimeDiagnostics {
+        required,
+        ok: issues.is_empty(),
+        path: path.to_string_lossy().to_string(),
+        tools: vec![node, npx, tsx],
+        issues,
+    }
+}
+
+/// Runs `npx <args>` for tsx, and the tool itself for everything else.
+fn check_tool(name: &'static str, path: &OsString, args: &[&str], cwd: Option<&Path>) -> ToolCheck {
+    let program = executable(if name == "tsx" { "npx" } else { name });
+    let location = find_on_path(program, path);
+
+    let version = location.as_ref().and_then(|location| {
+        let mut command = Command::new(location);
+        command.args(args);
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+        hide_console(&mut command);
+
+        let output = command.output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let version = String::from_utf8_lossy(&output.stdout);
+        version.lines().next().map(|line| line.trim().to_string())
+    });
+
+    ToolCheck {
+        name,
+        found: version.is_some(),
+        location: location.map(|p| p.to_string_lossy().to_string()),
+        version,
+    }
+}
+
+pub fn find_on_path(program: &str, path: &OsString) -> Option<PathBuf> {
+    std::env::split_paths(path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Looks for `program` in the usual install locations that aren't on `PATH`.
+#[cfg(not(windows))]
+fn find_outside_path(program: &'static str) -> Option<PathBuf> {
+    let home = dirs::home_dir();
+    let mut dirs: Vec<PathBuf> = COMMON_NODE_DIRS
+        .iter()
+        .filter_map(|dir| match dir.strip_prefix("~/") {
+            Some(rest) => home.as_ref().map(|home| home.join(rest)),
+            None => Some(PathBuf::from(dir)),
+        })
+        .collect();
+
+    // nvm keeps one directory per installed version.
+    if let Some(versions) = home.map(|home| home.join(".nvm").join("versions").join("node")) {
+        if let Ok(entries) = std::fs::read_dir(versions) {
+            dirs.extend(entries.flatten().map(|entry| entry.path().join("bin")));
+        }
+    }
+
+    dirs.into_iter().find(|dir| dir.join(program).is_file())
+}
+
+#[cfg(windows)]
+fn find_outside_path(program: &'static str) -> Option<PathBuf> {
+    let program_files = std::env::var_os("ProgramFiles")?;
+    let dir = PathBuf::from(program_files).join("nodejs");
+    dir.join(executable(program)).is_file().then_some(dir)
+}
+
+fn node_version_supported(version: &str) -> bool {
+    match (
+        Version::parse(version.trim_start_matches('v')),
+        VersionReq::parse(SUPPORTED_NODE_VERSIONS),
+    ) {
+        (Ok(version), Ok(range)) => range.matches(&version),
+        _ => false,
+    }
+}
+
+/// On Windows node ships as `node.exe` and npx as a `npx.cmd` script.
+fn executable(name: &'static str) -> &'static str {
+    if !cfg!(windows) {
+        return name;
+    }
+    match name {
+        "node" => "node.exe",
+        "npx" => "npx.cmd",
+        _ => name,
+    }
+}
+
+#[cfg(windows)]
+fn hide_console(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    command.creation_flags(CREATE_NO_WINDOW);
+}
+
+#[cfg(not(windows))]
+fn hide_console(_command: &mut Command) {}
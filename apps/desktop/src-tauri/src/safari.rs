@@ -0,0 +1,128 @@
+use crate::audit::audited;
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use tauri::AppHandle;
+
+#[cfg(target_os = "macos")]
+use crate::extension::{self, ExtensionRegistry, ExtensionVariant};
+#[cfg(target_os = "macos")]
+use std::path::Path;
+#[cfg(target_os = "macos")]
+use std::process::Command;
+
+#[cfg(target_os = "macos")]
+const SAFARI_APP: &str = "/Applications/Safari.app";
+
+#[cfg(not(target_os = "macos"))]
+const NOT_MACOS: &str = "Safari is only available on macOS";
+
+/// Safari on this Mac and the app its extension comes in.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafariStatus {
+    installed: bool,
+    version: Option<String>,
+    /// Where the app containing the extension was extracted, once
+    /// `install_safari_extension` ran.
+    extension_app: Option<String>,
+}
+
+/// Whether Safari is there to offer to users who don't have a browser the
+/// other builds run in. Always reports it missing outside macOS.
+#[tauri::command]
+#[apply(audited!)]
+pub fn detect_safari() -> Result<SafariStatus, String> {
+    #[cfg(target_os = "macos")]
+    {
+        let path = Path::new(SAFARI_APP);
+        let extension_app = ExtensionRegistry::default_extension().safari_app()?;
+        Ok(SafariStatus {
+            installed: path.exists(),
+            version: crate::browser::app_version(path),
+            extension_app: extension_app.map(|app| app.to_string_lossy().to_string()),
+        })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    Ok(SafariStatus {
+        installed: false,
+        version: None,
+        extension_app: None,
+    })
+}
+
+/// Extracts the Safari build, an app with the extension inside, and opens
+/// the app once in the background, which is when Safari picks up the
+/// extensions an app contains. The user still has to turn it on in Safari's
+/// settings, and builds that aren't signed only show up with "Allow
+/// Unsigned Extensions" checked in Safari's Develop menu. Returns the path
+/// of the app.
+#[tauri::command]
+#[apply(audited!)]
+pub fn install_safari_extension(app: AppHandle) -> Result<String, String> {
+    #[cfg(target_os = "macos")]
+    {
+        let spec = ExtensionRegistry::default_extension();
+        extension::extract(&app, spec, ExtensionVariant::Safari)?;
+        let extension_app = spec
+            .safari_app()?
+            .ok_or("The Safari build of the extension doesn't contain an app")?;
+
+        let status = Command::new("open")
+            .args(["-g", "-j"])
+            .arg(&extension_app)
+            .status()
+            .map_err(|e| e.to_string())?;
+        if !status.success() {
+            return Err(format!("Could not open {}", extension_app.display()));
+        }
+        tracing::info!("Installed Safari extension app {}", extension_app.display());
+        Ok(extension_app.to_string_lossy().to_string())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = app;
+        Err(NOT_MACOS.to_string())
+    }
+}
+
+/// Opens the Extensions tab of Safari's settings. That takes scripting
+/// Safari's window, which only works once the user let ClaudIn control the
+/// computer in Accessibility settings; otherwise the extension's app is
+/// opened, whose window has a button going to the same place.
+#[tauri::command]
+#[apply(audited!)]
+pub fn open_safari_extensions_preferences() -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        const SCRIPT: &str = r#"
+            tell application "Safari" to activate
+            tell application "System Events" to tell process "Safari"
+                keystroke "," using command down
+                delay 0.5
+                click button "Extensions" of toolbar 1 of window 1
+            end tell
+        "#;
+        let scripted = Command::new("osascript")
+            .args(["-e", SCRIPT])
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if scripted {
+            return Ok(());
+        }
+        tracing::warn!("Could not script Safari's settings, opening the extension's app instead");
+
+        let extension_app = ExtensionRegistry::default_extension()
+            .safari_app()?
+            .ok_or("Install the Safari extension first")?;
+        Command::new("open")
+            .arg(&extension_app)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    Err(NOT_MACOS.to_string())
+}
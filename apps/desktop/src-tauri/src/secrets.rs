@@ -0,0 +1,293 @@
+use macro_rules_attribute::apply;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use crate::audit::audited;
+use crate::i18n::t;
+
+/// Service the app's secrets are stored under in the OS's credential store.
+const SERVICE: &str = "com.claudin.app";
+
+const API_KEY_ACCOUNT: &str = "openrouter-api-key";
+
+/// The server takes the API key from this variable, which is only ever set
+/// on the server's own process.
+pub const API_KEY_VAR: &str = "OPENROUTER_API_KEY";
+
+const BRIDGE_TOKEN_ACCOUNT: &str = "bridge-token";
+
+/// The local server requires this token from every API request.
+pub const BRIDGE_TOKEN_VAR: &str = "CLAUDIN_BRIDGE_TOKEN";
+
+const DATA_KEY_ACCOUNT: &str = "data-key";
+
+/// The token sent to an external server, shared by every profile.
+const EXTERNAL_TOKEN_ACCOUNT: &str = "external-server-token";
+
+/// Paths the user picked in a file dialog, as a JSON array.
+const APPROVED_PATHS_ACCOUNT: &str = "approved-paths";
+
+/// The server encrypts its database with the key in this variable.
+pub const DATA_KEY_VAR: &str = "CLAUDIN_DATA_KEY";
+
+/// Where the bridge token is kept when there is no credential store, like
+/// on Linux without a Secret Service. Only the user can read it.
+const BRIDGE_TOKEN_FILE: &str = "bridge-token";
+
+/// The bridge token once read, since servers and health checks need it
+/// all the time.
+static BRIDGE_TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+/// Whether the bridge token had to be kept in `BRIDGE_TOKEN_FILE`.
+static BRIDGE_TOKEN_IN_FILE: AtomicBool = AtomicBool::new(false);
+
+/// Where the secrets the app can't do without ended up.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretStorageStatus {
+    /// The credential store failed and the bridge token is in a file any
+    /// process running as the user can read.
+    bridge_token_in_file: bool,
+    /// What to tell the user about it.
+    warning: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyStatus {
+    stored: bool,
+    /// The last characters of the key, to tell keys apart.
+    hint: Option<String>,
+}
+
+impl ApiKeyStatus {
+    fn of(key: Option<&str>) -> Self {
+        ApiKeyStatus {
+            stored: key.is_some(),
+            hint: key.map(|key| key.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect()),
+        }
+    }
+}
+
+/// Saves the API key in the Keychain, Credential Manager or Secret Service
+/// and restarts the running servers, which only get it when they're
+/// started.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn set_api_key(app: AppHandle, key: String) -> Result<ApiKeyStatus, String> {
+    let key = key.trim().to_string();
+    // Keys are plain tokens; whitespace or control characters are a paste
+    // gone wrong.
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(t("secrets.invalidApiKey"));
+    }
+    let status = ApiKeyStatus::of(Some(&key));
+    tauri::async_runtime::spawn_blocking(move || write(API_KEY_ACCOUNT, &key))
+        .await
+        .map_err(|e| e.to_string())??;
+    tracing::info!("Stored the API key in the credential store");
+    crate::server::restart_running(&app).await;
+    Ok(status)
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub async fn get_api_key_status() -> Result<ApiKeyStatus, String> {
+    let key = tauri::async_runtime::spawn_blocking(api_key)
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(ApiKeyStatus::of(key.as_deref()))
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub async fn delete_api_key(app: AppHandle) -> Result<ApiKeyStatus, String> {
+    tauri::async_runtime::spawn_blocking(|| delete(API_KEY_ACCOUNT))
+        .await
+        .map_err(|e| e.to_string())??;
+    tracing::info!("Deleted the API key from the credential store");
+    crate::server::restart_running(&app).await;
+    Ok(ApiKeyStatus::of(None))
+}
+
+pub fn api_key() -> Result<Option<String>, String> {
+    let key = read(API_KEY_ACCOUNT)?;
+    if let Some(key) = &key {
+        crate::redact::register(key);
+    }
+    Ok(key)
+}
+
+/// The token the local server, the app and the extension share, so other
+/// local processes can't use the server's API.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_bridge_token() -> Result<String, String> {
+    bridge_token()
+}
+
+/// Tells the settings whether secrets are kept somewhere less safe than
+/// the credential store, so the user can fix the store.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_secret_storage_status() -> Result<SecretStorageStatus, String> {
+    bridge_token()?;
+    let bridge_token_in_file = BRIDGE_TOKEN_IN_FILE.load(Ordering::Relaxed);
+    Ok(SecretStorageStatus {
+        bridge_token_in_file,
+        warning: bridge_token_in_file.then(|| t("secrets.bridgeTokenInFile")),
+    })
+}
+
+/// Reads the bridge token, generating it on first run. When the credential
+/// store doesn't work it's kept in a file instead, which
+/// `get_secret_storage_status` reports.
+pub fn bridge_token() -> Result<String, String> {
+    let mut cached = BRIDGE_TOKEN.lock().unwrap();
+    if let Some(token) = cached.as_ref() {
+        return Ok(token.clone());
+    }
+
+    let token = match read(BRIDGE_TOKEN_ACCOUNT) {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            let token = generate_token()?;
+            match write(BRIDGE_TOKEN_ACCOUNT, &token) {
+                Ok(()) => {
+                    // Left over from a run without a working credential store.
+                    let _ = std::fs::remove_file(crate::get_config_dir()?.join(BRIDGE_TOKEN_FILE));
+                    token
+                }
+                Err(e) => {
+                    tracing::warn!("Keeping the bridge token in a file, the credential store failed: {}", e);
+                    BRIDGE_TOKEN_IN_FILE.store(true, Ordering::Relaxed);
+                    token_from_file(Some(&token))?
+                }
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Reading the bridge token from a file, the credential store failed: {}", e);
+            BRIDGE_TOKEN_IN_FILE.store(true, Ordering::Relaxed);
+            token_from_file(None)?
+        }
+    };
+    crate::redact::register(&token);
+    *cached = Some(token.clone());
+    Ok(token)
+}
+
+/// The key the server's database is encrypted with, generated the first
+/// time encryption is turned on. Unlike the bridge token it never falls
+/// back to a file, which would sit next to the data it protects.
+pub fn data_key(create: bool) -> Result<Option<String>, String> {
+    let key = match read(DATA_KEY_ACCOUNT)? {
+        Some(key) => Some(key),
+        None if create => {
+            let key = generate_token()?;
+            write(DATA_KEY_ACCOUNT, &key).map_err(|e| format!("Could not store the encryption key: {}", e))?;
+            tracing::info!("Stored a new database encryption key in the credential store");
+            Some(key)
+        }
+        None => None,
+    };
+    if let Some(key) = &key {
+        crate::redact::register(key);
+    }
+    Ok(key)
+}
+
+/// The bearer token for the external server, if one was saved.
+pub fn external_token() -> Result<Option<String>, String> {
+    let token = read(EXTERNAL_TOKEN_ACCOUNT)?;
+    if let Some(token) = &token {
+        crate::redact::register(token);
+    }
+    Ok(token)
+}
+
+/// Stores the token for the external server, or removes it when empty.
+pub fn set_external_token(token: &str) -> Result<(), String> {
+    let token = token.trim();
+    if token.is_empty() {
+        return delete(EXTERNAL_TOKEN_ACCOUNT);
+    }
+    crate::redact::register(token);
+    write(EXTERNAL_TOKEN_ACCOUNT, token).map_err(|e| format!("Could not store the server token: {}", e))
+}
+
+/// Paths the user picked outside the app's directories. They're kept here
+/// rather than in the settings, where anyone who can edit the file could
+/// add to them.
+pub fn approved_paths() -> Result<Vec<PathBuf>, String> {
+    match read(APPROVED_PATHS_ACCOUNT)? {
+        Some(paths) => serde_json::from_str(&paths).map_err(|e| e.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn set_approved_paths(paths: &[PathBuf]) -> Result<(), String> {
+    let paths = serde_json::to_string(paths).map_err(|e| e.to_string())?;
+    write(APPROVED_PATHS_ACCOUNT, &paths)
+}
+
+fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| "Could not generate a secret".to_string())?;
+    Ok(hex::encode(bytes))
+}
+
+/// Reads the token from the fallback file, writing `new` or a new token when
+/// there is none yet.
+fn token_from_file(new: Option<&str>) -> Result<String, String> {
+    let path = crate::get_config_dir()?.join(BRIDGE_TOKEN_FILE);
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        if !token.trim().is_empty() {
+            return Ok(token.trim().to_string());
+        }
+    }
+    let token = match new {
+        Some(token) => token.to_string(),
+        None => generate_token()?,
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path).map_err(|e| e.to_string())?;
+    std::io::Write::write_all(&mut file, token.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(token)
+}
+
+/// The Keychain on macOS, the Credential Manager on Windows and the Secret
+/// Service, like GNOME Keyring or KWallet, elsewhere.
+fn entry(account: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, account).map_err(|e| e.to_string())
+}
+
+fn write(account: &str, secret: &str) -> Result<(), String> {
+    entry(account)?.set_password(secret).map_err(|e| e.to_string())
+}
+
+fn read(account: &str) -> Result<Option<String>, String> {
+    match entry(account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn delete(account: &str) -> Result<(), String> {
+    match entry(account)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
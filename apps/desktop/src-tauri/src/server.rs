@@ -0,0 +1,1742 @@
+use crate::audit::audited;
+use crate::config::{self, BackendMode, ServerConfig, ServerProfile};
+use crate::i18n::t;
+use crate::logs;
+use macro_rules_attribute::apply;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, Signal, System, UpdateKind};
+use tauri::async_runtime::{JoinHandle, Receiver};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::io::AsyncWriteExt;
+
+/// Port the server prefers; the extension and frontend expect it by default.
+const DEFAULT_SERVER_PORT: u16 = 3847;
+
+/// Profile used when a command doesn't name one. It keeps the layout from
+/// before profiles existed: its files live directly in the config dir and its
+/// server uses the server's own default data dir.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Directory in the config dir with one subdirectory per additional profile.
+const PROFILES_DIR: &str = "profiles";
+
+/// Name of the bundled server binary declared in `bundle.externalBin`.
+const SERVER_SIDECAR: &str = "claudin-server";
+
+/// File in the profile dir remembering the last port the server was given.
+const SERVER_PORT_FILE: &str = "server_port";
+
+/// File in the profile dir holding the PID of the running server, so a server
+/// orphaned by a crash can be found on the next launch.
+pub const SERVER_PID_FILE: &str = "server.pid";
+
+/// Origins the app's own webview loads from: `tauri://localhost` on macOS
+/// and Linux, `http://tauri.localhost` on Windows.
+const APP_ORIGINS: &[&str] = &["tauri://localhost", "http://tauri.localhost", "https://tauri.localhost"];
+
+/// Vite dev servers of the app and the extension, allowed in debug builds.
+const DEV_ORIGINS: &[&str] = &["http://localhost:1420", "http://localhost:5173"];
+
+/// Entry point the development server is run from, relative to `apps/`.
+const DEV_SERVER_SCRIPT: &str = "server/src/index.ts";
+
+/// How long the server gets to shut down after SIGTERM before it is killed.
+const SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lines returned by `read_server_log_tail` when the caller doesn't ask.
+const DEFAULT_LOG_TAIL_LINES: usize = 200;
+
+const LAZY_START_RESPONSE: &[u8] = b"HTTP/1.1 503 Service Unavailable\r\n\
+Retry-After: 2\r\n\
+Content-Length: 0\r\n\
+Connection: close\r\n\r\n";
+
+/// How long `restart_server` waits for the old process to free its port.
+const PORT_RELEASE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long `wait_for_server_ready` waits when the caller doesn't say.
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(30);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Consecutive failed starts after which the supervisor gives up.
+const MAX_RESTART_ATTEMPTS: u32 = 5;
+
+const INITIAL_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// A server that stays up this long is considered healthy again, so a crash
+/// after a long run starts the backoff from scratch.
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+/// Id of the crash notification's "Restart" button.
+#[cfg(any(target_os = "linux", windows))]
+const RESTART_ACTION: &str = "restart";
+
+/// How often the watchdog pings the server's health endpoint.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Failed pings in a row after which the server is considered hung.
+const WATCHDOG_MAX_FAILURES: u32 = 3;
+
+/// Time a freshly started server gets to come up before the watchdog
+/// starts counting failed pings.
+const WATCHDOG_STARTUP_GRACE: Duration = Duration::from_secs(30);
+
+/// Identifies an isolated server instance with its own port, data dir and
+/// lifecycle, e.g. one per LinkedIn account. Unrelated to `ServerProfile`,
+/// which decides how servers are run.
+pub type ProfileId = String;
+
+/// The servers of every profile used this session.
+#[derive(Default)]
+pub struct ServerState {
+    servers: Mutex<HashMap<ProfileId, Arc<ServerHandle>>>,
+}
+
+impl ServerState {
+    /// Returns the server of `profile`, creating an idle one on first use.
+    fn server(&self, profile: &str) -> Arc<ServerHandle> {
+        self.servers
+            .lock()
+            .unwrap()
+            .entry(profile.to_string())
+            .or_insert_with(|| Arc::new(ServerHandle::new(profile)))
+            .clone()
+    }
+
+    fn servers(&self) -> Vec<Arc<ServerHandle>> {
+        self.servers.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Handle to one spawned server process and the task supervising it.
+pub struct ServerHandle {
+    profile: ProfileId,
+    process: Mutex<Option<ServerProcess>>,
+    supervisor: Mutex<Option<JoinHandle<()>>>,
+    /// Placeholder listener that starts the server on first connection when
+    /// lazy start is enabled.
+    lazy_listener: Mutex<Option<JoinHandle<()>>>,
+    /// 0 until a port has been picked for a new profile.
+    port: Mutex<u16>,
+    /// Restarts this session, automatic and manual.
+    restart_count: AtomicU32,
+}
+
+impl ServerHandle {
+    fn new(profile: &str) -> Self {
+        let previous = read_persisted_port(profile);
+        let port = if profile == DEFAULT_PROFILE {
+            previous.unwrap_or(DEFAULT_SERVER_PORT)
+        } else {
+            previous.unwrap_or(0)
+        };
+
+        ServerHandle {
+            profile: profile.to_string(),
+            process: Mutex::new(None),
+            supervisor: Mutex::new(None),
+            lazy_listener: Mutex::new(None),
+            port: Mutex::new(port),
+            restart_count: AtomicU32::new(0),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self.profile == DEFAULT_PROFILE
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.process.lock().unwrap().as_ref().map(|p| p.child.pid())
+    }
+
+    fn port(&self) -> u16 {
+        *self.port.lock().unwrap()
+    }
+}
+
+struct ServerProcess {
+    child: CommandChild,
+    started_at: Instant,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    profile_id: ProfileId,
+    profile: ServerProfile,
+    backend_mode: BackendMode,
+    url: String,
+    /// For a remote backend, whether it answers health checks.
+    running: bool,
+    pid: Option<u32>,
+    uptime_secs: Option<u64>,
+    port: u16,
+    health: HealthCheck,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    pub healthy: bool,
+    latency_ms: Option<u64>,
+    version: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Resource usage of the server. Process figures cover the whole process
+/// tree, since in development the tracked PID is `npx` and not `node`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerMetrics {
+    pid: Option<u32>,
+    memory_bytes: Option<u64>,
+    cpu_time_ms: Option<u64>,
+    /// Not available on every platform.
+    open_files: Option<usize>,
+    /// Reported by the server's `/metrics` endpoint.
+    requests_served: Option<u64>,
+    restart_count: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerReadiness {
+    ready: bool,
+    waited_ms: u64,
+    /// Why the server isn't ready, when it isn't.
+    failure: Option<ReadinessFailure>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase", tag = "reason")]
+pub enum ReadinessFailure {
+    /// Nothing is running or being started, e.g. after `stop_server`.
+    NotRunning,
+    /// The supervisor gave up restarting the server.
+    Failed,
+    /// The server is running but didn't answer its health check in time.
+    #[serde(rename_all = "camelCase")]
+    Unhealthy { error: Option<String> },
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerRestartedPayload {
+    profile_id: ProfileId,
+    pid: u32,
+    attempt: u32,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerCrashedPayload {
+    profile_id: ProfileId,
+    pid: u32,
+    code: Option<i32>,
+    /// Whether the supervisor will try again; if not, the UI should offer a
+    /// manual restart.
+    will_restart: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerStoppedPayload {
+    profile_id: ProfileId,
+    /// `None` if no server was running.
+    pid: Option<u32>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerFailedPayload {
+    profile_id: ProfileId,
+    attempts: u32,
+    error: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerUnresponsivePayload {
+    profile_id: ProfileId,
+    pid: u32,
+    error: Option<String>,
+    /// Whether the watchdog is restarting the server; if not, a restart
+    /// already didn't help and the UI should let the user decide.
+    restarting: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerLogPayload {
+    profile_id: ProfileId,
+    stream: &'static str,
+    line: String,
+    /// Milliseconds since the Unix epoch.
+    timestamp: u64,
+}
+
+/// Per-server state of the watchdog.
+#[derive(Default)]
+struct WatchdogState {
+    failures: u32,
+    restarted: bool,
+    notified: bool,
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub async fn get_server_status(
+    state: tauri::State<'_, ServerState>,
+    profile: Option<String>,
+) -> Result<ServerStatus, String> {
+    let server = state.server(&profile_id(profile)?);
+    Ok(server_status(&config::load_profile(&server.profile)?.server, &server).await)
+}
+
+/// Lists the servers of every profile used this session.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn list_servers(state: tauri::State<'_, ServerState>) -> Result<Vec<ServerStatus>, String> {
+    let mut servers = state.servers();
+    servers.sort_by(|a, b| a.profile.cmp(&b.profile));
+
+    let mut statuses = Vec::with_capacity(servers.len());
+    for server in &servers {
+        let server_config = config::load_profile(&server.profile)?.server;
+        statuses.push(server_status(&server_config, server).await);
+    }
+    Ok(statuses)
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub async fn get_server_metrics(
+    state: tauri::State<'_, ServerState>,
+    profile: Option<String>,
+) -> Result<ServerMetrics, String> {
+    let server = state.server(&profile_id(profile)?);
+    let pid = server.pid();
+    let server_config = config::load_profile(&server.profile)?.server;
+    let url = server_url(&server_config, server.port());
+    let restart_count = server.restart_count.load(Ordering::Relaxed);
+
+    let mut metrics = ServerMetrics {
+        pid,
+        memory_bytes: None,
+        cpu_time_ms: None,
+        open_files: None,
+        requests_served: None,
+        restart_count,
+    };
+
+    metrics.requests_served = fetch_requests_served(&server_config, &url).await;
+
+    let Some(pid) = pid else {
+        return Ok(metrics);
+    };
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_memory().with_cpu(),
+    );
+    let tree: Vec<&Process> = process_tree(&system, Pid::from_u32(pid))
+        .into_iter()
+        .filter_map(|pid| system.process(pid))
+        .collect();
+
+    metrics.memory_bytes = Some(tree.iter().map(|p| p.memory()).sum());
+    metrics.cpu_time_ms = Some(tree.iter().map(|p| p.accumulated_cpu_time()).sum());
+    metrics.open_files = tree.iter().map(|p| p.open_files()).sum();
+
+    Ok(metrics)
+}
+
+/// Checks that a server is reachable at `url` before the user switches to
+/// it, using `token` the way the remote backend mode would.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn validate_server_connection(url: String, token: Option<String>) -> Result<HealthCheck, String> {
+    config::validate_http_url(&url, &t("settings.serverUrl"))?;
+
+    let server_config = ServerConfig {
+        profile: Some(ServerProfile::Custom),
+        external_url: Some(url),
+        external_token: token,
+        ..Default::default()
+    };
+    let url = server_url(&server_config, DEFAULT_SERVER_PORT);
+    Ok(probe_health(&server_config, &url).await)
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_server_port(state: tauri::State<'_, ServerState>, profile: Option<String>) -> Result<u16, String> {
+    Ok(state.server(&profile_id(profile)?).port())
+}
+
+/// The server's output goes to the app log, so this is the app log file.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_server_log_path() -> Result<String, String> {
+    Ok(logs::log_path()?.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn read_server_log_tail(lines: Option<usize>) -> Result<Vec<String>, String> {
+    // Logs written before redaction was added may still hold secrets.
+    let tail = logs::read_tail(&logs::log_path()?, lines.unwrap_or(DEFAULT_LOG_TAIL_LINES))?;
+    Ok(tail.into_iter().map(|line| crate::redact::text(&line).into_owned()).collect())
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_server_config() -> Result<ServerConfig, String> {
+    Ok(config::load()?.server)
+}
+
+/// Origins the user approved to call the local API, on top of the
+/// extension's and the app's, which are always allowed. Every other origin
+/// is turned away.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_allowed_origins() -> Result<Vec<String>, String> {
+    Ok(config::load()?.server.allowed_origins)
+}
+
+/// Replaces the approved origins and hands them to the running servers,
+/// returning the origins as saved.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn set_allowed_origins(app: AppHandle, origins: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for origin in &origins {
+        let origin = config::normalize_origin(origin)?;
+        if !normalized.contains(&origin) {
+            normalized.push(origin);
+        }
+    }
+
+    let mut settings = config::load()?;
+    settings.server.allowed_origins = normalized.clone();
+    config::save(&settings)?;
+    config::broadcast(&app, &settings)?;
+
+    refresh_allowed_origins(&app).await;
+    Ok(normalized)
+}
+
+/// Sends the allowed origins to every running local server, which only
+/// gets them in its environment when it starts. Called when the extension
+/// may have a new id, like after it was extracted or a store id was set.
+pub async fn refresh_allowed_origins(app: &AppHandle) {
+    let token = match crate::secrets::bridge_token() {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::warn!("Failed to read the bridge token: {}", e);
+            return;
+        }
+    };
+    for server in app.state::<ServerState>().servers() {
+        if server.pid().is_none() {
+            continue;
+        }
+        let server_config = match config::load_profile(&server.profile) {
+            Ok(settings) if settings.server.profile() != ServerProfile::Custom => settings.server,
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::warn!(profile = %server.profile, "Failed to update the allowed origins: {}", e);
+                continue;
+            }
+        };
+        let url = format!("{}/control/allowed-origins", server_url(&server_config, server.port()));
+        let sent = reqwest::Client::new()
+            .put(&url)
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "origins": allowed_origins(&server_config) }))
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = sent {
+            tracing::warn!(profile = %server.profile, "Failed to update the allowed origins: {}", e);
+        }
+    }
+}
+
+/// Saves the new server configuration and applies it, returning the new PID
+/// of the active profile's server, or `None` when it now points at an
+/// external server and the bundled ones were stopped.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn set_server_config(app: AppHandle, server_config: ServerConfig) -> Result<Option<u32>, String> {
+    server_config.validate()?;
+
+    let mut settings = config::load()?;
+    server_config.guard_paths(&app, &settings.server)?;
+    settings.server = server_config;
+    config::save(&settings)?;
+    config::broadcast(&app, &settings)?;
+
+    apply_config(&app, &settings.server).await?;
+    if settings.server.profile() == ServerProfile::Custom {
+        return Ok(None);
+    }
+    let active = app.state::<ServerState>().server(&crate::profiles::active());
+    Ok(active.pid())
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub async fn restart_server(app: AppHandle, profile: Option<String>) -> Result<u32, String> {
+    let server = app.state::<ServerState>().server(&profile_id(profile)?);
+    restart(&app, server).await
+}
+
+/// Stops the server until it is started or restarted again, e.g. to free the
+/// port for a server the user runs themselves.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn stop_server(app: AppHandle, profile: Option<String>) -> Result<(), String> {
+    let server = app.state::<ServerState>().server(&profile_id(profile)?);
+    let pid = server.pid();
+
+    let to_stop = server.clone();
+    tauri::async_runtime::spawn_blocking(move || stop(&to_stop))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let _ = app.emit("server-stopped", ServerStoppedPayload {
+        profile_id: server.profile.clone(),
+        pid,
+    });
+    Ok(())
+}
+
+/// Polls the health endpoint until the server answers or `timeout_ms` has
+/// passed, so onboarding doesn't finish against a server that is still
+/// booting or has failed. A lazily started server is started first.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn wait_for_server_ready(
+    app: AppHandle,
+    profile: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<ServerReadiness, String> {
+    let started = Instant::now();
+    let deadline = started + timeout_ms.map_or(DEFAULT_READY_TIMEOUT, Duration::from_millis);
+    let server = app.state::<ServerState>().server(&profile_id(profile)?);
+    let server_config = config::load_profile(&server.profile)?.server;
+
+    let lazy = server.lazy_listener.lock().unwrap().is_some();
+    if lazy && server_config.backend_mode() == BackendMode::Local {
+        ensure_running(&app, &server).await?;
+    }
+
+    let not_ready = |failure| ServerReadiness {
+        ready: false,
+        waited_ms: started.elapsed().as_millis() as u64,
+        failure: Some(failure),
+    };
+
+    loop {
+        if server_config.backend_mode() == BackendMode::Local {
+            let finished = server
+                .supervisor
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|task| task.inner().is_finished());
+            match finished {
+                None => return Ok(not_ready(ReadinessFailure::NotRunning)),
+                Some(true) => return Ok(not_ready(ReadinessFailure::Failed)),
+                Some(false) => {}
+            }
+        }
+
+        let url = server_url(&server_config, server.port());
+        let health = probe_health(&server_config, &url).await;
+        if health.healthy {
+            return Ok(ServerReadiness {
+                ready: true,
+                waited_ms: started.elapsed().as_millis() as u64,
+                failure: None,
+            });
+        }
+
+        if Instant::now() + READY_POLL_INTERVAL >= deadline {
+            return Ok(not_ready(ReadinessFailure::Unhealthy { error: health.error }));
+        }
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+    }
+}
+
+/// Starts the server if it isn't running yet and returns its PID. Naming a
+/// profile that hasn't been used yet spawns a separate server for it.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn ensure_server_running(app: AppHandle, profile: Option<String>) -> Result<u32, String> {
+    let server = app.state::<ServerState>().server(&profile_id(profile)?);
+    ensure_running(&app, &server).await
+}
+
+/// Switches between the dev, prod and custom server profiles and restarts
+/// the servers accordingly.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn set_server_profile(
+    app: AppHandle,
+    profile: ServerProfile,
+    external_url: Option<String>,
+) -> Result<(), String> {
+    let mut settings = config::load()?;
+    settings.server.profile = Some(profile);
+    if external_url.is_some() {
+        settings.server.external_url = external_url;
+    }
+    settings.server.validate()?;
+    config::save(&settings)?;
+    config::broadcast(&app, &settings)?;
+    apply_config(&app, &settings.server).await
+}
+
+/// Restarts the servers with a changed configuration, or stops them when
+/// it switched to the custom profile, which doesn't run any.
+pub async fn apply_config(app: &AppHandle, server_config: &ServerConfig) -> Result<(), String> {
+    if server_config.profile() == ServerProfile::Custom {
+        let handle = app.clone();
+        tauri::async_runtime::spawn_blocking(move || stop_all(&handle))
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        restart_all(app).await.map(|_| ())
+    }
+}
+
+/// Starts the active profile's server, or in lazy start mode waits for the
+/// first connection to its port before doing so. Does nothing for the custom
+/// profile.
+pub fn launch(app: &AppHandle) {
+    let profile = crate::profiles::active();
+    let server_config = config::load_profile(&profile).map(|s| s.server).unwrap_or_default();
+    if server_config.profile() == ServerProfile::Custom {
+        tracing::info!("Using external server, not starting one");
+        return;
+    }
+
+    let server = app.state::<ServerState>().server(&profile);
+    if !server_config.lazy_start {
+        // Spawn failures are logged and retried by the supervisor.
+        let _ = start(app, &server);
+        return;
+    }
+
+    if let Err(e) = start_lazy_listener(app, &server) {
+        tracing::warn!("Failed to set up lazy server start, starting now: {}", e);
+        let _ = start(app, &server);
+    }
+}
+
+/// Holds the server port until the first client connects. That client gets
+/// a 503 asking it to retry, and the server is started in the meantime.
+fn start_lazy_listener(app: &AppHandle, server: &Arc<ServerHandle>) -> Result<(), String> {
+    let server_config = config::load_profile(&server.profile)?.server;
+    let port = select_server_port(&server_config, server)?;
+    *server.port.lock().unwrap() = port;
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let listener = tokio::net::TcpListener::from_std(listener).map_err(|e| e.to_string())?;
+    tracing::info!("Server will start on first connection to port {}", port);
+
+    let handle = app.clone();
+    let lazy_server = server.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        if let Ok((mut stream, _)) = listener.accept().await {
+            let _ = stream.write_all(LAZY_START_RESPONSE).await;
+            let _ = stream.shutdown().await;
+        }
+        drop(listener);
+
+        lazy_server.lazy_listener.lock().unwrap().take();
+        let _ = start(&handle, &lazy_server);
+    });
+    *server.lazy_listener.lock().unwrap() = Some(task);
+
+    Ok(())
+}
+
+/// Starts the server under a supervisor that restarts it when it exits, and
+/// returns the PID of the first process. If that one fails to spawn the
+/// supervisor keeps retrying in the background.
+fn start(app: &AppHandle, server: &Arc<ServerHandle>) -> Result<u32, String> {
+    if config::load_profile(&server.profile)?.server.profile() == ServerProfile::Custom {
+        return Err("The custom server profile uses an external server".to_string());
+    }
+
+    let spawned = spawn_server(app, server);
+    let pid = spawned.as_ref().map(|(_, pid)| *pid).map_err(Clone::clone);
+
+    let task = tauri::async_runtime::spawn(supervise(app.clone(), server.clone(), spawned));
+    *server.supervisor.lock().unwrap() = Some(task);
+
+    pid
+}
+
+async fn ensure_running(app: &AppHandle, server: &Arc<ServerHandle>) -> Result<u32, String> {
+    {
+        let mut supervisor = server.supervisor.lock().unwrap();
+        match supervisor.as_ref() {
+            // A supervisor that gave up has finished; start over instead of
+            // waiting for a restart that will never come.
+            Some(task) if task.inner().is_finished() => {
+                supervisor.take();
+            }
+            Some(_) => return server.pid().ok_or_else(|| "Server is restarting".to_string()),
+            None => {}
+        }
+    }
+
+    let lazy_listener = server.lazy_listener.lock().unwrap().take();
+    if let Some(task) = lazy_listener {
+        task.abort();
+        // Wait for the listener to be dropped so the server can take the port.
+        let _ = task.await;
+    }
+
+    start(app, server)
+}
+
+async fn restart(app: &AppHandle, server: Arc<ServerHandle>) -> Result<u32, String> {
+    let port = server.port();
+
+    let to_stop = server.clone();
+    tauri::async_runtime::spawn_blocking(move || stop(&to_stop))
+        .await
+        .map_err(|e| e.to_string())?;
+    server.restart_count.fetch_add(1, Ordering::Relaxed);
+
+    let deadline = Instant::now() + PORT_RELEASE_TIMEOUT;
+    while port != 0 && !is_port_free(port) && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    start(app, &server)
+}
+
+/// Restarts the active profile's server and every other one that is
+/// running, returning the active server's new PID.
+pub async fn restart_all(app: &AppHandle) -> Result<u32, String> {
+    let state = app.state::<ServerState>();
+    let active = state.server(&crate::profiles::active());
+    let others: Vec<Arc<ServerHandle>> = state
+        .servers()
+        .into_iter()
+        .filter(|server| server.profile != active.profile && server.supervisor.lock().unwrap().is_some())
+        .collect();
+
+    for server in others {
+        let profile = server.profile.clone();
+        if let Err(e) = restart(app, server).await {
+            tracing::warn!(profile = %profile, "Failed to restart server: {}", e);
+        }
+    }
+
+    restart(app, active).await
+}
+
+/// Restarts the servers that are running, leaving stopped ones alone. A
+/// server that fails to restart is logged, since what it was restarted
+/// for has already been saved.
+pub async fn restart_running(app: &AppHandle) {
+    let running: Vec<Arc<ServerHandle>> = app
+        .state::<ServerState>()
+        .servers()
+        .into_iter()
+        .filter(|server| server.supervisor.lock().unwrap().is_some())
+        .collect();
+
+    for server in running {
+        let profile = server.profile.clone();
+        if let Err(e) = restart(app, server).await {
+            tracing::warn!(profile = %profile, "Failed to restart server: {}", e);
+        }
+    }
+}
+
+/// Stops supervising the server and terminates it.
+fn stop(server: &ServerHandle) {
+    if let Some(task) = server.supervisor.lock().unwrap().take() {
+        task.abort();
+    }
+    if let Some(task) = server.lazy_listener.lock().unwrap().take() {
+        task.abort();
+    }
+
+    let process = server.process.lock().unwrap().take();
+    if let Some(ServerProcess { child, .. }) = process {
+        let pid = child.pid();
+        tracing::info!(profile = %server.profile, "Stopping server with PID: {}", pid);
+        #[cfg(windows)]
+        let exited = request_shutdown(server.port(), pid);
+        #[cfg(not(windows))]
+        let exited = false;
+        if exited {
+            tracing::debug!(profile = %server.profile, "Server shut down on request");
+        } else if let Err(e) = terminate_process(child) {
+            tracing::error!(profile = %server.profile, "Failed to stop server: {}", e);
+        }
+    }
+
+    remove_pid_file(&server.profile);
+}
+
+/// Stops the server of `profile`, if it runs.
+pub fn stop_profile(app: &AppHandle, profile: &str) {
+    if let Some(server) = app.state::<ServerState>().servers.lock().unwrap().get(profile).cloned() {
+        stop(&server);
+    }
+}
+
+/// Stops the servers of all profiles.
+pub fn stop_all(app: &AppHandle) {
+    for server in app.state::<ServerState>().servers() {
+        stop(&server);
+    }
+}
+
+/// Kills servers left behind by a previous session that didn't shut down
+/// cleanly, so they don't hold on to their ports. A recorded PID is only
+/// trusted if that process still looks like our server, since PIDs get reused.
+pub fn reap_orphaned_servers() {
+    let profiles: Vec<(ProfileId, u32)> = known_profiles()
+        .into_iter()
+        .filter_map(|profile| read_pid_file(&profile).map(|pid| (profile, pid)))
+        .collect();
+    if profiles.is_empty() {
+        return;
+    }
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_cmd(UpdateKind::Always)
+            .with_exe(UpdateKind::Always),
+    );
+
+    for (profile, pid) in profiles {
+        let root = Pid::from_u32(pid);
+        if system.process(root).is_some_and(is_server_process) {
+            tracing::info!(profile = %profile, "Reaping orphaned server with PID: {}", pid);
+            kill_process_tree(&mut system, root);
+        }
+
+        remove_pid_file(&profile);
+    }
+}
+
+/// Starts the watchdog that catches a server which is still running but no
+/// longer answering. The first time it hangs it is restarted; if the new
+/// process hangs as well the user is notified instead.
+pub fn start_watchdog(app: &AppHandle) {
+    tauri::async_runtime::spawn(watchdog(app.clone()));
+}
+
+async fn watchdog(app: AppHandle) {
+    let mut watched: HashMap<ProfileId, WatchdogState> = HashMap::new();
+
+    loop {
+        tokio::time::sleep(WATCHDOG_INTERVAL).await;
+
+        for server in app.state::<ServerState>().servers() {
+            let watchdog = watched.entry(server.profile.clone()).or_default();
+            watch(&app, server, watchdog).await;
+        }
+    }
+}
+
+async fn watch(app: &AppHandle, server: Arc<ServerHandle>, watchdog: &mut WatchdogState) {
+    // Crashes and restarts are the supervisor's business; only watch a
+    // process that has had time to come up.
+    let pid = match server.process.lock().unwrap().as_ref() {
+        Some(process) if process.started_at.elapsed() >= WATCHDOG_STARTUP_GRACE => {
+            process.child.pid()
+        }
+        _ => {
+            watchdog.failures = 0;
+            return;
+        }
+    };
+    let server_config = match config::load_profile(&server.profile) {
+        Ok(settings) => settings.server,
+        Err(_) => return,
+    };
+    let url = server_url(&server_config, server.port());
+
+    let health = probe_health(&server_config, &url).await;
+    if health.healthy {
+        *watchdog = WatchdogState::default();
+        return;
+    }
+
+    watchdog.failures += 1;
+    emit_log(
+        app,
+        &server.profile,
+        "watchdog",
+        format!(
+            "Health check failed ({}/{}): {}",
+            watchdog.failures,
+            WATCHDOG_MAX_FAILURES,
+            health.error.as_deref().unwrap_or("unknown error")
+        ),
+    );
+    if watchdog.failures < WATCHDOG_MAX_FAILURES {
+        return;
+    }
+    watchdog.failures = 0;
+
+    if watchdog.restarted {
+        if !watchdog.notified {
+            let _ = app.emit("server-unresponsive", ServerUnresponsivePayload {
+                profile_id: server.profile.clone(),
+                pid,
+                error: health.error,
+                restarting: false,
+            });
+            notify(
+                app,
+                &t("notification.serverUnresponsive.title"),
+                &t("notification.serverUnresponsive.body"),
+            );
+            watchdog.notified = true;
+        }
+        return;
+    }
+
+    let _ = app.emit("server-unresponsive", ServerUnresponsivePayload {
+        profile_id: server.profile.clone(),
+        pid,
+        error: health.error,
+        restarting: true,
+    });
+    emit_log(
+        app,
+        &server.profile,
+        "watchdog",
+        "Server is not responding, restarting it".to_string(),
+    );
+    let profile = server.profile.clone();
+    if let Err(e) = restart(app, server).await {
+        tracing::warn!(profile = %profile, "Watchdog failed to restart server: {}", e);
+    }
+    watchdog.restarted = true;
+}
+
+async fn supervise(
+    app: AppHandle,
+    server: Arc<ServerHandle>,
+    mut spawned: Result<(Receiver<CommandEvent>, u32), String>,
+) {
+    let mut failures = 0;
+
+    loop {
+        let error = match spawned {
+            Ok((mut rx, pid)) => {
+                let started = Instant::now();
+                let code = wait_for_exit(&app, &server.profile, &mut rx).await;
+                forget_server_process(&server, pid);
+
+                if started.elapsed() >= STABLE_UPTIME {
+                    failures = 0;
+                }
+                report_crash(&app, &server, pid, code, failures);
+                format!("Server exited with code {:?}", code)
+            }
+            Err(e) => format!("Failed to start server: {}", e),
+        };
+
+        tracing::warn!(profile = %server.profile, "{}", error);
+        failures += 1;
+
+        if failures > MAX_RESTART_ATTEMPTS {
+            notify(
+                &app,
+                &t("notification.serverFailed.title"),
+                &t("notification.serverFailed.body"),
+            );
+            let _ = app.emit("server-failed", ServerFailedPayload {
+                profile_id: server.profile.clone(),
+                attempts: failures - 1,
+                error,
+            });
+            return;
+        }
+
+        tokio::time::sleep(restart_delay(failures)).await;
+
+        spawned = spawn_server(&app, &server);
+        if let Ok((_, pid)) = &spawned {
+            server.restart_count.fetch_add(1, Ordering::Relaxed);
+            let _ = app.emit("server-restarted", ServerRestartedPayload {
+                profile_id: server.profile.clone(),
+                pid: *pid,
+                attempt: failures,
+            });
+        }
+    }
+}
+
+/// Emits `server-crashed` for an unexpected exit. Only the first crash of a
+/// series raises a desktop notification; the supervisor handles the retries
+/// and notifies again if it gives up.
+fn report_crash(app: &AppHandle, server: &Arc<ServerHandle>, pid: u32, code: Option<i32>, failures: u32) {
+    let _ = app.emit("server-crashed", ServerCrashedPayload {
+        profile_id: server.profile.clone(),
+        pid,
+        code,
+        will_restart: failures < MAX_RESTART_ATTEMPTS,
+    });
+
+    if failures == 0 {
+        notify_restartable(
+            app,
+            server,
+            &t("notification.serverCrashed.title"),
+            &t("notification.serverCrashed.body"),
+        );
+    }
+}
+
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// A notification with a "Restart" action that restarts `server`. The
+/// notification plugin only supports actions on mobile, so this goes through
+/// notify-rust directly.
+///
+/// macOS gets the plain notification, whose body tells the user to restart
+/// the server from ClaudIn: notify-rust's macOS backend only reports a
+/// button press while the main run loop is pumped for it, which Tauri's
+/// event loop doesn't do.
+#[cfg(any(target_os = "linux", windows))]
+fn notify_restartable(app: &AppHandle, server: &Arc<ServerHandle>, title: &str, body: &str) {
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname("ClaudIn")
+        .summary(title)
+        .body(body)
+        .auto_icon()
+        .action(RESTART_ACTION, &t("notification.action.restart"));
+    // Windows only shows toasts for a registered app id, which development
+    // builds don't have.
+    #[cfg(windows)]
+    if !tauri::is_dev() {
+        notification.app_id(&app.config().identifier);
+    }
+    let shown = notification.show();
+    let handle = match shown {
+        Ok(handle) => handle,
+        Err(e) => {
+            tracing::warn!("Failed to show notification: {}", e);
+            return;
+        }
+    };
+
+    let app = app.clone();
+    let server = server.clone();
+    // Blocks until the notification is acted on or closed.
+    std::thread::spawn(move || {
+        handle.wait_for_action(|action| {
+            if action != RESTART_ACTION {
+                return;
+            }
+            tauri::async_runtime::spawn(async move {
+                let profile = server.profile.clone();
+                if let Err(e) = restart(&app, server).await {
+                    tracing::warn!(profile = %profile, "Failed to restart server from notification: {}", e);
+                }
+            });
+        });
+    });
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn notify_restartable(app: &AppHandle, _server: &Arc<ServerHandle>, title: &str, body: &str) {
+    notify(app, title, body);
+}
+
+/// Exponential backoff: 1s, 2s, 4s, ... capped at `MAX_RESTART_DELAY`.
+fn restart_delay(failures: u32) -> Duration {
+    INITIAL_RESTART_DELAY
+        .saturating_mul(1 << (failures - 1).min(16))
+        .min(MAX_RESTART_DELAY)
+}
+
+fn spawn_server(app: &AppHandle, server: &ServerHandle) -> Result<(Receiver<CommandEvent>, u32), String> {
+    let server_config = config::load_profile(&server.profile)?.server;
+    let port = select_server_port(&server_config, server)?;
+    *server.port.lock().unwrap() = port;
+    if let Err(e) = persist_port(&server.profile, port) {
+        tracing::warn!(profile = %server.profile, "Failed to persist server port: {}", e);
+    }
+
+    let mut command = server_command(app, &server_config)?
+        .env("PORT", port.to_string())
+        .envs(server_config.to_env());
+    if let Some(dir) = data_dir(&server.profile)? {
+        command = command.env("CLAUDIN_DATA_DIR", dir);
+    }
+    command = command.env("CLAUDIN_ALLOWED_ORIGINS", allowed_origins(&server_config).join(","));
+    if let Some(tls_port) = server_config.tls_port {
+        let certificate = crate::tls::ensure_certificate()?;
+        command = command
+            .env("CLAUDIN_TLS_PORT", tls_port.to_string())
+            .env("CLAUDIN_TLS_CERT", certificate.cert)
+            .env("CLAUDIN_TLS_KEY", certificate.key);
+    }
+    command = command.env(crate::secrets::BRIDGE_TOKEN_VAR, crate::secrets::bridge_token()?);
+    // The key is passed even with encryption off, so the server can decrypt
+    // a database that was encrypted before.
+    match crate::secrets::data_key(server_config.encrypt_data) {
+        Ok(Some(key)) => command = command.env(crate::secrets::DATA_KEY_VAR, key),
+        Ok(None) => {}
+        Err(e) if server_config.encrypt_data => return Err(e),
+        Err(e) => tracing::warn!("Failed to read the encryption key from the credential store: {}", e),
+    }
+    match crate::secrets::api_key() {
+        Ok(Some(key)) => command = command.env(crate::secrets::API_KEY_VAR, key),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to read the API key from the credential store: {}", e),
+    }
+    let (rx, child) = command.spawn().map_err(|e| e.to_string())?;
+
+    let pid = child.pid();
+    tracing::info!(profile = %server.profile, "Server started with PID: {:?}", pid);
+    if server_config.low_priority {
+        if let Err(e) = lower_priority(pid) {
+            tracing::warn!("Failed to lower server priority: {}", e);
+        }
+    }
+    if let Err(e) = write_pid_file(&server.profile, pid) {
+        tracing::warn!(profile = %server.profile, "Failed to write server PID file: {}", e);
+    }
+    *server.process.lock().unwrap() = Some(ServerProcess {
+        child,
+        started_at: Instant::now(),
+    });
+
+    Ok((rx, pid))
+}
+
+/// Every origin the server accepts requests from: the app, the extension
+/// under each id Chromium browsers gave it, and the ones the user approved.
+/// Firefox and Safari give each install a random origin, which the server
+/// accepts from requests carrying the bridge token instead.
+fn allowed_origins(server_config: &ServerConfig) -> Vec<String> {
+    let mut origins: Vec<String> = APP_ORIGINS.iter().map(|origin| origin.to_string()).collect();
+    if cfg!(debug_assertions) {
+        origins.extend(DEV_ORIGINS.iter().map(|origin| origin.to_string()));
+    }
+
+    let mut extension_ids = crate::extension::installed_extension_ids().unwrap_or_else(|e| {
+        tracing::warn!("Failed to list the extension's ids: {}", e);
+        Vec::new()
+    });
+    if let Ok(Some(store_id)) = config::load().map(|settings| settings.extension.store_id) {
+        extension_ids.push(store_id);
+    }
+    origins.extend(extension_ids.iter().map(|id| format!("chrome-extension://{}", id)));
+
+    for origin in &server_config.allowed_origins {
+        match config::normalize_origin(origin) {
+            Ok(origin) => origins.push(origin),
+            Err(e) => tracing::warn!("Ignoring allowed origin: {}", e),
+        }
+    }
+    let mut unique = Vec::new();
+    for origin in origins {
+        if !unique.contains(&origin) {
+            unique.push(origin);
+        }
+    }
+    unique
+}
+
+/// Builds the command that launches the server for the configured profile.
+/// The dev profile runs the sources with tsx so server changes don't need a
+/// recompile; prod runs the bundled sidecar binary.
+fn server_command(app: &AppHandle, server_config: &ServerConfig) -> Result<Command, String> {
+    match server_config.profile() {
+        ServerProfile::Dev => {
+            let server_script = match &server_config.script_path {
+                Some(script) => resolve_script_path(app, script)?,
+                None => dev_server_script(),
+            };
+            tracing::info!("Starting server from: {:?}", server_script);
+            Ok(script_command(app, &server_script))
+        }
+        ServerProfile::Prod => {
+            tracing::info!("Starting bundled server sidecar");
+            app.shell().sidecar(SERVER_SIDECAR).map_err(|e| e.to_string())
+        }
+        ServerProfile::Custom => Err("The custom server profile doesn't spawn a server".to_string()),
+    }
+}
+
+/// What the active profile's server is started from: the script in
+/// development and the bundled sidecar next to the app otherwise. A custom
+/// server isn't started by the app, so there's nothing.
+pub fn server_program(app: &AppHandle) -> Result<Option<PathBuf>, String> {
+    let server_config = config::load()?.server;
+    match server_config.profile() {
+        ServerProfile::Dev => match &server_config.script_path {
+            Some(script) => resolve_script_path(app, script).map(Some),
+            None => Ok(Some(dev_server_script())),
+        },
+        ServerProfile::Prod => {
+            let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+            let name = format!("{}{}", SERVER_SIDECAR, std::env::consts::EXE_SUFFIX);
+            Ok(exe.parent().map(|dir| dir.join(name)))
+        }
+        ServerProfile::Custom => Ok(None),
+    }
+}
+
+/// Runs TypeScript sources through tsx and anything else with plain node.
+fn script_command(app: &AppHandle, script: &Path) -> Command {
+    if script.extension().is_some_and(|ext| ext == "ts") {
+        app.shell().command("npx").arg("tsx").arg(script)
+    } else {
+        app.shell().command("node").arg(script)
+    }
+}
+
+/// Relative script paths are taken relative to the app's resource dir, so a
+/// bundled script can be referenced the same way on every machine. Scripts
+/// outside it have to have been picked by the user.
+fn resolve_script_path(app: &AppHandle, script: &Path) -> Result<PathBuf, String> {
+    let resource_dir = app.path().resource_dir().map_err(|e| e.to_string())?;
+    let script = resource_dir.join(script);
+    let escapes = script.components().any(|component| component == std::path::Component::ParentDir);
+    if escapes || !script.starts_with(&resource_dir) {
+        crate::paths::ensure_allowed(&script)?;
+    }
+
+    if !script.exists() {
+        return Err(format!("Server script not found: {}", script.display()));
+    }
+    Ok(script)
+}
+
+/// The workspace server entry point, located from the crate's own source
+/// directory rather than the working directory, which depends on how the
+/// app was launched.
+pub fn dev_server_script() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("..")
+        .join(DEV_SERVER_SCRIPT)
+}
+
+/// Base URL of the active profile's server.
+pub fn current_url(app: &AppHandle) -> Result<String, String> {
+    let profile = crate::profiles::active();
+    let port = app.state::<ServerState>().server(&profile).port();
+    Ok(server_url(&config::load_profile(&profile)?.server, port))
+}
+
+/// Health of the active profile's server.
+pub async fn current_health(app: &AppHandle) -> Result<HealthCheck, String> {
+    let url = current_url(app)?;
+    Ok(probe_health(&config::load()?.server, &url).await)
+}
+
+async fn server_status(server_config: &ServerConfig, server: &ServerHandle) -> ServerStatus {
+    let (pid, uptime_secs) = match server.process.lock().unwrap().as_ref() {
+        Some(process) => (
+            Some(process.child.pid()),
+            Some(process.started_at.elapsed().as_secs()),
+        ),
+        None => (None, None),
+    };
+    let port = server.port();
+    let url = server_url(server_config, port);
+
+    let health = probe_health(server_config, &url).await;
+    let backend_mode = server_config.backend_mode();
+    let running = match backend_mode {
+        BackendMode::Local => pid.is_some(),
+        BackendMode::Remote => health.healthy,
+    };
+
+    ServerStatus {
+        profile_id: server.profile.clone(),
+        profile: server_config.profile(),
+        backend_mode,
+        url,
+        running,
+        pid,
+        uptime_secs,
+        port,
+        health,
+    }
+}
+
+/// Base URL of the server the app talks to, without a trailing slash.
+fn server_url(server_config: &ServerConfig, port: u16) -> String {
+    match (&server_config.external_url, server_config.profile()) {
+        (Some(url), ServerProfile::Custom) => url.trim_end_matches('/').to_string(),
+        _ => format!("http://127.0.0.1:{}", port),
+    }
+}
+
+/// The configured port and the default port only apply to the default
+/// profile; other profiles keep whatever port they got the first time.
+fn select_server_port(server_config: &ServerConfig, server: &ServerHandle) -> Result<u16, String> {
+    if server.is_default() {
+        select_port(server_config.port, &[DEFAULT_SERVER_PORT, server.port()])
+    } else {
+        select_port(None, &[server.port()])
+    }
+}
+
+/// Picks the port for the next server start. A configured port is used as-is;
+/// otherwise the first free candidate, and finally whatever free port the OS
+/// hands out.
+fn select_port(configured: Option<u16>, candidates: &[u16]) -> Result<u16, String> {
+    if let Some(port) = configured {
+        if !is_port_free(port) {
+            return Err(format!("Configured port {} is already in use", port));
+        }
+        return Ok(port);
+    }
+
+    for (i, &candidate) in candidates.iter().enumerate() {
+        if candidate == 0 || candidates[..i].contains(&candidate) {
+            continue;
+        }
+        if is_port_free(candidate) {
+            return Ok(candidate);
+        }
+        tracing::warn!("Port {} is already in use", candidate);
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    Ok(port)
+}
+
+fn is_port_free(port: u16) -> bool {
+    TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// Resolves the `profile` argument of a command, the active profile when
+/// there is none. Ids end up in paths, so only short names made of letters,
+/// digits, `-` and `_` are accepted.
+pub fn profile_id(profile: Option<String>) -> Result<ProfileId, String> {
+    let profile = profile.unwrap_or_else(crate::profiles::active);
+    let valid = !profile.is_empty()
+        && profile.len() <= 64
+        && profile.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid profile id: {:?}", profile));
+    }
+    Ok(profile)
+}
+
+/// Directory holding a profile's port and PID files, and for profiles other
+/// than the default one its server's data.
+pub fn profile_dir(profile: &str) -> Result<PathBuf, String> {
+    let config_dir = crate::get_config_dir()?;
+    if profile == DEFAULT_PROFILE {
+        Ok(config_dir)
+    } else {
+        Ok(config_dir.join(PROFILES_DIR).join(profile))
+    }
+}
+
+/// Where the servers keep their databases, in every known profile.
+pub fn data_dirs() -> Result<Vec<PathBuf>, String> {
+    known_profiles().iter().map(|profile| database_dir(profile)).collect()
+}
+
+/// Where the server of `profile` keeps its database.
+pub fn database_dir(profile: &str) -> Result<PathBuf, String> {
+    match data_dir(profile)? {
+        Some(dir) => Ok(dir),
+        None => Ok(dirs::home_dir().ok_or("Could not find home directory")?.join(".claudin")),
+    }
+}
+
+/// The data dir handed to the server of `profile`, `None` when it's left to
+/// the server: the default profile uses the server's own default unless
+/// that's overridden, and every other profile its `data` dir.
+fn data_dir(profile: &str) -> Result<Option<PathBuf>, String> {
+    if profile == DEFAULT_PROFILE {
+        Ok(config::server_data_dir())
+    } else {
+        Ok(Some(profile_dir(profile)?.join("data")))
+    }
+}
+
+/// The default profile and every profile that has a directory on disk.
+pub fn known_profiles() -> Vec<ProfileId> {
+    let mut profiles = vec![DEFAULT_PROFILE.to_string()];
+    let Ok(config_dir) = crate::get_config_dir() else {
+        return profiles;
+    };
+    if let Ok(entries) = fs::read_dir(config_dir.join(PROFILES_DIR)) {
+        profiles.extend(
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| profile_id(Some(name.clone())).is_ok()),
+        );
+    }
+    profiles
+}
+
+fn read_persisted_port(profile: &str) -> Option<u16> {
+    let path = profile_dir(profile).ok()?.join(SERVER_PORT_FILE);
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn persist_port(profile: &str, port: u16) -> Result<(), String> {
+    let dir = profile_dir(profile)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    fs::write(dir.join(SERVER_PORT_FILE), port.to_string()).map_err(|e| e.to_string())
+}
+
+fn is_server_process(process: &Process) -> bool {
+    let is_sidecar = process.name().to_string_lossy().starts_with(SERVER_SIDECAR);
+    let runs_dev_script = process
+        .cmd()
+        .iter()
+        .any(|arg| Path::new(arg).ends_with(DEV_SERVER_SCRIPT));
+    is_sidecar || runs_dev_script
+}
+
+/// Signals `root` and all of its descendants to exit, then kills whatever is
+/// still running once `SERVER_SHUTDOWN_TIMEOUT` has passed.
+fn kill_process_tree(system: &mut System, root: Pid) {
+    let tree = process_tree(system, root);
+
+    for pid in &tree {
+        if let Some(process) = system.process(*pid) {
+            if process.kill_with(Signal::Term).is_none() {
+                process.kill();
+            }
+        }
+    }
+
+    let deadline = Instant::now() + SERVER_SHUTDOWN_TIMEOUT;
+    loop {
+        system.refresh_processes(ProcessesToUpdate::Some(&tree), true);
+        let alive: Vec<&Process> = tree.iter().filter_map(|pid| system.process(*pid)).collect();
+        if alive.is_empty() {
+            return;
+        }
+        if Instant::now() >= deadline {
+            for process in alive {
+                process.kill();
+            }
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Returns `root` followed by all of its descendants.
+fn process_tree(system: &System, root: Pid) -> Vec<Pid> {
+    let mut tree = vec![root];
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i];
+        tree.extend(
+            system
+                .processes()
+                .iter()
+                .filter(|(_, p)| p.parent() == Some(parent))
+                .map(|(pid, _)| *pid),
+        );
+        i += 1;
+    }
+    tree
+}
+
+fn read_pid_file(profile: &str) -> Option<u32> {
+    let path = profile_dir(profile).ok()?.join(SERVER_PID_FILE);
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn write_pid_file(profile: &str, pid: u32) -> Result<(), String> {
+    let dir = profile_dir(profile)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    fs::write(dir.join(SERVER_PID_FILE), pid.to_string()).map_err(|e| e.to_string())
+}
+
+fn remove_pid_file(profile: &str) {
+    if let Ok(dir) = profile_dir(profile) {
+        let _ = fs::remove_file(dir.join(SERVER_PID_FILE));
+    }
+}
+
+/// Forwards the process output to the webview as `server-log` events until
+/// it terminates, returning its exit code.
+async fn wait_for_exit(app: &AppHandle, profile: &str, rx: &mut Receiver<CommandEvent>) -> Option<i32> {
+    while let Some(event) = rx.recv().await {
+        let (stream, line) = match event {
+            CommandEvent::Stdout(bytes) => ("stdout", String::from_utf8_lossy(&bytes).into_owned()),
+            CommandEvent::Stderr(bytes) => ("stderr", String::from_utf8_lossy(&bytes).into_owned()),
+            CommandEvent::Error(error) => ("error", error),
+            CommandEvent::Terminated(payload) => {
+                emit_log(app, profile, "terminated", format!("Server exited with code {:?}", payload.code));
+                return payload.code;
+            }
+            _ => continue,
+        };
+        emit_log(app, profile, stream, line.trim_end().to_string());
+    }
+    None
+}
+
+/// Emits a `server-log` event and logs the line under the `server` target,
+/// which puts it in the app log file next to the app's own lines.
+fn emit_log(app: &AppHandle, profile: &str, stream: &'static str, line: String) {
+    match stream {
+        "stderr" | "error" => tracing::warn!(target: "server", profile, "[{}] {}", stream, line),
+        _ => tracing::info!(target: "server", profile, "[{}] {}", stream, line),
+    }
+
+    let _ = app.emit("server-log", ServerLogPayload {
+        profile_id: profile.to_string(),
+        stream,
+        line,
+        timestamp: chrono::Utc::now().timestamp_millis() as u64,
+    });
+}
+
+/// Clears the tracked process if it is still the one with `pid`, so a stale
+/// exit notification can't drop a newer process.
+fn forget_server_process(server: &ServerHandle, pid: u32) {
+    let mut process = server.process.lock().unwrap();
+    if process.as_ref().is_some_and(|p| p.child.pid() == pid) {
+        *process = None;
+    }
+}
+
+/// Starts a GET request to the server, authenticated with the token of a
+/// remote backend, or the bridge token for a local server.
+pub fn server_get(server_config: &ServerConfig, url: &str) -> reqwest::RequestBuilder {
+    let request = reqwest::Client::new().get(url);
+    let token = match server_config.backend_mode() {
+        BackendMode::Remote => server_config.auth_token(),
+        BackendMode::Local => crate::secrets::bridge_token()
+            .inspect_err(|e| tracing::warn!("Failed to read the bridge token: {}", e))
+            .ok(),
+    };
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+async fn probe_health(server_config: &ServerConfig, base_url: &str) -> HealthCheck {
+    let url = format!("{}/", base_url);
+    let started = Instant::now();
+
+    let response = match server_get(server_config, &url)
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return HealthCheck::failed(e.to_string()),
+    };
+
+    if !response.status().is_success() {
+        return HealthCheck::failed(format!("Health check returned {}", response.status()));
+    }
+
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let version = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("version")?.as_str().map(String::from));
+
+    HealthCheck {
+        healthy: true,
+        latency_ms: Some(latency_ms),
+        version,
+        error: None,
+    }
+}
+
+async fn fetch_requests_served(server_config: &ServerConfig, base_url: &str) -> Option<u64> {
+    let url = format!("{}/metrics", base_url);
+    let body = server_get(server_config, &url)
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .json::<serde_json::Value>()
+        .await
+        .ok()?;
+    body.get("requestsServed")?.as_u64()
+}
+
+impl HealthCheck {
+    fn failed(error: String) -> Self {
+        HealthCheck {
+            healthy: false,
+            latency_ms: None,
+            version: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Renices the server. Processes it spawns afterwards, like `node` under
+/// `npx`, inherit the lower priority.
+#[cfg(unix)]
+fn lower_priority(pid: u32) -> Result<(), String> {
+    const NICENESS: libc::c_int = 10;
+
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, NICENESS) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn lower_priority(pid: u32) -> Result<(), String> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, PROCESS_SET_INFORMATION,
+    };
+
+    unsafe {
+        let process = OpenProcess(PROCESS_SET_INFORMATION, 0, pid);
+        if process.is_null() {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        let result = SetPriorityClass(process, BELOW_NORMAL_PRIORITY_CLASS);
+        CloseHandle(process);
+        if result == 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Asks the process to exit with SIGTERM and falls back to SIGKILL if it is
+/// still alive once `SERVER_SHUTDOWN_TIMEOUT` has passed.
+#[cfg(unix)]
+fn terminate_process(child: CommandChild) -> Result<(), String> {
+    let pid = child.pid() as libc::pid_t;
+
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return child.kill().map_err(|e| e.to_string());
+    }
+
+    let deadline = Instant::now() + SERVER_SHUTDOWN_TIMEOUT;
+    while Instant::now() < deadline {
+        if unsafe { libc::kill(pid, 0) } != 0 {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    child.kill().map_err(|e| e.to_string())
+}
+
+/// Asks the server to save and exit through its control endpoint and waits
+/// for it to, since Windows has no SIGTERM and `taskkill /F` would lose the
+/// encrypted database's last save. Returns whether the process exited.
+#[cfg(windows)]
+fn request_shutdown(port: u16, pid: u32) -> bool {
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+
+    let token = match crate::secrets::bridge_token() {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::warn!("Failed to read the bridge token: {}", e);
+            return false;
+        }
+    };
+    let request = format!(
+        "POST /control/shutdown HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nAuthorization: Bearer {token}\r\n\
+         Content-Length: 0\r\nConnection: close\r\n\r\n"
+    );
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let accepted = TcpStream::connect_timeout(&address, HEALTH_CHECK_TIMEOUT).and_then(|mut stream| {
+        stream.set_read_timeout(Some(HEALTH_CHECK_TIMEOUT))?;
+        stream.write_all(request.as_bytes())?;
+        let mut status = [0u8; 12];
+        stream.read_exact(&mut status)?;
+        Ok(status.ends_with(b" 202"))
+    });
+    match accepted {
+        Ok(true) => {}
+        Ok(false) => return false,
+        Err(e) => {
+            tracing::debug!("Failed to ask the server to shut down: {}", e);
+            return false;
+        }
+    }
+
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    let deadline = Instant::now() + SERVER_SHUTDOWN_TIMEOUT;
+    while Instant::now() < deadline {
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        if system.process(pid).is_none() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+/// Kills the whole process tree, since in development `npx` leaves `node`
+/// running as a grandchild that a plain kill would orphan.
+#[cfg(windows)]
+fn terminate_process(child: CommandChild) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &child.pid().to_string(), "/T", "/F"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .map_err(|e| e.to_string())?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        child.kill().map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,244 @@
+use macro_rules_attribute::apply;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audit::audited;
+use crate::browser::{self, Browser};
+
+/// LinkedIn keeps the signed in member's session in this cookie.
+const AUTH_COOKIE: &str = "li_at";
+
+const LINKEDIN_DOMAIN: &str = "linkedin.com";
+
+/// Seconds from 1601, where Chromium counts cookie times from, to 1970.
+const CHROMIUM_EPOCH_OFFSET: i64 = 11_644_473_600;
+
+/// Whether the user is signed in to LinkedIn in the browser, which the
+/// extension needs before it can sync anything.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum LinkedInSession {
+    /// The user hasn't allowed looking at the browser's cookies yet, see
+    /// `set_linkedin_session_consent`.
+    ConsentRequired,
+    /// Signed in, in the profile with the given directory.
+    #[serde(rename_all = "camelCase")]
+    SignedIn { profile: String },
+    SignedOut,
+    /// The cookies couldn't be checked, e.g. in Firefox, or while Chrome on
+    /// Windows keeps them locked.
+    #[serde(rename_all = "camelCase")]
+    Unknown { reason: String },
+}
+
+/// Looks for LinkedIn's auth cookie in `profile` of `browser`, by default
+/// the default browser and the profiles the extension is used in. Only the
+/// cookie's presence and expiry are read, never its value, and only once
+/// the user agreed to it.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn check_linkedin_session(
+    browser: Option<Browser>,
+    profile: Option<String>,
+) -> Result<LinkedInSession, String> {
+    if !crate::config::load()?.browser.session_check_allowed {
+        return Ok(LinkedInSession::ConsentRequired);
+    }
+    let browser = browser.unwrap_or_else(browser::default_browser);
+    tauri::async_runtime::spawn_blocking(move || {
+        if !browser.is_chromium() {
+            return Ok(LinkedInSession::Unknown {
+                reason: format!("Signing in to LinkedIn can't be checked in {}", browser.display_name()),
+            });
+        }
+        let profiles = match profile {
+            Some(id) => {
+                let profiles: Vec<_> = browser.profiles().into_iter().filter(|p| p.id == id).collect();
+                if profiles.is_empty() {
+                    return Err(format!("{} has no profile {:?}", browser.display_name(), id));
+                }
+                profiles
+            }
+            None => browser.relevant_profiles(),
+        };
+
+        let mut failure = None;
+        for profile in profiles {
+            match has_auth_cookie(&profile.path) {
+                Ok(true) => return Ok(LinkedInSession::SignedIn { profile: profile.id }),
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::warn!("Could not check the LinkedIn session in {}: {}", profile.path.display(), e);
+                    failure = Some(e);
+                }
+            }
+        }
+        Ok(match failure {
+            Some(reason) => LinkedInSession::Unknown { reason },
+            None => LinkedInSession::SignedOut,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Records whether the user allowed `check_linkedin_session` to look at the
+/// browser's cookies.
+#[tauri::command]
+#[apply(audited!)]
+pub fn set_linkedin_session_consent(allowed: bool) -> Result<(), String> {
+    let mut settings = crate::config::load()?;
+    settings.browser.session_check_allowed = allowed;
+    crate::config::save(&settings)
+}
+
+/// Whether the profile has an auth cookie for LinkedIn that hasn't expired.
+/// Chromium moved its cookies into `Network` in version 96.
+fn has_auth_cookie(profile_dir: &Path) -> Result<bool, String> {
+    let path = [profile_dir.join("Network").join("Cookies"), profile_dir.join("Cookies")]
+        .into_iter()
+        .find(|path| path.is_file());
+    let Some(path) = path else {
+        return Ok(false);
+    };
+
+    // The browser keeps the database open and recent writes in its `-wal`
+    // file, so a copy of both is read instead of the database itself.
+    let copy = CookiesCopy::new(&path)?;
+    let connection = Connection::open_with_flags(&copy.database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Could not open {}: {}", path.display(), e))?;
+
+    // Session cookies have no expiry and last until the browser quits.
+    let now = (chrono::Utc::now().timestamp() + CHROMIUM_EPOCH_OFFSET) * 1_000_000;
+    query_auth_cookie(&connection, now).map_err(|e| format!("Could not read {}: {}", path.display(), e))
+}
+
+/// Whether `cookies` holds an auth cookie for LinkedIn or one of its
+/// subdomains that is still valid at `now`, in Chromium's microseconds.
+fn query_auth_cookie(connection: &Connection, now: i64) -> rusqlite::Result<bool> {
+    connection.query_row(
+        "SELECT EXISTS (
+            SELECT 1 FROM cookies
+            WHERE name = ?1
+              AND (host_key = ?2 OR host_key = '.' || ?2 OR host_key LIKE '%.' || ?2)
+              AND (expires_utc = 0 OR expires_utc > ?3)
+        )",
+        rusqlite::params![AUTH_COOKIE, LINKEDIN_DOMAIN, now],
+        |row| row.get(0),
+    )
+}
+
+/// A copy of a cookies database and its `-wal` file in a temporary
+/// directory, removed when dropped.
+struct CookiesCopy {
+    dir: PathBuf,
+    database: PathBuf,
+}
+
+impl CookiesCopy {
+    fn new(path: &Path) -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!(
+            "claudin-cookies-{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let copy = CookiesCopy {
+            database: dir.join("Cookies"),
+            dir,
+        };
+
+        fs::copy(path, &copy.database).map_err(|e| format!("Could not copy {}: {}", path.display(), e))?;
+        let mut wal = path.as_os_str().to_owned();
+        wal.push("-wal");
+        let wal = PathBuf::from(wal);
+        if wal.is_file() {
+            fs::copy(&wal, copy.dir.join("Cookies-wal"))
+                .map_err(|e| format!("Could not copy {}: {}", wal.display(), e))?;
+        }
+        Ok(copy)
+    }
+}
+
+impl Drop for CookiesCopy {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 13_350_000_000_000_000;
+
+    fn cookies(rows: &[(&str, &str, i64)]) -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE cookies (host_key TEXT, name TEXT, value TEXT, expires_utc INTEGER)")
+            .unwrap();
+        for (host, name, expires) in rows {
+            connection
+                .execute(
+                    "INSERT INTO cookies (host_key, name, value, expires_utc) VALUES (?1, ?2, 'secret', ?3)",
+                    rusqlite::params![host, name, expires],
+                )
+                .unwrap();
+        }
+        connection
+    }
+
+    #[test]
+    fn finds_unexpired_auth_cookie() {
+        let connection = cookies(&[(".www.linkedin.com", AUTH_COOKIE, NOW + 1)]);
+        assert!(query_auth_cookie(&connection, NOW).unwrap());
+    }
+
+    #[test]
+    fn session_cookies_count_as_unexpired() {
+        let connection = cookies(&[(".linkedin.com", AUTH_COOKIE, 0)]);
+        assert!(query_auth_cookie(&connection, NOW).unwrap());
+    }
+
+    #[test]
+    fn ignores_expired_and_other_cookies() {
+        let connection = cookies(&[
+            (".linkedin.com", AUTH_COOKIE, NOW - 1),
+            (".linkedin.com", "JSESSIONID", NOW + 1),
+            (".notlinkedin.com", AUTH_COOKIE, NOW + 1),
+            ("linkedin.com.example", AUTH_COOKIE, NOW + 1),
+        ]);
+        assert!(!query_auth_cookie(&connection, NOW).unwrap());
+    }
+
+    #[test]
+    fn reads_cookies_still_in_the_wal() {
+        let dir = std::env::temp_dir().join(format!("claudin-session-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Cookies");
+
+        let writer = Connection::open(&path).unwrap();
+        writer
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA wal_autocheckpoint = 0;
+                 CREATE TABLE cookies (host_key TEXT, name TEXT, value TEXT, expires_utc INTEGER);",
+            )
+            .unwrap();
+        writer
+            .execute(
+                "INSERT INTO cookies VALUES ('.linkedin.com', ?1, 'secret', 0)",
+                [AUTH_COOKIE],
+            )
+            .unwrap();
+        assert!(dir.join("Cookies-wal").is_file());
+
+        // The writer stays open like the browser, so nothing is checkpointed.
+        let found = has_auth_cookie(&dir);
+        drop(writer);
+        let _ = fs::remove_dir_all(&dir);
+        assert!(found.unwrap());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::audit::audited;
+use crate::config::{self, ChecklistItem, SetupStep};
+use macro_rules_attribute::apply;
+use semver::Version;
+use serde::Serialize;
+use std::fs;
+use std::sync::OnceLock;
+use tauri::AppHandle;
+
+/// How this start relates to the last one, decided once at startup.
+static RUN_CONTEXT: OnceLock<RunContext> = OnceLock::new();
+
+/// Whether the app runs for the first time, for the first time since an
+/// upgrade, or neither, so the frontend knows whether to show onboarding,
+/// release notes or nothing.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RunContext {
+    FirstRun,
+    /// `from` is unknown for versions from before the last run was tracked.
+    #[serde(rename_all = "camelCase")]
+    Upgraded { from: Option<String>, to: String },
+    Normal,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupProgress {
+    /// First step that isn't done, where the setup wizard resumes.
+    current: Option<SetupStep>,
+    steps: Vec<StepProgress>,
+    complete: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepProgress {
+    step: SetupStep,
+    completed: bool,
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_setup_progress() -> Result<SetupProgress, String> {
+    Ok(progress(&config::load()?.setup))
+}
+
+/// Records that the user got through `step`, which steps may be in any
+/// order since some can be done again from the wizard's later screens.
+#[tauri::command]
+#[apply(audited!)]
+pub fn complete_setup_step(app: AppHandle, step: SetupStep) -> Result<SetupProgress, String> {
+    let mut settings = config::load()?;
+    if settings.setup.completed_steps.insert(step) {
+        config::save(&settings)?;
+        config::broadcast(&app, &settings)?;
+        tracing::info!("Completed setup step {:?}", step);
+    }
+    Ok(progress(&settings.setup))
+}
+
+/// Finishes setup at once, for users who leave the wizard before going
+/// through every step.
+#[tauri::command]
+#[apply(audited!)]
+pub fn mark_setup_complete(app: AppHandle) -> Result<(), String> {
+    let mut settings = config::load()?;
+    settings.setup.completed_steps.extend(SetupStep::ALL);
+    config::save(&settings)?;
+    config::broadcast(&app, &settings)?;
+    let _ = fs::remove_file(config::legacy_setup_file()?);
+    Ok(())
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn is_setup_complete() -> Result<bool, String> {
+    Ok(config::load()?.setup.is_complete())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistEntry {
+    item: ChecklistItem,
+    done: bool,
+}
+
+/// The onboarding checklist, kept in the settings rather than the webview's
+/// storage so it survives that being cleared.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_checklist() -> Result<Vec<ChecklistEntry>, String> {
+    Ok(checklist(&config::load()?.setup))
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn set_checklist_item(app: AppHandle, item: ChecklistItem, done: bool) -> Result<Vec<ChecklistEntry>, String> {
+    let mut settings = config::load()?;
+    let changed = if done {
+        settings.setup.checklist.insert(item)
+    } else {
+        settings.setup.checklist.remove(&item)
+    };
+    if changed {
+        config::save(&settings)?;
+        config::broadcast(&app, &settings)?;
+    }
+    Ok(checklist(&settings.setup))
+}
+
+fn checklist(setup: &config::SetupConfig) -> Vec<ChecklistEntry> {
+    ChecklistItem::ALL
+        .into_iter()
+        .map(|item| ChecklistEntry {
+            item,
+            done: setup.checklist.contains(&item),
+        })
+        .collect()
+}
+
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_run_context() -> RunContext {
+    run_context().clone()
+}
+
+/// Decides the run context from the version that ran last and records the
+/// current one, which has to happen before anything else saves settings.
+pub fn record_run() {
+    if let Err(e) = detect_run_context() {
+        tracing::warn!("Failed to record the app version: {}", e);
+    }
+}
+
+pub fn run_context() -> &'static RunContext {
+    RUN_CONTEXT.get_or_init(|| RunContext::Normal)
+}
+
+fn detect_run_context() -> Result<(), String> {
+    let mut settings = config::load()?;
+    let current = env!("CARGO_PKG_VERSION").to_string();
+    let context = match settings.setup.last_run_version.as_deref() {
+        // Installs from before the version was tracked have gone through
+        // setup at least in part.
+        None if settings.setup.completed_steps.is_empty() => RunContext::FirstRun,
+        None => RunContext::Upgraded { from: None, to: current.clone() },
+        Some(last) if is_newer(&current, last) => RunContext::Upgraded {
+            from: Some(last.to_string()),
+            to: current.clone(),
+        },
+        Some(last) => {
+            if last != current {
+                tracing::warn!("Running {} after the newer {}", current, last);
+            }
+            RunContext::Normal
+        }
+    };
+    tracing::info!("Run context: {:?}", context);
+    let _ = RUN_CONTEXT.set(context);
+
+    if settings.setup.last_run_version.as_deref() != Some(current.as_str()) {
+        settings.setup.last_run_version = Some(current);
+        config::save(&settings)?;
+    }
+    Ok(())
+}
+
+/// Versions that don't parse count as different builds, and so as newer.
+fn is_newer(current: &str, last: &str) -> bool {
+    match (Version::parse(current), Version::parse(last)) {
+        (Ok(current), Ok(last)) => current > last,
+        _ => current != last,
+    }
+}
+
+fn progress(setup: &config::SetupConfig) -> SetupProgress {
+    SetupProgress {
+        current: setup.current_step(),
+        steps: SetupStep::ALL
+            .into_iter()
+            .map(|step| StepProgress {
+                step,
+                completed: setup.completed_steps.contains(&step),
+            })
+            .collect(),
+        complete: setup.is_complete(),
+    }
+}
@@ -0,0 +1,349 @@
+use crate::audit::audited;
+use crate::i18n::{t, t_with};
+use chrono::Datelike;
+use macro_rules_attribute::apply;
+use rcgen::{
+    BasicConstraints, CertificateParams, CidrSubnet, DistinguishedName, DnType, ExtendedKeyUsagePurpose,
+    GeneralSubtree, IsCa, Issuer, KeyPair, KeyUsagePurpose, NameConstraints,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Folder in the config dir holding the certificates.
+const TLS_DIR: &str = "tls";
+
+const CA_CERT_FILE: &str = "ca.crt";
+const CA_KEY_FILE: &str = "ca.key";
+const CERT_FILE: &str = "localhost.crt";
+const KEY_FILE: &str = "localhost.key";
+
+/// The authority users trust lasts long enough that they only ever trust it
+/// once.
+const CA_LIFETIME_DAYS: i64 = 3650;
+
+/// Browsers refuse server certificates valid for longer.
+const CERT_LIFETIME_DAYS: i64 = 397;
+
+/// The server certificate is replaced when it's this close to expiring.
+const RENEW_BEFORE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+const CA_NAME: &str = "ClaudIn Local Authority";
+
+/// Files the server is handed to serve https with.
+pub struct ServerCertificate {
+    /// The server's certificate followed by the authority's.
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsCertificate {
+    /// Port the server also serves https on, `None` when it doesn't.
+    port: Option<u16>,
+    /// The authority to trust, which signs the server's certificates.
+    ca_path: String,
+    cert_path: String,
+    /// SHA-256 of the authority's certificate, to check it before trusting.
+    fingerprint: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustGuidance {
+    ca_path: String,
+    steps: Vec<String>,
+    /// Commands doing the same from a terminal.
+    commands: Vec<String>,
+}
+
+/// The certificates for https on localhost, generated on first use.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_tls_certificate() -> Result<TlsCertificate, String> {
+    let files = ensure_certificate()?;
+    let ca = fs::read_to_string(dir()?.join(CA_CERT_FILE)).map_err(|e| e.to_string())?;
+    Ok(TlsCertificate {
+        port: crate::config::load()?.server.tls_port,
+        ca_path: dir()?.join(CA_CERT_FILE).to_string_lossy().to_string(),
+        cert_path: files.cert.to_string_lossy().to_string(),
+        fingerprint: fingerprint(&ca)?,
+    })
+}
+
+/// How to make this platform's browsers trust the local authority, which
+/// the app doesn't do by itself since it needs the user's or an admin's
+/// approval.
+#[tauri::command]
+#[apply(audited!)]
+pub fn get_tls_trust_guidance() -> Result<TrustGuidance, String> {
+    ensure_certificate()?;
+    let ca_path = dir()?.join(CA_CERT_FILE).to_string_lossy().to_string();
+    let path = [("path", ca_path.as_str())];
+    let (steps, commands) = if cfg!(target_os = "macos") {
+        (
+            vec![t_with("tls.macos.open", &path), t("tls.macos.trust")],
+            vec![format!(
+                "security add-trusted-cert -r trustRoot -k ~/Library/Keychains/login.keychain-db \"{}\"",
+                ca_path
+            )],
+        )
+    } else if cfg!(windows) {
+        (
+            vec![t_with("tls.windows.open", &path), t("tls.windows.store")],
+            vec![format!("certutil -user -addstore Root \"{}\"", ca_path)],
+        )
+    } else {
+        (
+            vec![t_with("tls.linux.system", &path), t("tls.linux.browsers")],
+            vec![
+                format!(
+                    "sudo cp \"{}\" /usr/local/share/ca-certificates/claudin.crt && sudo update-ca-certificates",
+                    ca_path
+                ),
+                format!(
+                    "certutil -d sql:$HOME/.pki/nssdb -A -t C,, -n ClaudIn -i \"{}\"",
+                    ca_path
+                ),
+            ],
+        )
+    };
+    Ok(TrustGuidance {
+        ca_path,
+        steps,
+        commands,
+    })
+}
+
+/// Makes sure there's a server certificate that isn't about to expire,
+/// creating the authority too on first use. The authority is kept, so a
+/// browser that trusts it keeps trusting renewed certificates, unless it
+/// predates the name constraints and could vouch for any site.
+pub fn ensure_certificate() -> Result<ServerCertificate, String> {
+    let dir = dir()?;
+    let files = ServerCertificate {
+        cert: dir.join(CERT_FILE),
+        key: dir.join(KEY_FILE),
+    };
+    let authority = match (
+        fs::read_to_string(dir.join(CA_KEY_FILE)),
+        fs::read_to_string(dir.join(CA_CERT_FILE)),
+    ) {
+        (Ok(key), Ok(cert)) if is_constrained_authority(&cert) => Some((key, cert)),
+        (Ok(_), Ok(_)) => {
+            tracing::warn!("Replacing the local certificate authority, which expired or isn't limited to localhost");
+            None
+        }
+        _ => None,
+    };
+
+    let renew_at = SystemTime::now() - Duration::from_secs(CERT_LIFETIME_DAYS as u64 * 24 * 60 * 60) + RENEW_BEFORE;
+    let fresh = fs::metadata(&files.cert)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified > renew_at);
+    if authority.is_some() && fresh && files.key.exists() {
+        return Ok(files);
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let (ca_key, ca_cert) = match authority {
+        Some((key, cert)) => (KeyPair::from_pem(&key).map_err(|e| format!("Invalid TLS key: {}", e))?, cert),
+        None => {
+            let key = KeyPair::generate().map_err(|e| format!("Could not generate a TLS key: {}", e))?;
+            let cert = authority_params()
+                .self_signed(&key)
+                .map_err(|e| format!("Could not create the certificate authority: {}", e))?
+                .pem();
+            write_private(&dir.join(CA_KEY_FILE), &key.serialize_pem())?;
+            fs::write(dir.join(CA_CERT_FILE), &cert).map_err(|e| e.to_string())?;
+            tracing::info!("Created the local certificate authority in {}", dir.display());
+            (key, cert)
+        }
+    };
+
+    let (key, cert) = server_certificate(&ca_key)?;
+    write_private(&files.key, &key)?;
+    fs::write(&files.cert, cert + &ca_cert).map_err(|e| e.to_string())?;
+    tracing::info!("Created a certificate for localhost");
+    Ok(files)
+}
+
+fn dir() -> Result<PathBuf, String> {
+    Ok(crate::get_config_dir()?.join(TLS_DIR))
+}
+
+/// The local authority, which can only sign certificates for the loopback
+/// names and addresses: browsers check the critical name constraints, so
+/// its key can't be used to impersonate other sites even if it leaks.
+fn authority_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params.distinguished_name = common_name(CA_NAME);
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    params.name_constraints = Some(NameConstraints {
+        permitted_subtrees: vec![
+            GeneralSubtree::DnsName("localhost".to_string()),
+            GeneralSubtree::IpAddress(CidrSubnet::from_v4_prefix([127, 0, 0, 1], 32)),
+            GeneralSubtree::IpAddress(CidrSubnet::from_v6_prefix(std::net::Ipv6Addr::LOCALHOST.octets(), 128)),
+        ],
+        excluded_subtrees: Vec::new(),
+    });
+    set_validity(&mut params, CA_LIFETIME_DAYS);
+    params
+}
+
+/// A new key and a certificate for it for localhost, signed by the
+/// authority, both PEM encoded.
+fn server_certificate(ca_key: &KeyPair) -> Result<(String, String), String> {
+    let mut params = CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()])
+        .map_err(|e| e.to_string())?;
+    params.distinguished_name = common_name("localhost");
+    params.is_ca = IsCa::ExplicitNoCa;
+    params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    params.use_authority_key_identifier_extension = true;
+    set_validity(&mut params, CERT_LIFETIME_DAYS);
+
+    let authority = authority_params();
+    let issuer = Issuer::from_params(&authority, ca_key);
+    let key = KeyPair::generate().map_err(|e| format!("Could not generate a TLS key: {}", e))?;
+    let cert = params
+        .signed_by(&key, &issuer)
+        .map_err(|e| format!("Could not sign the certificate: {}", e))?;
+    Ok((key.serialize_pem(), cert.pem()))
+}
+
+fn common_name(name: &str) -> DistinguishedName {
+    let mut distinguished_name = DistinguishedName::new();
+    distinguished_name.push(DnType::CommonName, name);
+    distinguished_name
+}
+
+/// Valid from the start of today, which leaves slack for clocks that are
+/// behind, for `lifetime_days`.
+fn set_validity(params: &mut CertificateParams, lifetime_days: i64) {
+    let date = |at: chrono::DateTime<chrono::Utc>| rcgen::date_time_ymd(at.year(), at.month() as u8, at.day() as u8);
+    let now = chrono::Utc::now();
+    params.not_before = date(now);
+    params.not_after = date(now + chrono::Duration::days(lifetime_days));
+}
+
+/// Whether the authority's certificate has the critical name constraints
+/// and is still valid, which certificates from older versions lack.
+fn is_constrained_authority(pem: &str) -> bool {
+    let Ok((_, pem)) = x509_parser::pem::parse_x509_pem(pem.as_bytes()) else {
+        return false;
+    };
+    let Ok(cert) = pem.parse_x509() else {
+        return false;
+    };
+    let constrained = cert
+        .tbs_certificate
+        .name_constraints()
+        .is_ok_and(|constraints| constraints.is_some_and(|constraints| constraints.critical));
+    constrained && cert.validity().is_valid()
+}
+
+fn fingerprint(pem: &str) -> Result<String, String> {
+    let (_, pem) = x509_parser::pem::parse_x509_pem(pem.as_bytes()).map_err(|e| format!("Invalid PEM file: {}", e))?;
+    let digest = Sha256::digest(&pem.contents);
+    Ok(digest
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":"))
+}
+
+/// Writes a key only the user can read.
+fn write_private(path: &Path, contents: &str) -> Result<(), String> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(|e| e.to_string())?;
+    std::io::Write::write_all(&mut file, contents.as_bytes()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x509_parser::extensions::{GeneralName, ParsedExtension};
+    use x509_parser::prelude::X509Certificate;
+
+    fn parse(pem: &str) -> X509Certificate<'static> {
+        let (_, pem) = x509_parser::pem::parse_x509_pem(pem.as_bytes()).unwrap();
+        let der = Box::leak(pem.contents.into_boxed_slice());
+        x509_parser::parse_x509_certificate(der).unwrap().1
+    }
+
+    #[test]
+    fn authority_is_limited_to_loopback() {
+        let key = KeyPair::generate().unwrap();
+        let pem = authority_params().self_signed(&key).unwrap().pem();
+        let cert = parse(&pem);
+
+        let constraints = cert.tbs_certificate.name_constraints().unwrap().unwrap();
+        assert!(constraints.critical);
+        let permitted = constraints.value.permitted_subtrees.as_ref().unwrap();
+        let names: Vec<_> = permitted.iter().map(|subtree| &subtree.base).collect();
+        assert!(matches!(names[0], GeneralName::DNSName("localhost")));
+        assert!(matches!(names[1], GeneralName::IPAddress([127, 0, 0, 1, 255, 255, 255, 255])));
+        assert!(matches!(names[2], GeneralName::IPAddress(address) if address.len() == 32));
+        assert!(constraints.value.excluded_subtrees.is_none());
+
+        let basic = cert.tbs_certificate.basic_constraints().unwrap().unwrap().value;
+        assert!(basic.ca);
+        assert_eq!(basic.path_len_constraint, Some(0));
+        assert!(is_constrained_authority(&pem));
+    }
+
+    #[test]
+    fn server_certificate_is_signed_by_the_authority() {
+        let ca_key = KeyPair::generate().unwrap();
+        let ca_pem = authority_params().self_signed(&ca_key).unwrap().pem();
+        let (key, pem) = server_certificate(&ca_key).unwrap();
+        assert!(KeyPair::from_pem(&key).is_ok());
+
+        let ca = parse(&ca_pem);
+        let cert = parse(&pem);
+        assert_eq!(cert.issuer(), ca.subject());
+        assert!(!cert.tbs_certificate.basic_constraints().unwrap().unwrap().value.ca);
+        assert!(cert.verify_signature(Some(ca.public_key())).is_ok());
+
+        let names: Vec<String> = cert
+            .subject_alternative_name()
+            .unwrap()
+            .unwrap()
+            .value
+            .general_names
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(names.len(), 3);
+        assert!(names[0].contains("localhost"));
+        let server_auth = cert.extensions().iter().any(|extension| {
+            matches!(extension.parsed_extension(), ParsedExtension::ExtendedKeyUsage(usage) if usage.server_auth)
+        });
+        assert!(server_auth);
+    }
+
+    #[test]
+    fn unconstrained_authority_is_replaced() {
+        let key = KeyPair::generate().unwrap();
+        let mut params = authority_params();
+        params.name_constraints = None;
+        assert!(!is_constrained_authority(&params.self_signed(&key).unwrap().pem()));
+        assert!(!is_constrained_authority("not a certificate"));
+    }
+
+    #[test]
+    fn fingerprint_is_colon_separated_sha256() {
+        let key = KeyPair::generate().unwrap();
+        let fingerprint = fingerprint(&authority_params().self_signed(&key).unwrap().pem()).unwrap();
+        assert_eq!(fingerprint.len(), 32 * 3 - 1);
+        assert!(fingerprint.split(':').all(|byte| byte.len() == 2));
+    }
+}
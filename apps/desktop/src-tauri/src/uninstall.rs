@@ -0,0 +1,114 @@
+use crate::audit::audited;
+use crate::reset::{self, ResetFailure, ResetOptions, ResetReport};
+use macro_rules_attribute::apply;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// Starts the app in uninstall mode, as the installer's uninstall hook does.
+const UNINSTALL_ARG: &str = "--prepare-uninstall";
+
+/// Also removes the user's data in uninstall mode.
+const REMOVE_USER_DATA_ARG: &str = "--remove-user-data";
+
+/// Written to the temp dir, since the config dir may be gone by then.
+const REPORT_FILE: &str = "claudin-uninstall-report.json";
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UninstallOptions {
+    /// The settings, profiles, logs, backups and the servers' databases.
+    user_data: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallReport {
+    removed: Vec<String>,
+    failed: Vec<ResetFailure>,
+    /// Where the report was written, if it could be.
+    report_path: Option<String>,
+}
+
+/// Cleans up what the app put outside its install dir before the app
+/// itself is removed: every extracted extension and the native messaging
+/// host registrations, and with `userData` everything else the app wrote.
+/// The app doesn't register itself to start at login, so there's nothing
+/// to remove for that.
+#[tauri::command]
+#[apply(audited!)]
+pub async fn prepare_uninstall(app: AppHandle, options: UninstallOptions) -> Result<UninstallReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::server::stop_all(&app);
+        prepare(&options)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Whether the installer started the app to clean up before uninstalling.
+pub fn is_uninstall_launch() -> bool {
+    std::env::args().skip(1).any(|arg| arg == UNINSTALL_ARG)
+}
+
+/// Runs the cleanup without a window for the installer. The servers weren't
+/// started by this process, so they're found from their PID files.
+pub fn run_for_installer() {
+    crate::server::reap_orphaned_servers();
+    let options = UninstallOptions {
+        user_data: std::env::args().skip(1).any(|arg| arg == REMOVE_USER_DATA_ARG),
+    };
+    prepare(&options);
+}
+
+/// The extension goes through `reset` so its folder is only deleted the way
+/// a reset deletes it. The config dir goes as a whole, backups included,
+/// rather than being backed up first.
+fn prepare(options: &UninstallOptions) -> UninstallReport {
+    let reset_options = ResetOptions {
+        extension: true,
+        database: options.user_data,
+        ..ResetOptions::default()
+    };
+    let mut report = reset::reset(&reset_options).unwrap_or_else(|error| {
+        let mut report = ResetReport::default();
+        report.failed.push(ResetFailure {
+            path: String::new(),
+            error,
+        });
+        report
+    });
+    if options.user_data {
+        match crate::config::config_dir() {
+            Ok(dir) => reset::remove(&dir, &mut report),
+            Err(error) => report.failed.push(ResetFailure {
+                path: String::new(),
+                error,
+            }),
+        }
+    }
+
+    let report_path = write_report(options, &report)
+        .inspect_err(|e| tracing::warn!("Failed to write the uninstall report: {}", e))
+        .ok();
+    UninstallReport {
+        removed: report.removed,
+        failed: report.failed,
+        report_path: report_path.map(|path| path.to_string_lossy().to_string()),
+    }
+}
+
+fn write_report(options: &UninstallOptions, report: &ResetReport) -> Result<PathBuf, String> {
+    let path = std::env::temp_dir().join(REPORT_FILE);
+    let contents = serde_json::json!({
+        "createdAt": chrono::Utc::now().to_rfc3339(),
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "userData": options.user_data,
+        "removed": report.removed,
+        "failed": report.failed,
+    });
+    let contents = serde_json::to_string_pretty(&contents).map_err(|e| e.to_string())?;
+    fs::write(&path, contents).map_err(|e| e.to_string())?;
+    Ok(path)
+}
@@ -8,6 +8,7 @@ import { PostsView } from './components/PostsView';
 import { ProfileDetailView } from './components/ProfileDetailView';
 import { CommandPalette } from './components/CommandPalette';
 import { SettingsModal } from './components/SettingsModal';
+import { apiFetch } from './lib/api';
 import { MCPModal } from './components/MCPModal';
 import { SetupWizard } from './components/SetupWizard';
 import { useAppStore } from './store/app';
@@ -29,7 +30,7 @@ export default function App() {
       }
 
       try {
-        const res = await fetch('http://localhost:3847/api/stats');
+        const res = await apiFetch('http://localhost:3847/api/stats');
         if (!res.ok) throw new Error('Server not ready');
       } catch {
         console.log('Server not ready, will retry...');
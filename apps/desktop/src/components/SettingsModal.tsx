@@ -1,8 +1,18 @@
 import { useState, useEffect } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { X, Eye, EyeOff, Key, CheckCircle2, AlertCircle, Loader2 } from 'lucide-react';
+import { invoke } from '@tauri-apps/api/core';
 import { useAppStore } from '../store/app';
-import { fetchSettingsStatus, updateSettings } from '../lib/api';
+
+interface ApiKeyStatus {
+  stored: boolean;
+  hint: string | null;
+}
+
+interface SecretStorageStatus {
+  bridgeTokenInFile: boolean;
+  warning: string | null;
+}
 
 export function SettingsModal() {
   const { isSettingsOpen, setSettingsOpen } = useAppStore();
@@ -12,6 +22,7 @@ export function SettingsModal() {
   const [status, setStatus] = useState<'idle' | 'success' | 'error'>('idle');
   const [message, setMessage] = useState('');
   const [isConfigured, setIsConfigured] = useState(false);
+  const [storageWarning, setStorageWarning] = useState<string | null>(null);
 
   // Reset state when opening
   useEffect(() => {
@@ -25,8 +36,10 @@ export function SettingsModal() {
 
   const checkStatus = async () => {
     try {
-      const { hasApiKey } = await fetchSettingsStatus();
-      setIsConfigured(hasApiKey);
+      const { stored } = await invoke<ApiKeyStatus>('get_api_key_status');
+      setIsConfigured(stored);
+      const { warning } = await invoke<SecretStorageStatus>('get_secret_storage_status');
+      setStorageWarning(warning);
     } catch (error) {
       console.error('Failed to check settings status:', error);
     }
@@ -45,7 +58,8 @@ export function SettingsModal() {
     setMessage('');
 
     try {
-      await updateSettings({ openrouter_api_key: apiKey });
+      // Kept in the OS keychain rather than the server's database.
+      await invoke<ApiKeyStatus>('set_api_key', { key: apiKey });
       setStatus('success');
       setMessage('Settings saved successfully');
       setIsConfigured(true);
@@ -116,6 +130,14 @@ export function SettingsModal() {
                   </span>
                 </div>
 
+                {/* The bridge token fell back to a plain file */}
+                {storageWarning && (
+                  <div className="flex items-start gap-3 p-3 rounded-lg border bg-yellow-500/10 border-yellow-500/20 text-yellow-500">
+                    <AlertCircle className="w-5 h-5 shrink-0" />
+                    <span className="text-sm">{storageWarning}</span>
+                  </div>
+                )}
+
                 {/* API Key Input */}
                 <div className="space-y-2">
                   <label htmlFor="apiKey" className="block text-sm font-medium text-text-secondary">
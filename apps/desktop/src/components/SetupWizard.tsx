@@ -1,39 +1,219 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 interface SetupWizardProps {
   onComplete: () => void;
 }
 
+interface ExtractionProgress {
+  filesCopied: number;
+  totalFiles: number;
+  bytesCopied: number;
+  totalBytes: number;
+}
+
+interface ServerReadiness {
+  ready: boolean;
+  waitedMs: number;
+  failure:
+    | { reason: 'notRunning' }
+    | { reason: 'failed' }
+    | { reason: 'unhealthy'; error: string | null }
+    | null;
+}
+
+type BrowserChoice = 'chrome' | 'firefox' | 'safari';
+
+const BROWSER_NAMES: Record<BrowserChoice, string> = {
+  chrome: 'Chrome',
+  firefox: 'Firefox',
+  safari: 'Safari',
+};
+
+interface SafariStatus {
+  installed: boolean;
+  version: string | null;
+  extensionApp: string | null;
+}
+
+interface BrowserCapabilities {
+  machineManaged: boolean;
+  mdmEnrolled: boolean;
+  canLoadUnpacked: boolean;
+  canOpenInternalUrls: boolean;
+  reasons: string[];
+}
+
+type LinkedInSession =
+  | { status: 'consentRequired' }
+  | { status: 'signedIn'; profile: string }
+  | { status: 'signedOut' }
+  | { status: 'unknown'; reason: string };
+
+type SetupStep = 'extractExtension' | 'loadExtension' | 'verifyConnection' | 'signInToLinkedIn';
+
+type ChecklistItem = 'extensionLoaded' | 'extensionPinned' | 'linkedInConnected' | 'firstMessageSent';
+
+interface SetupProgress {
+  current: SetupStep | null;
+  steps: { step: SetupStep; completed: boolean }[];
+  complete: boolean;
+}
+
+type OpenPageError =
+  | { reason: 'noSupportedBrowser' }
+  | { reason: 'launchFailed'; browser: string; url: string; error: string; copied: boolean; steps: string[] };
+
+function describeOpenPageError(e: unknown): string {
+  const failure = e as OpenPageError;
+  switch (failure?.reason) {
+    case 'noSupportedBrowser':
+      return "Your browser can't load the ClaudIn extension. Install Chrome, Edge, Brave or Firefox and open its extensions page from there.";
+    case 'launchFailed':
+      return `ClaudIn couldn't open the page. ${failure.steps.join(' ')}`;
+    default:
+      return String(e);
+  }
+}
+
+function describeFailure(failure: ServerReadiness['failure']): string {
+  switch (failure?.reason) {
+    case 'notRunning':
+      return 'The ClaudIn server is not running. Restart ClaudIn and try again.';
+    case 'failed':
+      return 'The ClaudIn server failed to start. Check the server logs, then restart ClaudIn.';
+    case 'unhealthy':
+      return `The ClaudIn server is not responding yet${failure.error ? ` (${failure.error})` : ''}. Try again in a moment.`;
+    default:
+      return 'The ClaudIn server is not ready yet.';
+  }
+}
+
 export function SetupWizard({ onComplete }: SetupWizardProps) {
   const [step, setStep] = useState(1);
   const [extensionPath, setExtensionPath] = useState('');
   const [isExtracting, setIsExtracting] = useState(false);
+  const [progress, setProgress] = useState<ExtractionProgress | null>(null);
   const [error, setError] = useState<string | null>(null);
   const [copied, setCopied] = useState(false);
+  const [isWaitingForServer, setIsWaitingForServer] = useState(false);
+  const [serverError, setServerError] = useState<string | null>(null);
+  const [openPageError, setOpenPageError] = useState<string | null>(null);
+  const [browser, setBrowser] = useState<BrowserChoice>('chrome');
+  const [hasSafari, setHasSafari] = useState(false);
+  const [linkedInSession, setLinkedInSession] = useState<LinkedInSession | null>(null);
+  const [capabilities, setCapabilities] = useState<BrowserCapabilities | null>(null);
 
   useEffect(() => {
     extractExtension();
+    invoke<SetupProgress>('get_setup_progress')
+      .then(resumeSetup)
+      .catch((e) => console.error('Failed to read setup progress:', e));
+    invoke<SafariStatus>('detect_safari')
+      .then((status) => setHasSafari(status.installed))
+      .catch((e) => console.error('Failed to detect Safari:', e));
   }, []);
 
-  async function extractExtension() {
+  useEffect(() => {
+    setCapabilities(null);
+    if (browser === 'safari') {
+      return;
+    }
+    invoke<BrowserCapabilities>('get_browser_capabilities', { browser: browser === 'firefox' ? 'firefox' : null })
+      .then(setCapabilities)
+      .catch((e) => console.error('Failed to check browser capabilities:', e));
+  }, [browser]);
+
+  const isLockedDown = capabilities !== null && (!capabilities.canLoadUnpacked || !capabilities.canOpenInternalUrls);
+
+  function resumeSetup(setupProgress: SetupProgress) {
+    const completed = (step: SetupStep) => setupProgress.steps.some((s) => s.step === step && s.completed);
+    if (completed('loadExtension')) {
+      setStep(3);
+    } else if (completed('extractExtension')) {
+      setStep(2);
+    }
+  }
+
+  function completeStep(step: SetupStep) {
+    invoke('complete_setup_step', { step }).catch((e) => console.error(`Failed to complete setup step ${step}:`, e));
+  }
+
+  function checkItem(item: ChecklistItem) {
+    invoke('set_checklist_item', { item, done: true }).catch((e) =>
+      console.error(`Failed to check off ${item}:`, e)
+    );
+  }
+
+  async function extractExtension(target: BrowserChoice = 'chrome') {
     setIsExtracting(true);
     setError(null);
+    setProgress(null);
+    const unlisten = await listen<ExtractionProgress>('extraction-progress', (event) => {
+      setProgress(event.payload);
+    });
     try {
-      const path = await invoke<string>('extract_extension');
+      const path =
+        target === 'safari'
+          ? await invoke<string>('install_safari_extension')
+          : await invoke<string>('extract_extension', { browser: target });
       setExtensionPath(path);
+      setBrowser(target);
+      completeStep('extractExtension');
     } catch (e) {
       setError(String(e));
     } finally {
+      unlisten();
       setIsExtracting(false);
     }
   }
 
-  async function openChromeExtensions() {
+  async function openBrowserExtensions() {
+    setOpenPageError(null);
+    try {
+      await invoke('open_browser_extensions');
+    } catch (e) {
+      console.error('Failed to open browser:', e);
+      setOpenPageError(describeOpenPageError(e));
+    }
+  }
+
+  async function openFirefoxAddons() {
+    setOpenPageError(null);
+    try {
+      await invoke('open_firefox_addons');
+    } catch (e) {
+      console.error('Failed to open Firefox:', e);
+      setOpenPageError(describeOpenPageError(e));
+    }
+  }
+
+  async function openSafariExtensionsPreferences() {
+    setOpenPageError(null);
     try {
-      await invoke('open_chrome_extensions');
+      await invoke('open_safari_extensions_preferences');
     } catch (e) {
-      console.error('Failed to open Chrome:', e);
+      console.error('Failed to open Safari settings:', e);
+      setOpenPageError(String(e));
+    }
+  }
+
+  async function checkLinkedInSession(allow = false) {
+    try {
+      if (allow) {
+        await invoke('set_linkedin_session_consent', { allowed: true });
+      }
+      const session = await invoke<LinkedInSession>('check_linkedin_session', {
+        browser: browser === 'firefox' ? 'firefox' : null,
+      });
+      setLinkedInSession(session);
+      if (session.status === 'signedIn') {
+        completeStep('signInToLinkedIn');
+        checkItem('linkedInConnected');
+      }
+    } catch (e) {
+      setLinkedInSession({ status: 'unknown', reason: String(e) });
     }
   }
 
@@ -46,6 +226,22 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
   }
 
   async function completeSetup() {
+    setIsWaitingForServer(true);
+    setServerError(null);
+    try {
+      const readiness = await invoke<ServerReadiness>('wait_for_server_ready', { timeoutMs: 30000 });
+      if (!readiness.ready) {
+        setServerError(describeFailure(readiness.failure));
+        return;
+      }
+      completeStep('verifyConnection');
+    } catch (e) {
+      setServerError(String(e));
+      return;
+    } finally {
+      setIsWaitingForServer(false);
+    }
+
     try {
       await invoke('mark_setup_complete');
       onComplete();
@@ -76,7 +272,20 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
                 <div className="flex-1">
                   <h3 className="text-lg font-semibold text-text-primary mb-1">Extension Ready</h3>
                   {isExtracting ? (
-                    <p className="text-text-secondary">Extracting extension files...</p>
+                    <div>
+                      <p className="text-text-secondary">
+                        Extracting extension files...
+                        {progress && ` (${progress.filesCopied}/${progress.totalFiles})`}
+                      </p>
+                      {progress && progress.totalBytes > 0 && (
+                        <div className="h-1.5 bg-bg-tertiary rounded-full mt-2 overflow-hidden">
+                          <div
+                            className="h-full bg-blue-500 transition-all"
+                            style={{ width: `${(progress.bytesCopied / progress.totalBytes) * 100}%` }}
+                          />
+                        </div>
+                      )}
+                    </div>
                   ) : error ? (
                     <p className="text-red-400">{error}</p>
                   ) : (
@@ -139,7 +348,9 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
                   <span className="text-blue-400 font-semibold">2</span>
                 </div>
                 <div className="flex-1">
-                  <h3 className="text-lg font-semibold text-text-primary mb-2">Load in Chrome</h3>
+                  <h3 className="text-lg font-semibold text-text-primary mb-2">
+                    Load in {BROWSER_NAMES[browser]}
+                  </h3>
                   
                   <div className="bg-bg-tertiary rounded-lg p-3 mb-4">
                     <p className="text-text-tertiary text-xs mb-1">Extension folder path:</p>
@@ -169,18 +380,63 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
                     </div>
                   </div>
 
-                  <ol className="text-text-secondary text-sm space-y-2 list-decimal list-inside mb-4">
-                    <li>Click <strong className="text-text-primary">Open Chrome Extensions</strong> below</li>
-                    <li>Enable <strong className="text-text-primary">Developer mode</strong> (top right toggle)</li>
-                    <li>Click <strong className="text-text-primary">Load unpacked</strong></li>
-                    <li>Paste the path above or navigate to the folder</li>
-                  </ol>
+                  {capabilities && isLockedDown && (
+                    <div className="bg-amber-500/10 border border-amber-500/30 rounded-lg p-3 mb-4">
+                      <p className="text-amber-300 text-sm mb-1">
+                        {capabilities.machineManaged || capabilities.mdmEnrolled
+                          ? 'Your browser is managed by your organization and blocks these steps:'
+                          : 'Your browser settings block these steps:'}
+                      </p>
+                      <ul className="text-text-secondary text-xs space-y-1">
+                        {capabilities.reasons.map((reason) => (
+                          <li key={reason}>• {reason}</li>
+                        ))}
+                      </ul>
+                      <p className="text-text-secondary text-xs mt-2">
+                        Ask your IT administrator to allow the ClaudIn extension.
+                      </p>
+                    </div>
+                  )}
+
+                  {browser === 'safari' ? (
+                    <ol className="text-text-secondary text-sm space-y-2 list-decimal list-inside mb-4">
+                      <li>Click <strong className="text-text-primary">Open Safari Settings</strong> below</li>
+                      <li>Check <strong className="text-text-primary">ClaudIn</strong> in the Extensions list</li>
+                      <li>
+                        If it isn't listed, enable <strong className="text-text-primary">Allow Unsigned Extensions</strong>{' '}
+                        in Safari's Develop menu
+                      </li>
+                    </ol>
+                  ) : browser === 'firefox' ? (
+                    <ol className="text-text-secondary text-sm space-y-2 list-decimal list-inside mb-4">
+                      <li>Click <strong className="text-text-primary">Open Firefox Add-ons</strong> below</li>
+                      <li>Click <strong className="text-text-primary">Load Temporary Add-on...</strong></li>
+                      <li>Select <strong className="text-text-primary">manifest.json</strong> in the folder above</li>
+                    </ol>
+                  ) : (
+                    <ol className="text-text-secondary text-sm space-y-2 list-decimal list-inside mb-4">
+                      <li>Click <strong className="text-text-primary">Open Extensions Page</strong> below</li>
+                      <li>Enable <strong className="text-text-primary">Developer mode</strong> (top right toggle)</li>
+                      <li>Click <strong className="text-text-primary">Load unpacked</strong></li>
+                      <li>Paste the path above or navigate to the folder</li>
+                    </ol>
+                  )}
                   <div className="flex gap-3">
                     <button
-                      onClick={openChromeExtensions}
+                      onClick={
+                        browser === 'safari'
+                          ? openSafariExtensionsPreferences
+                          : browser === 'firefox'
+                            ? openFirefoxAddons
+                            : openBrowserExtensions
+                      }
                       className="flex-1 py-2 px-4 bg-blue-600 hover:bg-blue-700 text-white font-medium rounded-lg transition-colors"
                     >
-                      Open Chrome Extensions
+                      {browser === 'safari'
+                        ? 'Open Safari Settings'
+                        : browser === 'firefox'
+                          ? 'Open Firefox Add-ons'
+                          : 'Open Extensions Page'}
                     </button>
                     <button
                       onClick={openExtensionFolder}
@@ -189,6 +445,22 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
                       Open Folder
                     </button>
                   </div>
+                  <div className="flex gap-4">
+                    {(['chrome', 'firefox', 'safari'] as const)
+                      .filter((other) => other !== browser && (other !== 'safari' || hasSafari))
+                      .map((other) => (
+                        <button
+                          key={other}
+                          onClick={() => extractExtension(other)}
+                          disabled={isExtracting}
+                          className="mt-3 text-sm text-text-tertiary hover:text-text-primary disabled:opacity-50 transition-colors"
+                        >
+                          Using {BROWSER_NAMES[other]} instead?
+                        </button>
+                      ))}
+                  </div>
+                  {error && <p className="text-red-400 text-sm mt-2">{error}</p>}
+                  {openPageError && <p className="text-red-400 text-sm mt-2">{openPageError}</p>}
                 </div>
               </div>
 
@@ -210,7 +482,11 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
                   Back
                 </button>
                 <button
-                  onClick={() => setStep(3)}
+                  onClick={() => {
+                    completeStep('loadExtension');
+                    checkItem('extensionLoaded');
+                    setStep(3);
+                  }}
                   className="flex-1 py-3 px-4 bg-blue-600 hover:bg-blue-700 text-white font-semibold rounded-lg transition-colors"
                 >
                   I've loaded the extension
@@ -239,7 +515,9 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
                   </svg>
                 </div>
                 <div>
-                  <h3 className="text-lg font-semibold text-text-primary">Loaded in Chrome</h3>
+                  <h3 className="text-lg font-semibold text-text-primary">
+                    Loaded in {BROWSER_NAMES[browser]}
+                  </h3>
                 </div>
               </div>
 
@@ -261,11 +539,50 @@ export function SetupWizard({ onComplete }: SetupWizardProps) {
                 </div>
               </div>
 
+              {browser !== 'safari' && (
+                <div className="bg-bg-tertiary rounded-lg p-3 text-sm">
+                  {linkedInSession?.status === 'consentRequired' ? (
+                    <div className="space-y-2">
+                      <p className="text-text-secondary">
+                        ClaudIn can check your browser's cookies for a LinkedIn sign-in. It only looks for the
+                        cookie, never reads it.
+                      </p>
+                      <button
+                        onClick={() => checkLinkedInSession(true)}
+                        className="py-1.5 px-3 bg-blue-600 hover:bg-blue-700 text-white font-medium rounded-lg transition-colors"
+                      >
+                        Allow and check
+                      </button>
+                    </div>
+                  ) : linkedInSession?.status === 'signedIn' ? (
+                    <p className="text-green-400">You're signed in to LinkedIn.</p>
+                  ) : linkedInSession?.status === 'signedOut' ? (
+                    <p className="text-amber-300">Sign in to LinkedIn in your browser before you continue.</p>
+                  ) : linkedInSession?.status === 'unknown' ? (
+                    <p className="text-text-secondary">
+                      ClaudIn couldn't check your LinkedIn sign-in ({linkedInSession.reason}). Make sure you're signed in.
+                    </p>
+                  ) : (
+                    <button
+                      onClick={() => checkLinkedInSession()}
+                      className="text-text-tertiary hover:text-text-primary transition-colors"
+                    >
+                      Check that I'm signed in to LinkedIn
+                    </button>
+                  )}
+                </div>
+              )}
+
+              {serverError && (
+                <p className="text-red-400 text-sm">{serverError}</p>
+              )}
+
               <button
                 onClick={completeSetup}
-                className="w-full py-3 px-4 bg-green-600 hover:bg-green-700 text-white font-semibold rounded-lg transition-colors"
+                disabled={isWaitingForServer}
+                className="w-full py-3 px-4 bg-green-600 hover:bg-green-700 disabled:opacity-50 disabled:cursor-not-allowed text-white font-semibold rounded-lg transition-colors"
               >
-                Start Using ClaudIn
+                {isWaitingForServer ? 'Waiting for the server...' : 'Start Using ClaudIn'}
               </button>
             </div>
           )}
@@ -1,5 +1,17 @@
+import { invoke } from '@tauri-apps/api/core';
+
 const API_BASE = 'http://localhost:3847/api';
 
+let bridgeToken: Promise<string> | null = null;
+
+/** Calls the local server with the token it requires from the app. */
+export async function apiFetch(url: string, init: RequestInit = {}): Promise<Response> {
+  bridgeToken ??= invoke<string>('get_bridge_token');
+  const headers = new Headers(init.headers);
+  headers.set('Authorization', `Bearer ${await bridgeToken}`);
+  return fetch(url, { ...init, headers });
+}
+
 export interface SendMessageCallbacks {
   onText: (text: string) => void;
   onToolStart?: (name: string, input: unknown) => void;
@@ -9,19 +21,19 @@ export interface SendMessageCallbacks {
 }
 
 export async function fetchConversations() {
-  const res = await fetch(`${API_BASE}/chat/conversations`);
+  const res = await apiFetch(`${API_BASE}/chat/conversations`);
   if (!res.ok) throw new Error('Failed to fetch conversations');
   return res.json();
 }
 
 export async function fetchConversation(id: string) {
-  const res = await fetch(`${API_BASE}/chat/conversations/${id}`);
+  const res = await apiFetch(`${API_BASE}/chat/conversations/${id}`);
   if (!res.ok) throw new Error('Failed to fetch conversation');
   return res.json();
 }
 
 export async function createConversation(title?: string) {
-  const res = await fetch(`${API_BASE}/chat/conversations`, {
+  const res = await apiFetch(`${API_BASE}/chat/conversations`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify({ title }),
@@ -31,7 +43,7 @@ export async function createConversation(title?: string) {
 }
 
 export async function renameConversation(id: string, title: string) {
-  const res = await fetch(`${API_BASE}/chat/conversations/${id}`, {
+  const res = await apiFetch(`${API_BASE}/chat/conversations/${id}`, {
     method: 'PATCH',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify({ title }),
@@ -41,7 +53,7 @@ export async function renameConversation(id: string, title: string) {
 }
 
 export async function deleteConversation(id: string) {
-  const res = await fetch(`${API_BASE}/chat/conversations/${id}`, {
+  const res = await apiFetch(`${API_BASE}/chat/conversations/${id}`, {
     method: 'DELETE',
   });
   if (!res.ok) throw new Error('Failed to delete conversation');
@@ -53,7 +65,7 @@ export async function sendMessage(
   message: string,
   callbacks: SendMessageCallbacks
 ) {
-  const res = await fetch(
+  const res = await apiFetch(
     `${API_BASE}/chat/conversations/${conversationId}/messages`,
     {
       method: 'POST',
@@ -115,7 +127,7 @@ export async function sendMessage(
 }
 
 export async function fetchStats() {
-  const res = await fetch(`${API_BASE}/stats`);
+  const res = await apiFetch(`${API_BASE}/stats`);
   if (!res.ok) throw new Error('Failed to fetch stats');
   return res.json();
 }
@@ -127,19 +139,19 @@ export async function searchProfiles(query: string, filters?: Record<string, unk
       if (value) params.append(key, String(value));
     });
   }
-  const res = await fetch(`${API_BASE}/profiles/search?${params}`);
+  const res = await apiFetch(`${API_BASE}/profiles/search?${params}`);
   if (!res.ok) throw new Error('Failed to search profiles');
   return res.json();
 }
 
 export async function fetchSettingsStatus(): Promise<{ configured: boolean; hasApiKey: boolean }> {
-  const res = await fetch(`${API_BASE}/settings/status`);
+  const res = await apiFetch(`${API_BASE}/settings/status`);
   if (!res.ok) throw new Error('Failed to fetch settings status');
   return res.json();
 }
 
 export async function updateSettings(settings: { openrouter_api_key?: string }): Promise<void> {
-  const res = await fetch(`${API_BASE}/settings`, {
+  const res = await apiFetch(`${API_BASE}/settings`, {
     method: 'PUT',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify(settings),
@@ -173,7 +185,7 @@ export async function fetchCRMProfiles(params: { search?: string; limit?: number
   if (params.limit) query.append('limit', String(params.limit));
   if (params.offset) query.append('offset', String(params.offset));
 
-  const res = await fetch(`${API_BASE}/profiles/crm/list?${query.toString()}`);
+  const res = await apiFetch(`${API_BASE}/profiles/crm/list?${query.toString()}`);
   if (!res.ok) throw new Error('Failed to fetch CRM profiles');
   return res.json() as Promise<CRMResponse>;
 }
@@ -208,13 +220,13 @@ export async function fetchPosts(params: { search?: string; limit?: number; offs
   if (params.limit) query.append('limit', String(params.limit));
   if (params.offset) query.append('offset', String(params.offset));
 
-  const res = await fetch(`${API_BASE}/profiles/posts/list?${query.toString()}`);
+  const res = await apiFetch(`${API_BASE}/profiles/posts/list?${query.toString()}`);
   if (!res.ok) throw new Error('Failed to fetch posts');
   return res.json() as Promise<PostsResponse>;
 }
 
 export async function queueProfileEnrichment(publicIdentifier: string): Promise<{ success: boolean }> {
-  const res = await fetch(`${API_BASE}/enrich/queue`, {
+  const res = await apiFetch(`${API_BASE}/enrich/queue`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify({ publicIdentifier }),
@@ -224,7 +236,7 @@ export async function queueProfileEnrichment(publicIdentifier: string): Promise<
 }
 
 export async function queueBulkEnrichment(identifiers: string[]): Promise<{ queued: number }> {
-  const res = await fetch(`${API_BASE}/enrich/queue/bulk`, {
+  const res = await apiFetch(`${API_BASE}/enrich/queue/bulk`, {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
     body: JSON.stringify({ identifiers }),
@@ -242,7 +254,7 @@ export interface EnrichmentStatus {
 }
 
 export async function fetchEnrichmentStatus(): Promise<EnrichmentStatus> {
-  const res = await fetch(`${API_BASE}/enrich/status`);
+  const res = await apiFetch(`${API_BASE}/enrich/status`);
   if (!res.ok) throw new Error('Failed to fetch enrichment status');
   return res.json();
 }
@@ -269,13 +281,13 @@ export async function fetchEnrichmentQueue(status?: string, limit?: number): Pro
   if (status) params.append('status', status);
   if (limit) params.append('limit', String(limit));
   
-  const res = await fetch(`${API_BASE}/enrich/queue/list?${params.toString()}`);
+  const res = await apiFetch(`${API_BASE}/enrich/queue/list?${params.toString()}`);
   if (!res.ok) throw new Error('Failed to fetch enrichment queue');
   return res.json();
 }
 
 export async function clearEnrichmentQueue(): Promise<{ success: boolean }> {
-  const res = await fetch(`${API_BASE}/enrich/queue`, { method: 'DELETE' });
+  const res = await apiFetch(`${API_BASE}/enrich/queue`, { method: 'DELETE' });
   if (!res.ok) throw new Error('Failed to clear enrichment queue');
   return res.json();
 }
@@ -343,7 +355,7 @@ export interface ProfileDetailResponse {
 }
 
 export async function fetchProfileDetail(id: string): Promise<ProfileDetailResponse> {
-  const res = await fetch(`${API_BASE}/profiles/${id}/detail`);
+  const res = await apiFetch(`${API_BASE}/profiles/${id}/detail`);
   if (!res.ok) throw new Error('Failed to fetch profile detail');
   return res.json();
 }
@@ -11,6 +11,7 @@ import type {
   SearchScrapeEvent,
   ScrapeEvent 
 } from '@claudin/shared';
+import { SERVER_URL, serverFetch } from '../shared/server';
 
 interface MessagesScrapeEvent extends ScrapeEvent {
   type: 'messages';
@@ -51,7 +52,6 @@ const SYNC_INTERVAL_MINUTES = 5;
 const FEED_REFRESH_INTERVAL_MINUTES = 15;
 const ENRICHMENT_CHECK_INTERVAL_MINUTES = 1;
 
-const SERVER_URL = 'http://localhost:3847/api';
 
 const profilesCache = new Map<string, SyncedProfile>();
 const messagesCache = new Map<string, SyncedMessage>();
@@ -302,7 +302,7 @@ async function processEnrichmentQueue() {
   try {
     isEnrichmentRunning = true;
     
-    const res = await fetch(`${SERVER_URL}/enrich/next`);
+    const res = await serverFetch(`${SERVER_URL}/enrich/next`);
     if (!res.ok) {
       console.log('[ClaudIn] Failed to fetch enrichment task');
       return;
@@ -353,7 +353,7 @@ async function processEnrichmentQueue() {
 
 async function markEnrichmentComplete(publicIdentifier: string, success: boolean, error?: string) {
   try {
-    await fetch(`${SERVER_URL}/enrich/complete`, {
+    await serverFetch(`${SERVER_URL}/enrich/complete`, {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
       body: JSON.stringify({ publicIdentifier, success, error }),
@@ -412,7 +412,7 @@ async function syncToServer(): Promise<{ success: boolean; profiles?: number; me
     const now = new Date().toISOString();
     
     if (unsyncedProfiles.length > 0) {
-      const res = await fetch(`${SERVER_URL}/sync/profiles`, {
+      const res = await serverFetch(`${SERVER_URL}/sync/profiles`, {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ profiles: unsyncedProfiles }),
@@ -440,7 +440,7 @@ async function syncToServer(): Promise<{ success: boolean; profiles?: number; me
     }
     
     if (unsyncedMessages.length > 0) {
-      const res = await fetch(`${SERVER_URL}/sync/messages`, {
+      const res = await serverFetch(`${SERVER_URL}/sync/messages`, {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ messages: unsyncedMessages }),
@@ -468,7 +468,7 @@ async function syncToServer(): Promise<{ success: boolean; profiles?: number; me
     }
     
     if (unsyncedPosts.length > 0) {
-      const res = await fetch(`${SERVER_URL}/sync/posts`, {
+      const res = await serverFetch(`${SERVER_URL}/sync/posts`, {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ posts: unsyncedPosts }),
@@ -1,4 +1,4 @@
-const SERVER_URL = 'http://localhost:3847/api';
+import { SERVER_URL, serverFetch } from '../shared/server';
 
 interface SyncedItem {
   syncedAt: string | null;
@@ -77,7 +77,7 @@ async function updateStats() {
 
 async function updateQueueStatus() {
   try {
-    const res = await fetch(`${SERVER_URL}/enrich/status`);
+    const res = await serverFetch(`${SERVER_URL}/enrich/status`);
     if (!res.ok) return;
     
     const { pending, processing, completed, total } = await res.json();
@@ -0,0 +1,43 @@
+/**
+ * Requests to the local ClaudIn server, which wants the token it shares with
+ * the desktop app on every API call. The app hands it out through its native
+ * messaging host.
+ */
+
+export const SERVER_URL = 'http://localhost:3847/api';
+
+const NATIVE_HOST = 'app.claudin.host';
+const TOKEN_KEY = 'claudin_bridge_token';
+
+async function requestBridgeToken(): Promise<string | null> {
+  try {
+    const response = await chrome.runtime.sendNativeMessage(NATIVE_HOST, { type: 'getBridgeToken' });
+    return response?.type === 'bridgeToken' ? response.token : null;
+  } catch (error) {
+    console.log('[ClaudIn] Could not reach the desktop app for the server token', error);
+    return null;
+  }
+}
+
+async function bridgeToken(refresh: boolean): Promise<string | null> {
+  if (!refresh) {
+    const stored = await chrome.storage.session.get(TOKEN_KEY);
+    if (stored[TOKEN_KEY]) return stored[TOKEN_KEY] as string;
+  }
+  const token = await requestBridgeToken();
+  if (token) await chrome.storage.session.set({ [TOKEN_KEY]: token });
+  return token;
+}
+
+async function send(url: string, init: RequestInit, refresh: boolean): Promise<Response> {
+  const headers = new Headers(init.headers);
+  const token = await bridgeToken(refresh);
+  if (token) headers.set('Authorization', `Bearer ${token}`);
+  return fetch(url, { ...init, headers });
+}
+
+/** Like `fetch`, asking the app for a new token once if the server rejects the stored one. */
+export async function serverFetch(url: string, init: RequestInit = {}): Promise<Response> {
+  const res = await send(url, init, false);
+  return res.status === 401 ? send(url, init, true) : res;
+}
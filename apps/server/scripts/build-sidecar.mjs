@@ -0,0 +1,59 @@
+/**
+ * Compiles the server into a standalone executable that the desktop app
+ * ships as a Tauri sidecar, so end users don't need Node installed.
+ *
+ * Tauri looks sidecars up by target triple, e.g.
+ * `binaries/claudin-server-aarch64-apple-darwin`.
+ */
+
+import { execFileSync } from 'child_process';
+import { mkdirSync } from 'fs';
+import { dirname, join } from 'path';
+import { fileURLToPath } from 'url';
+
+const SERVER_DIR = join(dirname(fileURLToPath(import.meta.url)), '..');
+const OUT_DIR = join(SERVER_DIR, '..', 'desktop', 'src-tauri', 'binaries');
+
+const BUN_TARGETS = {
+  'aarch64-apple-darwin': 'bun-darwin-arm64',
+  'x86_64-apple-darwin': 'bun-darwin-x64',
+  'x86_64-unknown-linux-gnu': 'bun-linux-x64',
+  'aarch64-unknown-linux-gnu': 'bun-linux-arm64',
+  'x86_64-pc-windows-msvc': 'bun-windows-x64',
+};
+
+function hostTriple() {
+  const output = execFileSync('rustc', ['-vV']).toString();
+  const match = output.match(/^host: (\S+)$/m);
+  if (!match) throw new Error('Could not determine the Rust host target triple');
+  return match[1];
+}
+
+function outPath(triple) {
+  const ext = triple.includes('windows') ? '.exe' : '';
+  return join(OUT_DIR, `claudin-server-${triple}${ext}`);
+}
+
+function compile(triple) {
+  const bunTarget = BUN_TARGETS[triple];
+  if (!bunTarget) throw new Error(`Unsupported sidecar target: ${triple}`);
+
+  console.log(`Compiling server sidecar for ${triple}...`);
+  execFileSync(
+    'bun',
+    ['build', 'src/index.ts', '--compile', `--target=${bunTarget}`, `--outfile=${outPath(triple)}`],
+    { cwd: SERVER_DIR, stdio: 'inherit' },
+  );
+}
+
+const triple = process.env.TAURI_TARGET_TRIPLE ?? hostTriple();
+mkdirSync(OUT_DIR, { recursive: true });
+
+if (triple === 'universal-apple-darwin') {
+  // Each per-arch cargo build needs its own binary, the bundle the fat one.
+  const parts = ['aarch64-apple-darwin', 'x86_64-apple-darwin'];
+  parts.forEach(compile);
+  execFileSync('lipo', ['-create', '-output', outPath(triple), ...parts.map(outPath)], { stdio: 'inherit' });
+} else {
+  compile(triple);
+}
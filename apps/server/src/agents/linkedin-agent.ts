@@ -6,10 +6,11 @@ import type { ChatMessage } from '@claudin/shared';
 const MODEL = 'anthropic/claude-sonnet-4.5';
 
 function getClient(): OpenAI {
-  const apiKey = getSetting('openrouter_api_key') || process.env.OPENROUTER_API_KEY || '';
+  // The desktop app passes the key it keeps in the OS keychain.
+  const apiKey = process.env.OPENROUTER_API_KEY || getSetting('openrouter_api_key') || '';
   return new OpenAI({
     apiKey,
-    baseURL: 'https://openrouter.ai/api/v1',
+    baseURL: process.env.CLAUDIN_API_BASE_URL || 'https://openrouter.ai/api/v1',
   });
 }
 
@@ -0,0 +1,81 @@
+/**
+ * At-rest encryption of the database
+ * The desktop app keeps the key in the OS's credential store and passes it
+ * in CLAUDIN_DATA_KEY. With encryption on, the database lives in memory and
+ * is written to disk as a single AES-256-GCM encrypted file.
+ */
+
+import Database from 'better-sqlite3';
+import { createCipheriv, createDecipheriv, randomBytes } from 'node:crypto';
+import { renameSync, writeFileSync } from 'node:fs';
+
+// Identifies the file format, in case it ever changes
+const MAGIC = Buffer.from('CLAUDIN\x01');
+const IV_LENGTH = 12;
+const TAG_LENGTH = 16;
+
+// How often changes made since the last save are written out
+const SAVE_INTERVAL_MS = 5000;
+
+export function dataKey(): Buffer | null {
+  const hex = process.env.CLAUDIN_DATA_KEY;
+  if (!hex) return null;
+  const key = Buffer.from(hex, 'hex');
+  if (key.length !== 32) throw new Error('CLAUDIN_DATA_KEY must be 32 bytes of hex');
+  return key;
+}
+
+export function encrypt(plain: Buffer, key: Buffer): Buffer {
+  const iv = randomBytes(IV_LENGTH);
+  const cipher = createCipheriv('aes-256-gcm', key, iv);
+  cipher.setAAD(MAGIC);
+  const body = Buffer.concat([cipher.update(plain), cipher.final()]);
+  return Buffer.concat([MAGIC, iv, cipher.getAuthTag(), body]);
+}
+
+export function decrypt(data: Buffer, key: Buffer): Buffer {
+  if (!data.subarray(0, MAGIC.length).equals(MAGIC)) {
+    throw new Error('Not an encrypted ClaudIn database');
+  }
+  const iv = data.subarray(MAGIC.length, MAGIC.length + IV_LENGTH);
+  const tag = data.subarray(MAGIC.length + IV_LENGTH, MAGIC.length + IV_LENGTH + TAG_LENGTH);
+  const decipher = createDecipheriv('aes-256-gcm', key, iv);
+  decipher.setAAD(MAGIC);
+  decipher.setAuthTag(tag);
+  return Buffer.concat([decipher.update(data.subarray(MAGIC.length + IV_LENGTH + TAG_LENGTH)), decipher.final()]);
+}
+
+/**
+ * Writes the database through a temporary file so a crash mid-write
+ * leaves the previous version in place.
+ */
+export function saveEncrypted(db: Database.Database, path: string, key: Buffer) {
+  const tmp = `${path}.tmp`;
+  writeFileSync(tmp, encrypt(db.serialize(), key), { mode: 0o600 });
+  renameSync(tmp, path);
+}
+
+/**
+ * Saves the in-memory database whenever it changed. Returns the save, which
+ * the server runs once more before it exits.
+ */
+export function persistEncrypted(db: Database.Database, path: string, key: Buffer): () => void {
+  const changes = () => (db.prepare('SELECT total_changes() AS n').get() as { n: number }).n;
+  let saved = changes();
+  const save = () => {
+    const now = changes();
+    if (now === saved) return;
+    saveEncrypted(db, path, key);
+    saved = now;
+  };
+
+  setInterval(() => {
+    try {
+      save();
+    } catch (error) {
+      console.error('Failed to save the encrypted database:', error);
+    }
+  }, SAVE_INTERVAL_MS).unref();
+
+  return save;
+}
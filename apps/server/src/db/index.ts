@@ -4,15 +4,21 @@
  */
 
 import Database from 'better-sqlite3';
-import { existsSync, mkdirSync } from 'fs';
+import { existsSync, mkdirSync, readFileSync, rmSync } from 'fs';
 import { join } from 'path';
 import { homedir } from 'os';
+import { dataKey, decrypt, persistEncrypted, saveEncrypted } from './encryption.js';
 
-// Store DB in user's data directory
-const DATA_DIR = join(homedir(), '.claudin');
+// Store DB in user's data directory, unless the desktop app runs this
+// server for a separate profile and hands it its own directory
+const DATA_DIR = process.env.CLAUDIN_DATA_DIR || join(homedir(), '.claudin');
 const DB_PATH = join(DATA_DIR, 'claudin.db');
+// Where the database is kept instead when the desktop app turns on encryption
+const ENCRYPTED_DB_PATH = join(DATA_DIR, 'claudin.db.enc');
 
 let db: Database.Database;
+// Writes out the encrypted database's last changes, when encryption is on
+let flushEncrypted: (() => void) | undefined;
 
 export function getDb(): Database.Database {
   if (!db) {
@@ -38,16 +44,22 @@ export function withTransactionSync<T>(fn: () => T): T {
   return withTransaction(fn);
 }
 
+/**
+ * Saves pending changes and closes the database, before the server exits.
+ */
+export function closeDatabase() {
+  if (!db) return;
+  flushEncrypted?.();
+  db.close();
+}
+
 export function initDatabase(): Database.Database {
   // Ensure data directory exists
   if (!existsSync(DATA_DIR)) {
     mkdirSync(DATA_DIR, { recursive: true });
   }
 
-  console.log(`Database path: ${DB_PATH}`);
-  
-  db = new Database(DB_PATH);
-  db.pragma('journal_mode = WAL'); // Better performance
+  db = process.env.CLAUDIN_ENCRYPT_DATA === '1' ? openEncrypted() : openPlain();
   
   // Create tables
   db.exec(`
@@ -212,10 +224,63 @@ export function initDatabase(): Database.Database {
   // Run versioned migrations
   runMigrations();
 
+  // A new encrypted database is saved right away, so it exists on disk even
+  // if nothing gets written to it
+  if (process.env.CLAUDIN_ENCRYPT_DATA === '1' && !existsSync(ENCRYPTED_DB_PATH)) {
+    saveEncrypted(db, ENCRYPTED_DB_PATH, dataKey()!);
+  }
+
   console.log('Database initialized');
   return db;
 }
 
+function openPlain(): Database.Database {
+  // Encryption was turned off since the last start: decrypt the database
+  // back to a regular file
+  if (existsSync(ENCRYPTED_DB_PATH) && !existsSync(DB_PATH)) {
+    const key = dataKey();
+    if (!key) throw new Error('The database is encrypted but no key was given');
+    const encrypted = new Database(decrypt(readFileSync(ENCRYPTED_DB_PATH), key));
+    encrypted.exec(`VACUUM INTO '${DB_PATH.replace(/'/g, "''")}'`);
+    encrypted.close();
+    rmSync(ENCRYPTED_DB_PATH);
+    console.log('Decrypted the database');
+  }
+
+  console.log(`Database path: ${DB_PATH}`);
+  const plain = new Database(DB_PATH);
+  plain.pragma('journal_mode = WAL'); // Better performance
+  return plain;
+}
+
+function openEncrypted(): Database.Database {
+  const key = dataKey();
+  if (!key) throw new Error('Database encryption is on but no key was given');
+
+  console.log(`Database path: ${ENCRYPTED_DB_PATH}`);
+  let memory: Database.Database;
+  if (existsSync(ENCRYPTED_DB_PATH)) {
+    memory = new Database(decrypt(readFileSync(ENCRYPTED_DB_PATH), key));
+  } else if (existsSync(DB_PATH)) {
+    // Encryption was just turned on: encrypt the existing database, then
+    // remove the plain copy along with its WAL files
+    const plain = new Database(DB_PATH);
+    plain.pragma('wal_checkpoint(TRUNCATE)');
+    memory = new Database(plain.serialize());
+    plain.close();
+    saveEncrypted(memory, ENCRYPTED_DB_PATH, key);
+    for (const suffix of ['', '-wal', '-shm']) {
+      rmSync(`${DB_PATH}${suffix}`, { force: true });
+    }
+    console.log('Encrypted the database');
+  } else {
+    memory = new Database(':memory:');
+  }
+
+  flushEncrypted = persistEncrypted(memory, ENCRYPTED_DB_PATH, key);
+  return memory;
+}
+
 /**
  * Run database migrations based on version tracking.
  * Each migration runs only once.
@@ -7,7 +7,10 @@ import { serve } from '@hono/node-server';
 import { Hono } from 'hono';
 import { cors } from 'hono/cors';
 import { logger } from 'hono/logger';
-import { initDatabase } from './db/index.js';
+import { timingSafeEqual } from 'node:crypto';
+import { readFileSync } from 'node:fs';
+import { createServer } from 'node:https';
+import { closeDatabase, initDatabase } from './db/index.js';
 import { profilesRouter } from './routes/profiles.js';
 import { chatRouter } from './routes/chat.js';
 import { statsRouter } from './routes/stats.js';
@@ -15,31 +18,135 @@ import { syncRouter } from './routes/sync.js';
 import { settingsRouter } from './routes/settings.js';
 import { enrichRouter } from './routes/enrich.js';
 
+const SERVER_VERSION = '0.1.0';
+// Bump on breaking changes to the local API; the desktop app checks it
+const PROTOCOL_VERSION = 1;
+
 const app = new Hono();
 
 // Middleware
-app.use('*', logger());
+// Request logging is on unless the desktop app asked for warn/error only
+const LOG_LEVEL = process.env.CLAUDIN_LOG_LEVEL || 'info';
+if (LOG_LEVEL === 'info' || LOG_LEVEL === 'debug') {
+  app.use('*', logger());
+}
+
+// Request counter reported to the desktop app through /metrics
+let requestsServed = 0;
+app.use('*', async (_c, next) => {
+  requestsServed++;
+  await next();
+});
+// The desktop app passes a token it shares with the extension, which every
+// API request has to carry so other local processes can't use the API
+const BRIDGE_TOKEN = process.env.CLAUDIN_BRIDGE_TOKEN;
+function hasBridgeToken(header: string | undefined): boolean {
+  const expected = Buffer.from(`Bearer ${BRIDGE_TOKEN}`);
+  const actual = Buffer.from(header ?? '');
+  return actual.length === expected.length && timingSafeEqual(actual, expected);
+}
+
+// The desktop app passes the extension's origins, its own and the ones the
+// user approved; without it only the dev servers and Tauri are allowed. It
+// updates them through /control/allowed-origins when the extension changes
+let allowedOrigins = process.env.CLAUDIN_ALLOWED_ORIGINS
+  ? process.env.CLAUDIN_ALLOWED_ORIGINS.split(',').filter(Boolean)
+  : ['http://localhost:5173', 'http://localhost:1420', 'tauri://localhost'];
+// Firefox and Safari give each install of the extension a random id, so their
+// origins can't be listed up front; they're let in when they carry the token
+const EXTENSION_SCHEMES = ['moz-extension://', 'safari-web-extension://'];
+function isExtensionOrigin(origin: string): boolean {
+  return EXTENSION_SCHEMES.some((scheme) => origin.startsWith(scheme));
+}
 app.use('*', cors({
-  origin: (origin) => {
-    // Allow localhost dev servers and Tauri
-    const allowedOrigins = ['http://localhost:5173', 'http://localhost:1420', 'tauri://localhost'];
-    if (allowedOrigins.includes(origin)) return origin;
-    // Allow any Chrome extension
-    if (origin?.startsWith('chrome-extension://')) return origin;
-    return null;
-  },
+  origin: (origin) => (allowedOrigins.includes(origin) || isExtensionOrigin(origin) ? origin : null),
   credentials: true,
 }));
+// CORS only hides responses from other origins, so refuse their requests
+// outright; requests without an Origin don't come from a web page
+app.use('*', async (c, next) => {
+  const origin = c.req.header('Origin');
+  if (origin && !allowedOrigins.includes(origin)) {
+    const tokenChecked = c.req.method === 'OPTIONS'
+      || (BRIDGE_TOKEN !== undefined && hasBridgeToken(c.req.header('Authorization')));
+    if (!isExtensionOrigin(origin) || !tokenChecked) {
+      return c.json({ error: 'Origin not allowed' }, 403);
+    }
+  }
+  await next();
+});
+
+if (BRIDGE_TOKEN) {
+  app.use('/api/*', async (c, next) => {
+    if (c.req.method !== 'OPTIONS' && !hasBridgeToken(c.req.header('Authorization'))) {
+      return c.json({ error: 'Unauthorized' }, 401);
+    }
+    await next();
+  });
+}
+
+// Lets the desktop app update the allowed origins without a restart
+app.put('/control/allowed-origins', async (c) => {
+  if (!BRIDGE_TOKEN || !hasBridgeToken(c.req.header('Authorization'))) {
+    return c.json({ error: 'Unauthorized' }, 401);
+  }
+  const body = await c.req.json().catch(() => null);
+  const origins = body?.origins;
+  if (!Array.isArray(origins) || !origins.every((origin) => typeof origin === 'string')) {
+    return c.json({ error: 'Expected { origins: string[] }' }, 400);
+  }
+  allowedOrigins = origins.filter(Boolean);
+  return c.json({ origins: allowedOrigins });
+});
+
+// Saves the database and exits
+function shutdown() {
+  try {
+    closeDatabase();
+  } catch (error) {
+    console.error('Failed to close the database:', error);
+  }
+  process.exit(0);
+}
+for (const signal of ['SIGINT', 'SIGTERM'] as const) {
+  process.on(signal, shutdown);
+}
+
+// Windows can't send SIGTERM, so the desktop app asks here instead
+app.post('/control/shutdown', (c) => {
+  if (!BRIDGE_TOKEN || !hasBridgeToken(c.req.header('Authorization'))) {
+    return c.json({ error: 'Unauthorized' }, 401);
+  }
+  // Leave time for the response to go out
+  setTimeout(shutdown, 100);
+  return c.json({ status: 'shutting down' }, 202);
+});
+
+// Diagnostics for the desktop app
+app.get('/metrics', (c) => {
+  return c.json({
+    requestsServed,
+    uptimeSeconds: Math.round(process.uptime()),
+  });
+});
 
 // Health check
 app.get('/', (c) => {
   return c.json({ 
     name: 'ClaudIn Server',
-    version: '0.1.0',
+    version: SERVER_VERSION,
     status: 'running',
   });
 });
 
+// Compatibility handshake with the desktop app
+app.get('/version', (c) => {
+  return c.json({
+    version: SERVER_VERSION,
+    protocolVersion: PROTOCOL_VERSION,
+  });
+});
+
 // API Routes
 app.route('/api/profiles', profilesRouter);
 app.route('/api/chat', chatRouter);
@@ -50,6 +157,8 @@ app.route('/api/enrich', enrichRouter);
 
 // Initialize database and start server
 const PORT = process.env.PORT ? parseInt(process.env.PORT) : 3847;
+// Set by the desktop app when https is enabled, alongside the http port
+const TLS_PORT = process.env.CLAUDIN_TLS_PORT ? parseInt(process.env.CLAUDIN_TLS_PORT) : undefined;
 
 async function main() {
   console.log('Initializing database...');
@@ -62,6 +171,19 @@ async function main() {
   });
   
   console.log(`ClaudIn server running at http://localhost:${PORT}`);
+
+  if (TLS_PORT && process.env.CLAUDIN_TLS_CERT && process.env.CLAUDIN_TLS_KEY) {
+    serve({
+      fetch: app.fetch,
+      port: TLS_PORT,
+      createServer,
+      serverOptions: {
+        cert: readFileSync(process.env.CLAUDIN_TLS_CERT),
+        key: readFileSync(process.env.CLAUDIN_TLS_KEY),
+      },
+    });
+    console.log(`ClaudIn server running at https://localhost:${TLS_PORT}`);
+  }
 }
 
 main().catch(console.error);
@@ -20,7 +20,7 @@ settingsRouter.get('/', (c) => {
 });
 
 settingsRouter.get('/status', (c) => {
-  const apiKey = getSetting('openrouter_api_key');
+  const apiKey = process.env.OPENROUTER_API_KEY || getSetting('openrouter_api_key');
   return c.json({ 
     configured: !!apiKey,
     hasApiKey: !!apiKey,